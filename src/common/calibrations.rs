@@ -1,6 +1,9 @@
 use fastrand::Rng;
 
-use crate::graphics::palette::DRAW_COLOR_B;
+use crate::{
+    common::types::{scalar, Scalar},
+    graphics::palette::DRAW_COLOR_B,
+};
 
 pub const RNG_SEED: u64 = 555;
 pub const MAX_ENEMIES: usize = 250;
@@ -14,18 +17,90 @@ pub const NEXT_LIFE_SCORE: u32 = 100_000;
 pub const RESPITE_DURATION: usize = 120;
 pub const DEATH_COUNTDOWN_DURATION: usize = 90;
 pub const SCORE_BOMB: u32 = 10;
+pub const SCORE_CHAIN_BOMB: u32 = 5;
+pub const SCORE_LINE_BOMB: u32 = 15;
+pub const SCORE_DELAYED_BOMB: u32 = 20;
+pub const SCORE_REPEL_BOMB: u32 = 5;
 pub const SCORE_ENEMY: u32 = 1;
+// Power-ups: at most MAX_POWERUPS at once, one every POWERUP_FRAME_FREQ
+// frames, waiting POWERUP_LIFE_SPAN frames to be picked up. Once picked up,
+// they last POWERUP_DURATION frames.
+pub const MAX_POWERUPS: usize = 2;
+pub const POWERUP_FRAME_FREQ: usize = 900;
+pub const POWERUP_LIFE_SPAN: u32 = 600;
+pub const POWERUP_DURATION: u32 = 600;
+// Magnet: reach, and how fast it pulls enemies of the player's color
+pub const MAGNET_RADIUS: Scalar = scalar(30.0);
+pub const MAGNET_PULL: Scalar = scalar(0.5);
+// Points of each enemy and bomb with the double-points power-up
+pub const DOUBLE_POINTS: u32 = 2;
 // Enemy color switch times
 pub const ENEMY_FRAME: [usize; DIFFICULTY_LEVELS] = [120, 60, 30, 25, 15, 10, 8, 6, 4, 2];
 // Enemy spawn times
 pub const EN_COL_FRAME: [usize; DIFFICULTY_LEVELS] = [240, 180, 160, 120, 100, 80, 60, 60, 60, 60];
+// Odds of the mixes below are out of MIX_TOTAL
+pub const MIX_TOTAL: u8 = 16;
+// Odds of an enemy being a dasher, an orbiter, a splitter, a
+// wanderer or a tank, by difficulty. The others are chasers.
+pub const ARCHETYPE_MIX: [[u8; 5]; DIFFICULTY_LEVELS] = [
+    [0, 0, 0, 0, 0],
+    [0, 0, 1, 1, 0],
+    [1, 0, 1, 1, 0],
+    [1, 1, 1, 1, 0],
+    [1, 1, 2, 1, 1],
+    [2, 1, 2, 1, 1],
+    [2, 2, 2, 1, 1],
+    [2, 2, 2, 2, 1],
+    [3, 2, 2, 2, 2],
+    [3, 3, 2, 2, 2],
+];
+// Odds of a bomb being a chain, a line, a delayed or a repel bomb, by
+// difficulty. The others are standard bombs.
+pub const BOMB_MIX: [[u8; 4]; DIFFICULTY_LEVELS] = [
+    [0, 0, 0, 0],
+    [2, 0, 2, 0],
+    [2, 2, 2, 0],
+    [2, 2, 2, 2],
+    [3, 2, 2, 2],
+    [3, 3, 2, 2],
+    [3, 3, 3, 2],
+    [3, 3, 3, 3],
+    [4, 3, 3, 3],
+    [4, 4, 3, 3],
+];
+// Arena (index in `LAYOUTS`) by difficulty, when arenas rotate
+pub const ARENA_ROTATION: [usize; DIFFICULTY_LEVELS] = [0, 0, 1, 1, 2, 2, 3, 3, 1, 2];
+// Health of the boss showing up when the game gets to a difficulty, 0 for
+// none. Absorbing an enemy of its color takes 1, a bomb wave BOSS_BOMB_DAMAGE.
+pub const BOSS_HEALTH: [u32; DIFFICULTY_LEVELS] = [0, 0, 0, 24, 0, 0, 36, 0, 0, 48];
+pub const BOSS_BOMB_DAMAGE: u32 = 4;
+// Points for defeating a boss, before the multiplier
+pub const SCORE_BOSS: u32 = 100;
 // Score to difficulty
 pub const DIFF_MUL_PROGRESSION: [u32; DIFFICULTY_LEVELS - 1] =
     [12, 30, 80, 120, 240, 320, 450, 1000, 2000];
 
+/// Index of the `odds` (out of MIX_TOTAL) that `rng` lands on, None for the
+/// rest. Doesn't draw from `rng` when all the odds are 0, so that games with
+/// nothing to pick from play as they always did.
+pub fn roll(rng: &Rng, odds: &[u8]) -> Option<usize> {
+    if odds.iter().all(|&odds| odds == 0) {
+        return None;
+    }
+    let mut roll = rng.u8(0..MIX_TOTAL);
+    for (n, &odds) in odds.iter().enumerate() {
+        if roll < odds {
+            return Some(n);
+        }
+        roll -= odds;
+    }
+    None
+}
+
 /// Calibrations impact the gameplay difficulty, randomness, when the player
 /// gets extra lives, etc...
 pub struct Calibrations {
+    pub seed: u64,
     pub difficulty: u32,
     pub score_next_life: u32,
     pub rng: Rng,
@@ -33,10 +108,16 @@ pub struct Calibrations {
 }
 impl Calibrations {
     pub fn new(tick_for_extra_rng: usize) -> Self {
+        Self::with_seed(RNG_SEED + tick_for_extra_rng as u64)
+    }
+
+    /// Same as `new` but with a known seed, e.g. to replay a game.
+    pub fn with_seed(seed: u64) -> Self {
         Self {
+            seed,
             difficulty: INIT_DIFFICULTY,
             score_next_life: NEXT_LIFE_SCORE,
-            rng: Rng::with_seed(RNG_SEED + tick_for_extra_rng as u64),
+            rng: Rng::with_seed(seed),
             enemy_color: DRAW_COLOR_B,
         }
     }
@@ -0,0 +1,182 @@
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use super::types::ScalarExt;
+
+/// Signed 16.16 fixed point number: 16 bits of integer part (enough for
+/// anything on a 160x160 screen, squares aside) and 16 bits of fraction.
+/// Arithmetic is plain integer arithmetic, so results are the same on any
+/// target and build.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Hash)]
+pub struct Fixed(i32);
+
+const FRACTION_BITS: u32 = 16;
+const ONE: i32 = 1 << FRACTION_BITS;
+
+impl Fixed {
+    /// Smallest positive value
+    pub const EPSILON: Fixed = Fixed(1);
+
+    /// Closest value to `value`, usable for constants.
+    pub const fn from_f64(value: f64) -> Self {
+        let scaled = value * ONE as f64;
+        if scaled < 0.0 {
+            Self((scaled - 0.5) as i32)
+        } else {
+            Self((scaled + 0.5) as i32)
+        }
+    }
+
+    pub fn abs(self) -> Self {
+        Self(self.0.abs())
+    }
+}
+
+/// Integer square root, rounded down (Newton's method).
+fn isqrt(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    // Start above the root, every step gets closer until it stops decreasing
+    let mut x = 1 << (64 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let next = (x + n / x) / 2;
+        if next >= x {
+            return x;
+        }
+        x = next;
+    }
+}
+
+impl ScalarExt for Fixed {
+    fn vector_length(self, other: Self) -> Self {
+        // Squares don't fit in 16.16, so compute on the raw values instead
+        let x = self.0.unsigned_abs() as u64;
+        let y = other.0.unsigned_abs() as u64;
+        Self(isqrt(x * x + y * y).min(i32::MAX as u64) as i32)
+    }
+
+    fn to_i32(self) -> i32 {
+        self.0 / ONE
+    }
+
+    #[cfg(test)]
+    fn to_f64(self) -> f64 {
+        self.0 as f64 / ONE as f64
+    }
+}
+
+impl From<i16> for Fixed {
+    fn from(value: i16) -> Self {
+        Self((value as i32) << FRACTION_BITS)
+    }
+}
+
+impl From<i32> for Fixed {
+    fn from(value: i32) -> Self {
+        Self(value << FRACTION_BITS)
+    }
+}
+
+impl From<u32> for Fixed {
+    fn from(value: u32) -> Self {
+        Self((value as i32) << FRACTION_BITS)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self(((self.0 as i64 * other.0 as i64) >> FRACTION_BITS) as i32)
+    }
+}
+
+impl Div for Fixed {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        Self((((self.0 as i64) << FRACTION_BITS) / other.0 as i64) as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Fixed::from_f64(1.5).0, 3 << 15);
+        assert_eq!(Fixed::from_f64(-0.25).0, -(1 << 14));
+        assert_eq!(Fixed::from(-3i32).to_f64(), -3.0);
+        assert_eq!(Fixed::from(160u32).to_i32(), 160);
+        // Rounds toward zero, like `as`
+        assert_eq!(Fixed::from_f64(-3.5).to_i32(), -3);
+        assert_eq!(Fixed::from_f64(7.99).to_i32(), 7);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Fixed::from_f64(2.5);
+        let b = Fixed::from_f64(-0.5);
+        assert_eq!(a + b, Fixed::from(2i32));
+        assert_eq!(a - b, Fixed::from(3i32));
+        assert_eq!(a * b, Fixed::from_f64(-1.25));
+        assert_eq!(a / b, Fixed::from(-5i32));
+        assert_eq!(-b, Fixed::from_f64(0.5));
+        assert_eq!(b.abs(), Fixed::from_f64(0.5));
+        assert_eq!(a.clamp(b, Fixed::from(1i32)), Fixed::from(1i32));
+    }
+
+    #[test]
+    fn integer_sqrt() {
+        for n in (0..100).chain([1 << 40, u32::MAX as u64, u64::MAX]) {
+            let root = isqrt(n) as u128;
+            assert!(root * root <= n as u128 && (root + 1) * (root + 1) > n as u128);
+        }
+    }
+
+    #[test]
+    fn vector_length_without_overflow() {
+        let (x, y) = (Fixed::from(300i32), Fixed::from(-400i32));
+        assert_eq!(x.vector_length(y), Fixed::from(500i32));
+        let one = Fixed::from(1i32);
+        let diagonal = one.vector_length(one).to_f64();
+        assert!((diagonal - std::f64::consts::SQRT_2).abs() < 1e-4);
+    }
+}
@@ -1,2 +1,5 @@
 pub mod calibrations;
+pub mod crc;
+#[cfg(feature = "fixed-point")]
+pub mod fixed;
 pub mod types;
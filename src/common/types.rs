@@ -1,24 +1,84 @@
 use std::ops::{Add, AddAssign, Sub};
 
+/// Number used for positions, sizes and speeds: `f64` by default, 16.16 fixed
+/// point with the `fixed-point` feature.
+#[cfg(not(feature = "fixed-point"))]
+pub type Scalar = f64;
+#[cfg(feature = "fixed-point")]
+pub type Scalar = super::fixed::Fixed;
+
+/// Turns a float (e.g. a constant) into a `Scalar`.
+#[cfg(not(feature = "fixed-point"))]
+pub const fn scalar(value: f64) -> Scalar {
+    value
+}
+#[cfg(feature = "fixed-point")]
+pub const fn scalar(value: f64) -> Scalar {
+    Scalar::from_f64(value)
+}
+
+/// Largest difference between the float and fixed point versions of a
+/// computation that's still considered the same result.
+#[cfg(all(test, not(feature = "fixed-point")))]
+pub const TOLERANCE: f64 = f64::EPSILON;
+#[cfg(all(test, feature = "fixed-point"))]
+pub const TOLERANCE: f64 = 1e-3;
+
+/// What `Scalar` can do on top of arithmetic, comparisons, `abs`, `sqrt` and
+/// conversions from integers, which both kinds of numbers have.
+pub trait ScalarExt {
+    /// Length of the (self, other) vector
+    fn vector_length(self, other: Self) -> Self;
+
+    /// Integer part, like `as i32`
+    fn to_i32(self) -> i32;
+
+    /// For tests to compare both versions against the same expected values
+    #[cfg(test)]
+    fn to_f64(self) -> f64;
+}
+
+impl ScalarExt for f64 {
+    fn vector_length(self, other: Self) -> Self {
+        (self.powi(2) + other.powi(2)).sqrt()
+    }
+
+    fn to_i32(self) -> i32 {
+        self as i32
+    }
+
+    #[cfg(test)]
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, PartialOrd, Debug, Default)]
 pub struct Coord {
-    pub x: f64,
-    pub y: f64,
+    pub x: Scalar,
+    pub y: Scalar,
 }
 impl Coord {
+    /// Coord from float constants
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self {
+            x: scalar(x),
+            y: scalar(y),
+        }
+    }
+
     /// Absolute distance between two points
-    pub fn distance_to(self, other: &Self) -> f64 {
-        let d_vect = self - *other;
-        (d_vect.x.powi(2) + d_vect.y.powi(2)).sqrt()
+    pub fn distance_to(self, other: &Self) -> Scalar {
+        (self - *other).norm()
     }
 
     /// Norm (length) of Coord vector
-    pub fn norm(self) -> f64 {
-        (self.x.powi(2) + self.y.powi(2)).sqrt()
+    pub fn norm(self) -> Scalar {
+        self.x.vector_length(self.y)
     }
 
     /// Scale Coordinate by a simple multiplication factor
-    pub fn scale(self, scale: f64) -> Coord {
+    pub fn scale(self, scale: Scalar) -> Coord {
         Self {
             x: self.x * scale,
             y: self.y * scale,
@@ -26,7 +86,7 @@ impl Coord {
     }
 
     /// Clamps all coordinates between two values (x-y independent)
-    pub fn clamp(self, min_x: f64, max_x: f64, min_y: f64, max_y: f64) -> Coord {
+    pub fn clamp(self, min_x: Scalar, max_x: Scalar, min_y: Scalar, max_y: Scalar) -> Coord {
         Self {
             x: self.x.clamp(min_x, max_x),
             y: self.y.clamp(min_y, max_y),
@@ -68,35 +128,37 @@ impl Sub for Coord {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use approx::assert_abs_diff_eq;
 
     #[test]
     fn distance_to() {
-        let a = Coord { x: 0.0, y: 0.0 };
-        let b = Coord { x: 3.0, y: 4.0 };
-        assert_eq!(a.distance_to(&b), 5.0);
+        let a = Coord::new(0.0, 0.0);
+        let b = Coord::new(3.0, 4.0);
+        assert_abs_diff_eq!(a.distance_to(&b).to_f64(), 5.0, epsilon = TOLERANCE);
     }
 
     #[test]
     fn norm() {
-        let a = Coord { x: 0.0, y: 0.0 };
-        let b = Coord { x: 3.0, y: 4.0 };
-        assert_eq!(a.norm(), 0.0);
-        assert_eq!(b.norm(), 5.0);
+        let a = Coord::new(0.0, 0.0);
+        let b = Coord::new(3.0, 4.0);
+        assert_abs_diff_eq!(a.norm().to_f64(), 0.0, epsilon = TOLERANCE);
+        assert_abs_diff_eq!(b.norm().to_f64(), 5.0, epsilon = TOLERANCE);
     }
 
     #[test]
     fn scale() {
-        let a = Coord { x: 0.0, y: 0.0 };
-        let b = Coord { x: 3.0, y: 4.0 };
-        assert_eq!(a.scale(2.0), a);
-        assert_eq!(b.scale(2.0), Coord { x: 6.0, y: 8.0 });
+        let a = Coord::new(0.0, 0.0);
+        let b = Coord::new(3.0, 4.0);
+        assert_eq!(a.scale(scalar(2.0)), a);
+        assert_eq!(b.scale(scalar(2.0)), Coord::new(6.0, 8.0));
     }
 
     #[test]
     fn clamp() {
-        let a = Coord { x: 0.0, y: 0.0 };
-        let b = Coord { x: 3.0, y: 4.0 };
-        assert_eq!(a.clamp(0.0, 1.0, 0.0, 1.0), a);
-        assert_eq!(b.clamp(0.0, 1.0, 0.0, 1.0), Coord { x: 1.0, y: 1.0 });
+        let a = Coord::new(0.0, 0.0);
+        let b = Coord::new(3.0, 4.0);
+        let (zero, one) = (scalar(0.0), scalar(1.0));
+        assert_eq!(a.clamp(zero, one, zero, one), a);
+        assert_eq!(b.clamp(zero, one, zero, one), Coord::new(1.0, 1.0));
     }
 }
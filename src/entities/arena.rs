@@ -0,0 +1,95 @@
+/// Fixed capacity storage for entities. Everything is allocated up front, and
+/// entities are kept (and iterated) in the order they were inserted, so the
+/// simulation doesn't depend on anything but the order things spawn in.
+pub struct Arena<T> {
+    items: Vec<T>,
+    capacity: usize,
+}
+
+impl<T> Arena<T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Adds an item after all the others. When the arena is full the item is
+    /// dropped and false is returned.
+    pub fn insert(&mut self, item: T) -> bool {
+        if self.items.len() == self.capacity {
+            return false;
+        }
+        self.items.push(item);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    // Only shown in the statistics of debug builds
+    #[cfg(any(debug_assertions, test))]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.items
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.items.iter_mut()
+    }
+
+    /// Keeps only the items for which `f` is true, without changing their order.
+    pub fn retain(&mut self, f: impl FnMut(&T) -> bool) {
+        self.items.retain(f);
+    }
+
+    /// Removes everything, the memory is kept for later use.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_up_to_capacity() {
+        let mut arena = Arena::with_capacity(3);
+        let buffer = arena.items.as_ptr();
+        assert!(arena.insert(1));
+        assert!(arena.insert(2));
+        assert!(arena.insert(3));
+        assert!(!arena.insert(4));
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena.capacity(), 3);
+        assert_eq!(arena.items.as_ptr(), buffer);
+    }
+
+    #[test]
+    fn order_is_preserved() {
+        let mut arena = Arena::with_capacity(10);
+        for i in 0..10 {
+            arena.insert(i);
+        }
+        arena.retain(|i| i % 3 != 0);
+        arena.insert(42);
+        assert!(arena.iter().copied().eq([1, 2, 4, 5, 7, 8, 42]));
+
+        arena.clear();
+        assert_eq!(arena.len(), 0);
+        assert_eq!(arena.capacity(), 10);
+    }
+}
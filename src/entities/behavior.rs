@@ -0,0 +1,166 @@
+use fastrand::Rng;
+
+use crate::common::{
+    calibrations::{self, ARCHETYPE_MIX},
+    types::{scalar, Coord, Scalar},
+};
+
+/// How an enemy moves, and how it takes absorbs and bombs. The archetypes
+/// carry what they need to remember from a frame to the next.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Behavior {
+    /// Rate-limited pure pursuit of its player
+    #[default]
+    Chaser,
+    /// Stops to aim at its player, then lunges at them. Frames since spawn.
+    Dasher(u32),
+    /// Circles around its player
+    Orbiter,
+    /// A chaser that splits in two smaller chasers when a bomb converts it
+    Splitter,
+    /// Ignores players, bouncing off the walls
+    Wanderer,
+    /// Big and slow, takes several absorbs. Absorbs left.
+    Tank(u8),
+}
+
+impl Behavior {
+    /// The archetypes after the chaser, in the order of `ARCHETYPE_MIX`
+    pub const SPECIAL: [Behavior; 5] = [
+        Behavior::Dasher(0),
+        Behavior::Orbiter,
+        Behavior::Splitter,
+        Behavior::Wanderer,
+        Behavior::Tank(Self::TANK_ABSORBS),
+    ];
+    // Dashers aim for this long, standing still, then lunge
+    const AIM_FRAMES: u32 = 45;
+    const LUNGE_FRAMES: u32 = 15;
+    const LUNGE_SPEED: Scalar = scalar(3.0);
+    // Dashers about to lunge are drawn with a ring around them
+    const TELL_FRAMES: u32 = 15;
+
+    // Distance orbiters keep from their player
+    const ORBIT_RADIUS: Scalar = scalar(24.0);
+
+    pub const TANK_ABSORBS: u8 = 3;
+    pub const TANK_SIZE: Scalar = scalar(11.0);
+    pub const TANK_SPEED: Scalar = scalar(0.45);
+    // Tanks shrink by this much each absorb, and are pushed back
+    pub const TANK_SHRINK: Scalar = scalar(2.0);
+    pub const TANK_KNOCKBACK: Scalar = scalar(10.0);
+
+    pub const SPLITTER_SIZE: Scalar = scalar(7.0);
+    pub const SPLIT_SIZE: Scalar = scalar(4.0);
+
+    /// An archetype for a new enemy, with the odds of `difficulty`. Draws
+    /// from `rng` only when there are archetypes other than the chaser.
+    pub fn random(rng: &Rng, difficulty: u32) -> Self {
+        let mix = ARCHETYPE_MIX[difficulty as usize];
+        calibrations::roll(rng, &mix).map_or(Behavior::Chaser, |n| Self::SPECIAL[n])
+    }
+
+    /// Whether it goes after players at all.
+    pub fn hunts(&self) -> bool {
+        *self != Behavior::Wanderer
+    }
+
+    /// Where a dasher is in its aim and lunge cycle.
+    fn dash_phase(&self) -> Option<u32> {
+        match self {
+            Behavior::Dasher(frames) => Some(frames % (Self::AIM_FRAMES + Self::LUNGE_FRAMES)),
+            _ => None,
+        }
+    }
+
+    /// A dasher standing still to aim.
+    pub fn aiming(&self) -> bool {
+        self.dash_phase()
+            .is_some_and(|phase| phase < Self::AIM_FRAMES)
+    }
+
+    /// A dasher about to lunge.
+    pub fn about_to_lunge(&self) -> bool {
+        self.dash_phase().is_some_and(|phase| {
+            (Self::AIM_FRAMES - Self::TELL_FRAMES..Self::AIM_FRAMES).contains(&phase)
+        })
+    }
+
+    /// Where it would like to head, given the vector to its player (from
+    /// center to center) and its length. None to keep its course.
+    pub fn heading(&self, to_player: Coord, norm: Scalar) -> Option<Coord> {
+        match self {
+            Behavior::Dasher(_) if !self.aiming() => None,
+            // Tangent to the orbit, corrected towards it
+            Behavior::Orbiter => {
+                let radial = (norm - Self::ORBIT_RADIUS) / Self::ORBIT_RADIUS;
+                Some(Coord {
+                    x: (-to_player.y + to_player.x * radial) / norm,
+                    y: (to_player.x + to_player.y * radial) / norm,
+                })
+            }
+            _ => Some(to_player),
+        }
+    }
+
+    /// How much faster than its speed it moves this frame.
+    pub fn speed_factor(&self) -> Scalar {
+        match self {
+            Behavior::Dasher(_) if self.aiming() => scalar(0.0),
+            Behavior::Dasher(_) => Self::LUNGE_SPEED,
+            _ => scalar(1.0),
+        }
+    }
+
+    /// One frame older.
+    pub fn tick(&mut self) {
+        if let Behavior::Dasher(frames) = self {
+            *frames = frames.wrapping_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::calibrations::{DIFFICULTY_LEVELS, MIX_TOTAL};
+
+    #[test]
+    fn mix_follows_difficulty() {
+        let rng = Rng::with_seed(1);
+        let first = rng.get_seed();
+        assert_eq!(Behavior::random(&rng, 0), Behavior::Chaser);
+        assert_eq!(rng.get_seed(), first, "no draw without archetypes");
+
+        for mix in ARCHETYPE_MIX {
+            assert!(mix.iter().sum::<u8>() <= MIX_TOTAL);
+        }
+        let hardest = (DIFFICULTY_LEVELS - 1) as u32;
+        let mut seen = [false; 5];
+        for _ in 0..1000 {
+            let behavior = Behavior::random(&rng, hardest);
+            if let Some(n) = Behavior::SPECIAL.iter().position(|&b| b == behavior) {
+                seen[n] = true;
+            }
+        }
+        assert_eq!(seen, [true; 5]);
+    }
+
+    #[test]
+    fn dashers_aim_then_lunge() {
+        let mut dasher = Behavior::Dasher(0);
+        for _ in 0..Behavior::AIM_FRAMES {
+            assert!(dasher.aiming());
+            assert_eq!(dasher.speed_factor(), scalar(0.0));
+            assert!(dasher.heading(Coord::new(1.0, 0.0), scalar(1.0)).is_some());
+            dasher.tick();
+        }
+        for _ in 0..Behavior::LUNGE_FRAMES {
+            assert!(!dasher.aiming());
+            assert_eq!(dasher.speed_factor(), Behavior::LUNGE_SPEED);
+            assert!(dasher.heading(Coord::new(1.0, 0.0), scalar(1.0)).is_none());
+            dasher.tick();
+        }
+        assert!(dasher.aiming());
+    }
+}
@@ -1,45 +1,183 @@
-use super::{entity::Entity, player::PlayerN, traits::Visible};
-use crate::{common::types::Coord, graphics::palette::DRAW_COLOR_BOMB, wasm4::SCREEN_SIZE};
+use fastrand::Rng;
+
+use super::{
+    entity::Entity,
+    player::{Player, PlayerN},
+    traits::{Pickup, Visible},
+};
+use crate::{
+    common::{
+        calibrations::{
+            self, BOMB_MIX, SCORE_BOMB, SCORE_CHAIN_BOMB, SCORE_DELAYED_BOMB, SCORE_LINE_BOMB,
+            SCORE_REPEL_BOMB,
+        },
+        types::{scalar, Coord, Scalar, ScalarExt},
+    },
+    graphics::palette::{self, DRAW_COLOR_BOMB},
+    wasm4::{self, SCREEN_SIZE},
+};
+
+/// What a bomb's wave does, and how the bomb goes off
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BombKind {
+    /// A wave growing around it, recoloring the enemies it touches
+    #[default]
+    Standard,
+    /// Like the standard one, and its wave sets off the bombs it reaches
+    Chain,
+    /// A wave sweeping ahead, the way the player was heading
+    Line,
+    /// Carried around once picked up, goes off a while after being dropped
+    Delayed,
+    /// A wave pushing enemies away instead of recoloring them
+    Repel,
+}
+
+impl BombKind {
+    /// The kinds after the standard one, in the order of `BOMB_MIX`
+    pub const SPECIAL: [BombKind; 4] = [
+        BombKind::Chain,
+        BombKind::Line,
+        BombKind::Delayed,
+        BombKind::Repel,
+    ];
+
+    /// A kind for a new bomb, with the odds of `difficulty`.
+    pub fn random(rng: &Rng, difficulty: u32) -> Self {
+        let mix = BOMB_MIX[difficulty as usize];
+        calibrations::roll(rng, &mix).map_or(BombKind::Standard, |n| Self::SPECIAL[n])
+    }
+
+    /// Points for setting it off, before the multiplier.
+    pub fn score(self) -> u32 {
+        match self {
+            BombKind::Standard => SCORE_BOMB,
+            BombKind::Chain => SCORE_CHAIN_BOMB,
+            BombKind::Line => SCORE_LINE_BOMB,
+            BombKind::Delayed => SCORE_DELAYED_BOMB,
+            BombKind::Repel => SCORE_REPEL_BOMB,
+        }
+    }
+}
 
 pub struct Bomb {
     pub entity: Entity,
     pub exploded: bool,
+    /// Whoever set it off, or carries it
     pub who_exploded: Option<PlayerN>,
-    growth_rate: f64,
+    pub kind: BombKind,
+    /// Whether its wave reached the boss's weak point, it only hurts it once
+    pub struck_boss: bool,
+    /// Delayed bombs: frames before going off, once dropped
+    fuse: Option<u32>,
+    growth_rate: Scalar,
 }
 
 impl Bomb {
+    /// Frames a dropped delayed bomb takes to go off
+    pub const FUSE_FRAMES: u32 = 45;
+    /// Size and speed of the wave of line bombs
+    const LINE_SIZE: Scalar = scalar(20.0);
+    const LINE_SPEED: Scalar = scalar(4.0);
+    /// Rings left behind by a line wave
+    const TRAIL_LENGTH: u32 = 2;
+    /// How far a repel wave pushes the enemies it touches, each frame
+    pub const REPEL_PUSH: Scalar = scalar(3.0);
+
     pub fn new(pos: &Coord) -> Self {
         let mut bomb = Self::default();
         bomb.entity.position = *pos;
         bomb
     }
 
+    /// The same bomb, of kind `kind`.
+    pub fn with_kind(mut self, kind: BombKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
     pub fn update(&mut self) {
         if self.exploded {
-            self.grow();
+            match self.kind {
+                BombKind::Line => self.sweep(),
+                _ => self.grow(),
+            }
+        } else if let Some(fuse) = self.fuse.as_mut() {
+            *fuse = fuse.saturating_sub(1);
+        }
+    }
+
+    /// Sets it off, for `player_n`. A line wave goes towards `heading`.
+    pub fn explode(&mut self, player_n: PlayerN, heading: Coord) {
+        self.exploded = true;
+        self.who_exploded = Some(player_n);
+        if self.kind == BombKind::Line {
+            let center = self.entity.center();
+            self.entity.size = Self::LINE_SIZE;
+            self.entity.position = center
+                - Coord {
+                    x: self.entity.radius(),
+                    y: self.entity.radius(),
+                };
+            self.entity.direction = heading;
         }
     }
 
+    /// The player carrying it around, if it's a delayed bomb not dropped yet.
+    pub fn carrier(&self) -> Option<PlayerN> {
+        match self.exploded || self.fuse.is_some() {
+            true => None,
+            false => self.who_exploded,
+        }
+    }
+
+    /// Drops a carried bomb where it is, lighting its fuse.
+    pub fn light_fuse(&mut self) {
+        self.fuse = Some(Self::FUSE_FRAMES);
+    }
+
+    /// A dropped bomb whose fuse ran out, ready to go off.
+    pub fn primed(&self) -> bool {
+        !self.exploded && self.fuse == Some(0)
+    }
+
     fn grow(&mut self) {
         let grow_amt = self.growth_rate;
-        self.entity.size = (self.entity.size + grow_amt).clamp(2.0, SCREEN_SIZE as f64 - 1.0);
-        self.entity.position.x -= grow_amt / 2.0;
-        self.entity.position.y -= grow_amt / 2.0;
+        self.entity.size = (self.entity.size + grow_amt)
+            .clamp(scalar(2.0), Scalar::from(SCREEN_SIZE) - scalar(1.0));
+        self.entity.position.x -= grow_amt / scalar(2.0);
+        self.entity.position.y -= grow_amt / scalar(2.0);
         self.entity.life -= 1;
     }
+
+    fn sweep(&mut self) {
+        self.entity.advance(self.entity.direction, Self::LINE_SPEED);
+        self.entity.life -= 1;
+    }
+
+    /// A ring `inset` pixels inside the bomb's circle, `offset` away from it.
+    fn ring(&self, offset: Coord, inset: i32) {
+        let size = self.entity.size.to_i32() - 2 * inset;
+        let position = self.entity.position + offset;
+        wasm4::oval(
+            position.x.to_i32() + inset,
+            position.y.to_i32() + inset,
+            size as u32,
+            size as u32,
+        );
+    }
 }
 impl Default for Bomb {
     fn default() -> Self {
-        const DEFAULT_GROWTH_RATE: f64 = 3.5;
-        const DEFAULT_SIZE: f64 = 9.0;
+        const DEFAULT_GROWTH_RATE: Scalar = scalar(3.5);
+        const DEFAULT_SIZE: Scalar = scalar(9.0);
         const DEFAULT_LIFE_SPAN: u32 = 60 / 2;
         Self {
             entity: Entity {
                 position: Coord::default(),
                 direction: Coord::default(),
                 size: DEFAULT_SIZE,
-                speed: 0.0,
+                speed: scalar(0.0),
                 // id: 0,
                 color: DRAW_COLOR_BOMB,
                 life: DEFAULT_LIFE_SPAN,
@@ -47,13 +185,82 @@ impl Default for Bomb {
             growth_rate: DEFAULT_GROWTH_RATE,
             exploded: false,
             who_exploded: None,
+            kind: BombKind::Standard,
+            struck_boss: false,
+            fuse: None,
         }
     }
 }
 
+impl Pickup for Bomb {
+    fn entity(&self) -> &Entity {
+        &self.entity
+    }
+
+    fn available(&self) -> bool {
+        !self.exploded && self.who_exploded.is_none()
+    }
+
+    /// Delayed bombs are carried, the others go off.
+    fn pick(&mut self, player: &Player) {
+        match self.kind {
+            BombKind::Delayed => self.who_exploded = Some(player.player_number),
+            _ => self.explode(player.player_number, player.heading),
+        }
+    }
+}
+
+/// Chain bombs and their waves have a ring inside, line bombs a slot and their
+/// wave a trail, repel bombs a dot and a wave of another color. Delayed bombs
+/// are solid, blinking once dropped.
 impl Visible for Bomb {
     fn draw(&self) {
-        self.entity.draw();
+        let none = Coord::default();
+        match self.kind {
+            BombKind::Delayed if !self.exploded => {
+                let fuse = self.fuse.unwrap_or(Self::FUSE_FRAMES);
+                if fuse < Self::FUSE_FRAMES && (fuse / 4).is_multiple_of(2) {
+                    return;
+                }
+                palette::set_draw_color(DRAW_COLOR_BOMB | (DRAW_COLOR_BOMB >> 4));
+                self.ring(none, 1);
+                self.entity.draw();
+            }
+            BombKind::Repel if self.exploded => {
+                palette::set_draw_color(0x40);
+                self.ring(none, 0);
+            }
+            _ => self.entity.draw(),
+        }
+        palette::set_draw_color(DRAW_COLOR_BOMB);
+        match self.kind {
+            BombKind::Chain => self.ring(none, 2),
+            BombKind::Line if self.exploded => {
+                let norm = self.entity.direction.norm();
+                if norm > Scalar::EPSILON {
+                    let step = self.entity.direction.scale(Self::LINE_SPEED / norm);
+                    for k in 1..=Self::TRAIL_LENGTH {
+                        self.ring(none - step.scale(Scalar::from(k)), 2 * k as i32);
+                    }
+                }
+            }
+            BombKind::Line => {
+                palette::set_draw_color(DRAW_COLOR_BOMB >> 4);
+                let center = self.entity.center();
+                let half = self.entity.radius().to_i32();
+                wasm4::hline(
+                    center.x.to_i32() - half + 1,
+                    center.y.to_i32(),
+                    (2 * half - 1) as u32,
+                );
+            }
+            BombKind::Repel if !self.exploded => {
+                palette::set_draw_color(DRAW_COLOR_BOMB >> 4);
+                let center = self.entity.center();
+                wasm4::rect(center.x.to_i32() - 1, center.y.to_i32() - 1, 2, 2);
+            }
+            _ => (),
+        }
     }
 }
 
@@ -63,27 +270,60 @@ mod tests {
 
     #[test]
     fn grow() {
-        let mut bomb = Bomb::default();
-        bomb.exploded = true;
-        bomb.entity.size = 10.0;
-        bomb.entity.position = Coord { x: 10.0, y: 10.0 };
+        let mut bomb = Bomb {
+            exploded: true,
+            ..Default::default()
+        };
+        bomb.entity.size = scalar(10.0);
+        bomb.entity.position = Coord::new(10.0, 10.0);
         bomb.entity.life = 1;
         bomb.grow();
-        assert_eq!(bomb.entity.size, 13.5);
-        assert_eq!(bomb.entity.position, Coord { x: 8.25, y: 8.25 });
+        assert_eq!(bomb.entity.size, scalar(13.5));
+        assert_eq!(bomb.entity.position, Coord::new(8.25, 8.25));
         assert_eq!(bomb.entity.life, 0);
     }
 
     #[test]
     fn grow_max() {
-        let mut bomb = Bomb::default();
-        bomb.exploded = true;
-        bomb.entity.size = SCREEN_SIZE as f64 - 1.0;
-        bomb.entity.position = Coord { x: 10.0, y: 10.0 };
+        let mut bomb = Bomb {
+            exploded: true,
+            ..Default::default()
+        };
+        bomb.entity.size = Scalar::from(SCREEN_SIZE) - scalar(1.0);
+        bomb.entity.position = Coord::new(10.0, 10.0);
         bomb.entity.life = 1;
         bomb.grow();
-        assert_eq!(bomb.entity.size, SCREEN_SIZE as f64 - 1.0);
-        assert_eq!(bomb.entity.position, Coord { x: 8.25, y: 8.25 });
+        assert_eq!(bomb.entity.size, Scalar::from(SCREEN_SIZE) - scalar(1.0));
+        assert_eq!(bomb.entity.position, Coord::new(8.25, 8.25));
         assert_eq!(bomb.entity.life, 0);
     }
+
+    #[test]
+    fn line_bombs_sweep() {
+        let mut bomb = Bomb::new(&Coord::new(50.0, 50.0)).with_kind(BombKind::Line);
+        let center = bomb.entity.center();
+        bomb.explode(PlayerN::P2, Coord::new(0.0, -1.0));
+        assert_eq!(bomb.entity.center(), center);
+        assert_eq!(bomb.who_exploded, Some(PlayerN::P2));
+        bomb.update();
+        assert_eq!(bomb.entity.size, Bomb::LINE_SIZE);
+        assert_eq!(bomb.entity.center(), center - Coord::new(0.0, 4.0));
+    }
+
+    #[test]
+    fn delayed_bombs_are_carried() {
+        let mut bomb = Bomb::new(&Coord::new(50.0, 50.0)).with_kind(BombKind::Delayed);
+        bomb.pick(&Player::new(PlayerN::P3));
+        assert!(!bomb.exploded);
+        assert!(!bomb.available());
+        assert_eq!(bomb.carrier(), Some(PlayerN::P3));
+
+        bomb.light_fuse();
+        assert_eq!(bomb.carrier(), None);
+        for _ in 0..Bomb::FUSE_FRAMES {
+            assert!(!bomb.primed());
+            bomb.update();
+        }
+        assert!(bomb.primed());
+    }
 }
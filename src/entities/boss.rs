@@ -0,0 +1,185 @@
+use super::{
+    entity::Entity,
+    obstacle::Obstacle,
+    traits::{Movable, Visible},
+};
+use crate::{
+    common::types::{scalar, Coord, Scalar, ScalarExt},
+    graphics::palette::{self, DRAW_COLOR_A, DRAW_COLOR_B, DRAW_COLOR_PLAIN},
+    wasm4,
+};
+
+/// A big disk showing up at some difficulties (see `BOSS_HEALTH`), bouncing
+/// around the arena and pushing players out of its way. Only its weak point,
+/// in the middle, can be hurt: by absorbing enemies of its color, or by bomb
+/// waves reaching it. The more it's hurt, the faster it goes and the more
+/// often it switches color.
+pub struct Boss {
+    /// The body, in the color of the weak point
+    pub entity: Entity,
+    pub health: u32,
+    pub max_health: u32,
+    /// Frames since it showed up
+    pub age: u32,
+}
+
+impl Boss {
+    /// Phases of the fight, by health lost: each goes faster and attacks
+    /// harder (see `BOSS_ATTACKS`)
+    pub const PHASES: usize = 3;
+    const SPEEDS: [Scalar; Self::PHASES] = [scalar(0.3), scalar(0.45), scalar(0.6)];
+    // Frames between color switches, by phase
+    const COLOR_FRAMES: [u32; Self::PHASES] = [240, 180, 120];
+    const SIZE: Scalar = scalar(24.0);
+    const WEAK_POINT_SIZE: Scalar = scalar(10.0);
+
+    /// A boss at `position`, with all its `health`.
+    pub fn new(position: Coord, health: u32) -> Self {
+        Self {
+            entity: Entity {
+                position,
+                direction: Coord::new(1.0, 1.0),
+                size: Self::SIZE,
+                speed: Self::SPEEDS[0],
+                color: DRAW_COLOR_A,
+                life: 1,
+            },
+            health,
+            max_health: health,
+            age: 0,
+        }
+    }
+
+    /// The phase it's in, from 0 at full health.
+    pub fn phase(&self) -> usize {
+        let lost = (self.max_health - self.health) as usize;
+        (lost * Self::PHASES / self.max_health.max(1) as usize).min(Self::PHASES - 1)
+    }
+
+    /// Takes `damage`, returns whether it's defeated.
+    pub fn hit(&mut self, damage: u32) -> bool {
+        self.health = self.health.saturating_sub(damage);
+        self.health == 0
+    }
+
+    /// Whether `entity` (a bomb wave) reaches the weak point.
+    pub fn exposed_to(&self, entity: &Entity) -> bool {
+        let reach = entity.radius() + Self::WEAK_POINT_SIZE / scalar(2.0);
+        entity.center().distance_to(&self.entity.center()) < reach
+    }
+
+    /// Pushes `entity` (a player) out of the body, if they overlap.
+    pub fn push_out(&self, entity: &mut Entity) {
+        let overlap = self.entity.radius() + entity.radius() - self.entity.distance(entity);
+        if overlap > scalar(0.0) {
+            entity.advance(entity.center() - self.entity.center(), overlap);
+        }
+    }
+}
+
+impl Movable for Boss {
+    fn update_position(&mut self, obstacles: &[Obstacle]) {
+        let phase = self.phase();
+        self.entity.speed = Self::SPEEDS[phase];
+        self.entity
+            .advance(self.entity.direction, self.entity.speed);
+        let push = self.entity.resolve(obstacles);
+        self.entity.bounce(push);
+        self.age += 1;
+        if self.age.is_multiple_of(Self::COLOR_FRAMES[phase]) {
+            self.entity.color = match self.entity.color {
+                DRAW_COLOR_A => DRAW_COLOR_B,
+                _ => DRAW_COLOR_A,
+            };
+        }
+    }
+}
+
+/// The body cracks a bit more with each phase, the weak point is in the
+/// middle.
+impl Visible for Boss {
+    fn draw(&self) {
+        let (x, y) = (
+            self.entity.position.x.to_i32(),
+            self.entity.position.y.to_i32(),
+        );
+        let size = self.entity.size.to_i32();
+        palette::set_draw_color(DRAW_COLOR_PLAIN);
+        wasm4::oval(x, y, size as u32, size as u32);
+        palette::set_draw_color(0x1);
+        let phase = self.phase() as i32;
+        if phase > 0 {
+            wasm4::vline(x + size / 2, y + 2, (size - 4) as u32);
+        }
+        if phase > 1 {
+            wasm4::hline(x + 2, y + size / 2, (size - 4) as u32);
+        }
+        let weak_point = Self::WEAK_POINT_SIZE.to_i32();
+        palette::set_draw_color(self.entity.color);
+        wasm4::oval(
+            x + (size - weak_point) / 2,
+            y + (size - weak_point) / 2,
+            weak_point as u32,
+            weak_point as u32,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entities::player::Player, wasm4::SCREEN_SIZE};
+
+    #[test]
+    fn phases_by_health() {
+        let mut boss = Boss::new(Coord::new(60.0, 60.0), 30);
+        assert_eq!(boss.phase(), 0);
+        assert!(!boss.hit(10));
+        assert_eq!(boss.phase(), 1);
+        assert!(!boss.hit(12));
+        assert_eq!(boss.phase(), 2);
+        assert!(boss.hit(10));
+        assert_eq!((boss.health, boss.phase()), (0, 2));
+    }
+
+    #[test]
+    fn weak_point_in_the_middle() {
+        let boss = Boss::new(Coord::new(60.0, 60.0), 10);
+        let mut wave = Player::default().entity;
+        wave.size = scalar(4.0);
+        // On the body, but away from the weak point
+        wave.position = Coord::new(60.0, 70.0);
+        assert!(boss.entity.collided_with(&wave, scalar(0.0)));
+        assert!(!boss.exposed_to(&wave));
+        wave.position = Coord::new(68.0, 70.0);
+        assert!(boss.exposed_to(&wave));
+    }
+
+    #[test]
+    fn bounces_and_switches_color() {
+        let mut boss = Boss::new(Coord::new(120.0, 120.0), 10);
+        let max = Scalar::from(SCREEN_SIZE) - boss.entity.size;
+        for _ in 0..Boss::COLOR_FRAMES[0] - 1 {
+            boss.update_position(&[]);
+            let position = boss.entity.position;
+            assert!(position.x >= scalar(0.0) && position.x <= max);
+            assert!(position.y >= scalar(0.0) && position.y <= max);
+        }
+        assert_eq!(boss.entity.color, DRAW_COLOR_A);
+        // Off the bottom right corner by now
+        assert!(boss.entity.direction.x < scalar(0.0));
+        boss.update_position(&[]);
+        assert_eq!(boss.entity.color, DRAW_COLOR_B);
+    }
+
+    #[test]
+    fn pushes_players_away() {
+        let boss = Boss::new(Coord::new(60.0, 60.0), 10);
+        let mut player = Player::default();
+        player.entity.position = Coord::new(75.0, 68.0);
+        assert!(boss.entity.collided_with(&player.entity, scalar(0.0)));
+        boss.push_out(&mut player.entity);
+        assert!(!boss.entity.collided_with(&player.entity, scalar(-0.01)));
+        assert!(player.entity.position.x > scalar(75.0));
+    }
+}
@@ -1,13 +1,22 @@
 use super::{
+    behavior::Behavior,
     entity::Entity,
+    obstacle::Obstacle,
     player::{Player, PlayerN},
     traits::{Movable, Visible},
 };
 
-use crate::{common::types::Coord, graphics::palette::DRAW_COLOR_A};
+use crate::{
+    common::types::{scalar, Coord, Scalar, ScalarExt},
+    graphics::palette::{self, DRAW_COLOR_A, DRAW_COLOR_B},
+    wasm4,
+};
 pub struct Enemy {
     pub entity: Entity,
     pub follows: Option<PlayerN>,
+    /// Versus: the player who sent the enemy, it leaves them alone
+    pub owner: Option<PlayerN>,
+    pub behavior: Behavior,
 }
 
 impl Enemy {
@@ -20,12 +29,18 @@ impl Enemy {
     // of dying when enemies spawn just as you glide by the edges.
     const I_FRAMES_ON_SPAWN: u32 = 12;
 
-    pub fn follow(&mut self, player: &Option<Player>) {
-        if let Some(player) = player {
+    // Largest change of direction (per axis) in a frame
+    const MAX_TURN: Scalar = scalar(0.09);
+
+    // How far ahead enemies look for obstacles in their way
+    const LOOKAHEAD: Scalar = scalar(10.0);
+
+    pub fn follow(&mut self, player: &Option<Player>, obstacles: &[Obstacle]) {
+        // Wanderers ignore players
+        if let Some(player) = player.as_ref().filter(|_| self.behavior.hunts()) {
             self.follows = Some(player.player_number);
-            // Standard pure pursuit
-            let p_radius = player.entity.size / 2.0;
-            let e_radius = self.entity.size / 2.0;
+            let p_radius = player.entity.size / scalar(2.0);
+            let e_radius = self.entity.size / scalar(2.0);
 
             let p_center = player.entity.position
                 + Coord {
@@ -39,20 +54,57 @@ impl Enemy {
                 };
             let p_to_e = p_center - e_center;
             let norm = p_to_e.norm();
-            if norm <= 2.0 * f64::EPSILON {
+            if norm <= scalar(2.0) * Scalar::EPSILON {
                 return;
             }
-
-            // Rate limit turns to make enemies slightly slower to follow sharp turns for more satisfying escapes
-            let ddx_norm = (self.entity.direction.x - p_to_e.x / norm).clamp(-0.09, 0.09);
-            let ddy_norm = (self.entity.direction.y - p_to_e.y / norm).clamp(-0.09, 0.09);
-            self.entity.direction.x -= ddx_norm;
-            self.entity.direction.y -= ddy_norm;
+            if let Some(heading) = self.behavior.heading(p_to_e, norm) {
+                self.turn_towards(self.around(heading, obstacles));
+            }
         } else {
             self.follows = None;
         }
     }
 
+    /// `heading`, unless there's an obstacle right ahead: then along it, on
+    /// the side away from its center.
+    fn around(&self, heading: Coord, obstacles: &[Obstacle]) -> Coord {
+        let norm = heading.norm();
+        if norm <= Scalar::EPSILON {
+            return heading;
+        }
+        let center = self.entity.center();
+        let ahead = center + heading.scale(Self::LOOKAHEAD / norm);
+        let radius = self.entity.radius();
+        let Some(obstacle) = obstacles.iter().find(|o| o.contains(ahead, radius)) else {
+            return heading;
+        };
+        let away = center - obstacle.center();
+        let side = Coord {
+            x: -heading.y,
+            y: heading.x,
+        };
+        match side.x * away.x + side.y * away.y >= scalar(0.0) {
+            true => side,
+            false => Coord::default() - side,
+        }
+    }
+
+    /// Turns towards `heading`, at most `MAX_TURN` per axis. Standard pure
+    /// pursuit when heading to the player.
+    fn turn_towards(&mut self, heading: Coord) {
+        let norm = heading.norm();
+        if norm <= Scalar::EPSILON {
+            return;
+        }
+        // Rate limit turns to make enemies slightly slower to follow sharp turns for more satisfying escapes
+        let ddx_norm =
+            (self.entity.direction.x - heading.x / norm).clamp(-Self::MAX_TURN, Self::MAX_TURN);
+        let ddy_norm =
+            (self.entity.direction.y - heading.y / norm).clamp(-Self::MAX_TURN, Self::MAX_TURN);
+        self.entity.direction.x -= ddx_norm;
+        self.entity.direction.y -= ddy_norm;
+    }
+
     pub fn new(_id: usize, pos: Coord, color: u16) -> Self {
         let mut enemy = Self::default();
         enemy.entity.position = pos;
@@ -60,6 +112,96 @@ impl Enemy {
         enemy
     }
 
+    /// The same enemy, of archetype `behavior`. Tanks and splitters are
+    /// bigger, tanks slower too.
+    pub fn with_behavior(mut self, behavior: Behavior) -> Self {
+        match behavior {
+            Behavior::Tank(_) => {
+                self.entity.size = Behavior::TANK_SIZE;
+                self.entity.speed = Behavior::TANK_SPEED;
+            }
+            Behavior::Splitter => self.entity.size = Behavior::SPLITTER_SIZE,
+            _ => (),
+        }
+        self.behavior = behavior;
+        self
+    }
+
+    /// Versus: the enemy absorbed by `owner` is sent at `target`, in the color
+    /// that hurts them. It's new again, so it can't hurt anyone right away.
+    pub fn send(&mut self, owner: PlayerN, target: &Player) {
+        self.owner = Some(owner);
+        self.follows = Some(target.player_number);
+        self.entity.color = match target.entity.color {
+            DRAW_COLOR_A => DRAW_COLOR_B,
+            _ => DRAW_COLOR_A,
+        };
+        self.entity.life = Self::LIFE_SPAN;
+        // Wanderers wouldn't go after them
+        if self.behavior == Behavior::Wanderer {
+            self.behavior = Behavior::Chaser;
+        }
+    }
+
+    /// Moves at half its speed, while a player slows time down.
+    pub fn update_position_slow(&mut self, obstacles: &[Obstacle]) {
+        self.step(self.entity.speed / scalar(2.0), obstacles);
+    }
+
+    fn step(&mut self, speed: Scalar, obstacles: &[Obstacle]) {
+        let speed = speed * self.behavior.speed_factor();
+        self.entity.advance(self.entity.direction, speed);
+        let push = self.entity.resolve(obstacles);
+        if self.behavior == Behavior::Wanderer {
+            self.entity.bounce(push);
+        }
+        self.behavior.tick();
+        self.entity.life -= 1;
+    }
+
+    /// A tank absorbed by `player` with absorbs left shrinks and is pushed
+    /// back, instead of going away. Returns whether it took the absorb.
+    pub fn withstand(&mut self, player: &Player) -> bool {
+        let Behavior::Tank(absorbs) = &mut self.behavior else {
+            return false;
+        };
+        if *absorbs <= 1 {
+            return false;
+        }
+        *absorbs -= 1;
+        self.entity.size -= Behavior::TANK_SHRINK;
+        let away = self.entity.center() - player.entity.center();
+        self.entity.advance(away, Behavior::TANK_KNOCKBACK);
+        true
+    }
+
+    /// A splitter hit by a bomb breaks in two smaller chasers, heading apart:
+    /// it becomes one of them and returns the other.
+    pub fn split(&mut self) -> Option<Enemy> {
+        if self.behavior != Behavior::Splitter {
+            return None;
+        }
+        self.behavior = Behavior::Chaser;
+        self.entity.size = Behavior::SPLIT_SIZE;
+        let direction = self.entity.direction;
+        let across = Coord {
+            x: -direction.y,
+            y: direction.x,
+        };
+        let mut other = Enemy::new(0, self.entity.position, self.entity.color);
+        other.entity.size = Behavior::SPLIT_SIZE;
+        other.entity.life = self.entity.life;
+        other.owner = self.owner;
+        other.entity.direction = direction - across;
+        self.entity.direction = direction + across;
+        other
+            .entity
+            .advance(other.entity.direction, Behavior::SPLIT_SIZE / scalar(2.0));
+        self.entity
+            .advance(self.entity.direction, Behavior::SPLIT_SIZE / scalar(2.0));
+        Some(other)
+    }
+
     pub fn kill(&mut self) {
         self.entity.life = 0;
     }
@@ -70,8 +212,8 @@ impl Enemy {
 
 impl Default for Enemy {
     fn default() -> Self {
-        const DEFAULT_SPEED: f64 = 0.7;
-        const DEFAULT_SIZE: f64 = 5.0;
+        const DEFAULT_SPEED: Scalar = scalar(0.7);
+        const DEFAULT_SIZE: Scalar = scalar(5.0);
         Self {
             entity: Entity {
                 position: Coord::default(),
@@ -82,38 +224,182 @@ impl Default for Enemy {
                 life: Self::LIFE_SPAN,
             },
             follows: Some(PlayerN::P1),
+            owner: None,
+            behavior: Behavior::Chaser,
         }
     }
 }
 
 impl Movable for Enemy {
-    fn update_position(&mut self) {
-        self.entity.update_position();
-        self.entity.life -= 1;
+    fn update_position(&mut self, obstacles: &[Obstacle]) {
+        self.step(self.entity.speed, obstacles);
     }
 }
 
+/// Tanks show the absorbs they have left as a dot, splitters a crack, and
+/// dashers a ring when about to lunge.
 impl Visible for Enemy {
     fn draw(&self) {
         self.entity.draw();
+        let (x, y) = (
+            self.entity.position.x.to_i32(),
+            self.entity.position.y.to_i32(),
+        );
+        let size = self.entity.size.to_i32();
+        match self.behavior {
+            Behavior::Tank(absorbs) => {
+                let dot = absorbs as i32;
+                palette::set_draw_color(self.entity.color >> 4);
+                wasm4::oval(
+                    x + (size - dot) / 2,
+                    y + (size - dot) / 2,
+                    dot as u32,
+                    dot as u32,
+                );
+            }
+            Behavior::Splitter => {
+                palette::set_draw_color(self.entity.color >> 4);
+                wasm4::vline(x + size / 2, y + 1, (size - 2) as u32);
+            }
+            Behavior::Dasher(_) if self.behavior.about_to_lunge() => {
+                palette::set_draw_color(self.entity.color & 0xf0);
+                wasm4::oval(x - 2, y - 2, size as u32 + 4, size as u32 + 4);
+            }
+            _ => (),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::entities::obstacle::ObstacleKind;
     #[test]
     fn follow() {
         let mut enemy = Enemy::default();
         let mut player = Player::default();
-        player.entity.position = Coord { x: 10.0, y: 10.0 };
-        enemy.entity.position = Coord { x: 20.0, y: 10.0 };
-        enemy.follow(&Some(player));
+        player.entity.position = Coord::new(10.0, 10.0);
+        enemy.entity.position = Coord::new(20.0, 10.0);
+        enemy.follow(&Some(player), &[]);
         assert_eq!(enemy.follows, Some(PlayerN::P1));
-        enemy.update_position();
-        assert!(enemy.entity.position.x < 20.0);
-        enemy.follow(&None);
+        enemy.update_position(&[]);
+        assert!(enemy.entity.position.x < scalar(20.0));
+        enemy.follow(&None, &[]);
+        assert_eq!(enemy.follows, None);
+    }
+
+    #[test]
+    fn orbiters_circle_around() {
+        let mut enemy = Enemy::default().with_behavior(Behavior::Orbiter);
+        let mut player = Player::default();
+        player.entity.position = Coord::new(80.0, 80.0);
+        enemy.entity.position = Coord::new(20.0, 80.0);
+        let player = Some(player);
+        for _ in 0..400 {
+            enemy.follow(&player, &[]);
+            enemy.update_position(&[]);
+        }
+        // Not on the player, but around them
+        let distance = enemy.entity.distance(&player.as_ref().unwrap().entity);
+        assert!((scalar(15.0)..scalar(35.0)).contains(&distance));
+        let before = enemy.entity.position;
+        for _ in 0..30 {
+            enemy.follow(&player, &[]);
+            enemy.update_position(&[]);
+        }
+        assert!(enemy.entity.position.distance_to(&before) > scalar(5.0));
+    }
+
+    #[test]
+    fn steers_around_obstacles() {
+        let mut enemy = Enemy::default();
+        let mut player = Player::default();
+        player.entity.position = Coord::new(100.0, 80.0);
+        enemy.entity.position = Coord::new(20.0, 80.0);
+        // Right between them
+        let center = enemy.entity.center();
+        let corner = Coord {
+            x: scalar(45.0),
+            y: center.y - scalar(5.0),
+        };
+        let pillar = Obstacle::new(ObstacleKind::Pillar, corner, Coord::new(10.0, 10.0));
+        let player = Some(player);
+        for _ in 0..300 {
+            enemy.follow(&player, &[pillar]);
+            enemy.update_position(&[pillar]);
+            assert_eq!(enemy.entity.penetration(&pillar), None);
+        }
+        assert!(enemy.entity.position.x > scalar(60.0));
+
+        // Wanderers bounce off them
+        let mut enemy = Enemy::default().with_behavior(Behavior::Wanderer);
+        enemy.entity.position = Coord::new(30.0, 80.0);
+        enemy.entity.direction = Coord::new(1.0, 0.0);
+        for _ in 0..30 {
+            enemy.update_position(&[pillar]);
+        }
+        assert!(enemy.entity.direction.x < scalar(0.0));
+    }
+
+    #[test]
+    fn wanderers_bounce() {
+        let mut enemy = Enemy::default().with_behavior(Behavior::Wanderer);
+        enemy.entity.position = Coord::new(2.0, 50.0);
+        enemy.entity.direction = Coord::new(-1.0, 0.0);
+        enemy.follow(&Some(Player::default()), &[]);
         assert_eq!(enemy.follows, None);
+        for _ in 0..5 {
+            enemy.update_position(&[]);
+        }
+        assert!(enemy.entity.direction.x > scalar(0.0));
+        assert!(enemy.entity.position.x > scalar(0.0));
+        assert_eq!(enemy.entity.position.y, scalar(50.0));
+    }
+
+    #[test]
+    fn tanks_take_several_absorbs() {
+        let mut enemy = Enemy::default().with_behavior(Behavior::Tank(Behavior::TANK_ABSORBS));
+        enemy.entity.position = Coord::new(50.0, 50.0);
+        let mut player = Player::default();
+        player.entity.position = Coord::new(44.0, 50.0);
+        for absorbs in 1..Behavior::TANK_ABSORBS {
+            assert!(enemy.withstand(&player));
+            assert_eq!(
+                enemy.entity.size,
+                Behavior::TANK_SIZE - Behavior::TANK_SHRINK * Scalar::from(absorbs as u32)
+            );
+        }
+        assert!(enemy.entity.position.x > scalar(50.0));
+        assert!(!enemy.withstand(&player));
+        assert!(!Enemy::default().withstand(&player));
+    }
+
+    #[test]
+    fn splitters_split() {
+        let mut enemy = Enemy::default().with_behavior(Behavior::Splitter);
+        enemy.entity.position = Coord::new(50.0, 50.0);
+        enemy.entity.direction = Coord::new(1.0, 0.0);
+        let other = enemy.split().expect("splitters split");
+        for half in [&enemy, &other] {
+            assert_eq!(half.behavior, Behavior::Chaser);
+            assert_eq!(half.entity.size, Behavior::SPLIT_SIZE);
+            assert_eq!(half.entity.life, Enemy::LIFE_SPAN);
+        }
+        assert!(enemy.entity.position.y > other.entity.position.y);
+        assert!(enemy.split().is_none());
+    }
+
+    #[test]
+    fn send() {
+        let mut enemy = Enemy::default();
+        enemy.entity.life = 1;
+        let mut target = Player::new(PlayerN::P2);
+        target.entity.color = DRAW_COLOR_A;
+        enemy.send(PlayerN::P1, &target);
+        assert_eq!(enemy.owner, Some(PlayerN::P1));
+        assert_eq!(enemy.follows, Some(PlayerN::P2));
+        assert_eq!(enemy.entity.color, DRAW_COLOR_B);
+        assert!(enemy.just_spawned());
     }
 
     #[test]
@@ -1,6 +1,9 @@
 use crate::{
-    common::types::Coord,
-    entities::traits::{Movable, Visible},
+    common::types::{scalar, Coord, Scalar, ScalarExt},
+    entities::{
+        obstacle::Obstacle,
+        traits::{Movable, Visible},
+    },
     graphics::palette,
     wasm4::{self, SCREEN_SIZE},
 };
@@ -8,56 +11,139 @@ use crate::{
 pub struct Entity {
     pub position: Coord,
     pub direction: Coord,
-    pub size: f64,
-    pub speed: f64,
+    pub size: Scalar,
+    pub speed: Scalar,
     // pub id: usize, // unused
     pub color: u16,
     pub life: u32,
 }
 
 impl Entity {
-    /// Distance between the center of two visible entities (assumed circular)
-    pub fn distance(&self, other: &Entity) -> f64 {
-        let radius1 = other.size / 2.0 - 0.5;
-        let radius2 = self.size / 2.0 - 0.5;
+    /// Radius of the circle the entity is drawn as
+    pub fn radius(&self) -> Scalar {
+        self.size / scalar(2.0) - scalar(0.5)
+    }
 
-        let center1 = other.position
-            + Coord {
-                x: radius1,
-                y: radius1,
-            };
-        let center2 = self.position
+    /// Center of the circle the entity is drawn as
+    pub fn center(&self) -> Coord {
+        let radius = self.radius();
+        self.position
             + Coord {
-                x: radius2,
-                y: radius2,
-            };
+                x: radius,
+                y: radius,
+            }
+    }
 
-        center1.distance_to(&center2)
+    /// Distance between the center of two visible entities (assumed circular)
+    pub fn distance(&self, other: &Entity) -> Scalar {
+        other.center().distance_to(&self.center())
     }
 
-    pub fn collided_with(&self, other: &Entity, extra_reach: f64) -> bool {
+    pub fn collided_with(&self, other: &Entity, extra_reach: Scalar) -> bool {
         //Circular bounding box collision
-        let radius1 = other.size / 2.0 - 0.5;
-        let radius2 = self.size / 2.0 - 0.5;
+        self.distance(other) < other.radius() + self.radius() + extra_reach
+    }
+
+    /// Moves `speed` pixels towards `direction`, staying on screen.
+    pub fn advance(&mut self, direction: Coord, speed: Scalar) {
+        let norm = direction.norm();
+        if norm <= Scalar::EPSILON {
+            return;
+        }
+        self.position += direction.scale(speed / norm);
+        self.stay_on_screen();
+    }
 
-        self.distance(other) < radius1 + radius2 + extra_reach
+    fn stay_on_screen(&mut self) {
+        let max = Scalar::from(SCREEN_SIZE) - self.size;
+        self.position = self.position.clamp(scalar(0.0), max, scalar(0.0), max);
+    }
+
+    /// Where the entity has to move to get out of `obstacle`, None if they
+    /// don't overlap. Circle against rectangle: away from the closest point
+    /// of the rectangle, or out of its closest side when the center is in.
+    pub fn penetration(&self, obstacle: &Obstacle) -> Option<Coord> {
+        let (center, radius) = (self.center(), self.radius());
+        let (min, max) = (obstacle.position, obstacle.position + obstacle.size);
+        let closest = center.clamp(min.x, max.x, min.y, max.y);
+        let out = center - closest;
+        let distance = out.norm();
+        if distance >= radius {
+            return None;
+        }
+        if distance > Scalar::EPSILON {
+            return Some(out.scale((radius - distance) / distance));
+        }
+        let sides = [
+            Coord {
+                x: min.x - center.x - radius,
+                y: scalar(0.0),
+            },
+            Coord {
+                x: max.x - center.x + radius,
+                y: scalar(0.0),
+            },
+            Coord {
+                x: scalar(0.0),
+                y: min.y - center.y - radius,
+            },
+            Coord {
+                x: scalar(0.0),
+                y: max.y - center.y + radius,
+            },
+        ];
+        sides.into_iter().reduce(|a, b| match a.norm() <= b.norm() {
+            true => a,
+            false => b,
+        })
+    }
+
+    /// Moves out of the obstacles it overlaps, staying on screen. Returns how
+    /// far it was pushed.
+    pub fn resolve(&mut self, obstacles: &[Obstacle]) -> Coord {
+        let start = self.position;
+        for obstacle in obstacles {
+            if let Some(push) = self.penetration(obstacle) {
+                self.position += push;
+            }
+        }
+        self.stay_on_screen();
+        self.position - start
+    }
+
+    /// Heads back into the screen from the walls it reached, and away from
+    /// the obstacles that pushed it.
+    pub fn bounce(&mut self, push: Coord) {
+        let max = Scalar::from(SCREEN_SIZE) - self.size;
+        let direction = &mut self.direction;
+        if push.x > scalar(0.0) {
+            direction.x = direction.x.abs();
+        } else if push.x < scalar(0.0) {
+            direction.x = -direction.x.abs();
+        }
+        if push.y > scalar(0.0) {
+            direction.y = direction.y.abs();
+        } else if push.y < scalar(0.0) {
+            direction.y = -direction.y.abs();
+        }
+        if self.position.x <= scalar(0.0) {
+            direction.x = direction.x.abs();
+        } else if self.position.x >= max {
+            direction.x = -direction.x.abs();
+        }
+        if self.position.y <= scalar(0.0) {
+            direction.y = direction.y.abs();
+        } else if self.position.y >= max {
+            direction.y = -direction.y.abs();
+        }
     }
 }
 
 /// All entities by default will move in the direction they are facing
 impl Movable for Entity {
-    fn update_position(&mut self) {
-        let norm = self.direction.norm();
-        if norm <= f64::EPSILON {
-            return;
-        }
-        self.position += self.direction.scale(self.speed / norm);
-        self.position = self.position.clamp(
-            0.0,
-            SCREEN_SIZE as f64 - self.size,
-            0.0,
-            SCREEN_SIZE as f64 - self.size,
-        );
+    fn update_position(&mut self, obstacles: &[Obstacle]) {
+        self.advance(self.direction, self.speed);
+        self.resolve(obstacles);
     }
 }
 
@@ -66,17 +152,23 @@ impl Visible for Entity {
     fn draw(&self) {
         palette::set_draw_color(self.color);
         wasm4::oval(
-            self.position.x as i32,
-            self.position.y as i32,
-            self.size as u32,
-            self.size as u32,
+            self.position.x.to_i32(),
+            self.position.y.to_i32(),
+            self.size.to_i32() as u32,
+            self.size.to_i32() as u32,
         );
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{common::types::Coord, entities::traits::Movable};
+    use crate::{
+        common::types::{scalar, Coord, ScalarExt, TOLERANCE},
+        entities::{
+            obstacle::{Obstacle, ObstacleKind},
+            traits::Movable,
+        },
+    };
     use approx::assert_abs_diff_eq;
 
     use super::Entity;
@@ -84,136 +176,181 @@ mod tests {
     #[test]
     fn distance_same() {
         let e1 = Entity {
-            position: Coord { x: 0.0, y: 0.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 1.0,
-            speed: 0.0,
+            position: Coord::new(0.0, 0.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(1.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
         let e2 = Entity {
-            position: Coord { x: 0.0, y: 0.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 1.0,
-            speed: 0.0,
+            position: Coord::new(0.0, 0.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(1.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
-        assert_abs_diff_eq!(e1.distance(&e2), 0.0);
+        assert_abs_diff_eq!(e1.distance(&e2).to_f64(), 0.0, epsilon = TOLERANCE);
     }
     #[test]
     fn distance_same_size() {
         let e1 = Entity {
-            position: Coord { x: 0.0, y: 0.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 1.0,
-            speed: 0.0,
+            position: Coord::new(0.0, 0.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(1.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
         let e2 = Entity {
-            position: Coord { x: 1.0, y: 0.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 1.0,
-            speed: 0.0,
+            position: Coord::new(1.0, 0.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(1.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
-        assert_abs_diff_eq!(e1.distance(&e2), 1.0);
+        assert_abs_diff_eq!(e1.distance(&e2).to_f64(), 1.0, epsilon = TOLERANCE);
     }
     #[test]
     fn distance_all_different() {
         let e1 = Entity {
-            position: Coord { x: 0.0, y: 0.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 10.0,
-            speed: 0.0,
+            position: Coord::new(0.0, 0.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(10.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
         let e2 = Entity {
-            position: Coord { x: 10.0, y: 10.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 10.0,
-            speed: 0.0,
+            position: Coord::new(10.0, 10.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(10.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
-        assert_abs_diff_eq!(e1.distance(&e2), 14.142135623730951);
+        assert_abs_diff_eq!(
+            e1.distance(&e2).to_f64(),
+            14.142135623730951,
+            epsilon = TOLERANCE
+        );
     }
 
     #[test]
     fn collided_with_distance() {
         let e1 = Entity {
-            position: Coord { x: 0.0, y: 0.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 20.0,
-            speed: 0.0,
+            position: Coord::new(0.0, 0.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(20.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
         let e2 = Entity {
-            position: Coord { x: 10.0, y: 10.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 20.0,
-            speed: 0.0,
+            position: Coord::new(10.0, 10.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(20.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
-        assert!(e1.collided_with(&e2, 0.0));
-        assert!(!e1.collided_with(&e2, -10.0));
+        assert!(e1.collided_with(&e2, scalar(0.0)));
+        assert!(!e1.collided_with(&e2, scalar(-10.0)));
     }
 
     #[test]
     fn collided_with_size() {
         let mut e1 = Entity {
-            position: Coord { x: 0.0, y: 0.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 10.0,
-            speed: 0.0,
+            position: Coord::new(0.0, 0.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(10.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
         let e2 = Entity {
-            position: Coord { x: 100.0, y: 100.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 10.0,
-            speed: 0.0,
+            position: Coord::new(100.0, 100.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(10.0),
+            speed: scalar(0.0),
             color: 0,
             life: 0,
         };
-        assert!(!e1.collided_with(&e2, 0.0));
-        e1.size = 150.0;
-        assert!(e1.collided_with(&e2, 0.0));
+        assert!(!e1.collided_with(&e2, scalar(0.0)));
+        e1.size = scalar(150.0);
+        assert!(e1.collided_with(&e2, scalar(0.0)));
     }
 
     #[test]
     fn update_position_diagonal() {
         let mut e1 = Entity {
-            position: Coord { x: 0.0, y: 0.0 },
-            direction: Coord { x: 1.0, y: 1.0 },
-            size: 10.0,
-            speed: 10.0,
+            position: Coord::new(0.0, 0.0),
+            direction: Coord::new(1.0, 1.0),
+            size: scalar(10.0),
+            speed: scalar(10.0),
             color: 0,
             life: 0,
         };
-        e1.update_position();
-        assert_abs_diff_eq!(e1.position.x, 7.071067811865475);
-        assert_abs_diff_eq!(e1.position.y, 7.071067811865475);
+        e1.update_position(&[]);
+        assert_abs_diff_eq!(
+            e1.position.x.to_f64(),
+            7.071067811865475,
+            epsilon = TOLERANCE
+        );
+        assert_abs_diff_eq!(
+            e1.position.y.to_f64(),
+            7.071067811865475,
+            epsilon = TOLERANCE
+        );
     }
 
     #[test]
     fn update_position_horizontal() {
         let mut e1 = Entity {
-            position: Coord { x: 0.0, y: 0.0 },
-            direction: Coord { x: 1.0, y: 0.0 },
-            size: 10.0,
-            speed: 10.0,
+            position: Coord::new(0.0, 0.0),
+            direction: Coord::new(1.0, 0.0),
+            size: scalar(10.0),
+            speed: scalar(10.0),
+            color: 0,
+            life: 0,
+        };
+        e1.update_position(&[]);
+        assert_abs_diff_eq!(e1.position.x.to_f64(), 10.0, epsilon = TOLERANCE);
+        assert_abs_diff_eq!(e1.position.y.to_f64(), 0.0, epsilon = TOLERANCE);
+    }
+
+    #[test]
+    fn obstacles_push_out() {
+        let mut e1 = Entity {
+            position: Coord::new(0.0, 20.0),
+            direction: Coord::new(1.0, 0.0),
+            size: scalar(10.0),
+            speed: scalar(4.0),
             color: 0,
             life: 0,
         };
-        e1.update_position();
-        assert_abs_diff_eq!(e1.position.x, 10.0);
-        assert_abs_diff_eq!(e1.position.y, 0.0);
+        let size = Coord::new(4.0, 30.0);
+        let wall = Obstacle::new(ObstacleKind::Wall, Coord::new(12.0, 10.0), size);
+        assert_eq!(e1.penetration(&wall), None);
+
+        // Stopped against the side of the wall, however hard it pushes
+        for _ in 0..5 {
+            e1.update_position(&[wall]);
+        }
+        assert_abs_diff_eq!(e1.position.x.to_f64(), 3.0, epsilon = TOLERANCE);
+        assert_abs_diff_eq!(e1.position.y.to_f64(), 20.0, epsilon = TOLERANCE);
+
+        // Out of the closest side, when the center is inside
+        e1.position = Coord::new(10.0, 30.0);
+        let push = e1.resolve(&[wall]);
+        assert_abs_diff_eq!(push.x.to_f64(), 6.0, epsilon = TOLERANCE);
+        assert_abs_diff_eq!(push.y.to_f64(), 0.0, epsilon = TOLERANCE);
+
+        // Away from corners
+        e1.position = Coord::new(14.0, 2.0);
+        let push = e1.penetration(&wall).expect("overlaps the corner");
+        assert!(push.x > scalar(0.0) && push.y < scalar(0.0));
     }
 }
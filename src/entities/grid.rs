@@ -0,0 +1,149 @@
+use super::entity::Entity;
+use crate::{
+    common::types::{scalar, Scalar, ScalarExt},
+    wasm4::SCREEN_SIZE,
+};
+
+const CELL_SIZE: u32 = 16;
+const COLUMNS: usize = (SCREEN_SIZE / CELL_SIZE) as usize;
+const CELLS: usize = COLUMNS * COLUMNS;
+
+/// Uniform grid over the screen, to only look for collisions between entities
+/// that are close to each other. Entities are bucketed by the cell their
+/// center falls in (anything off screen goes to the closest cell), so what may
+/// touch an entity is found in the few cells around it.
+pub struct Grid {
+    // The entities in cell `c` are `indices[cell_start[c]..cell_start[c + 1]]`,
+    // in the order they were added. Cells are stored row by row.
+    cell_start: [u16; CELLS + 1],
+    indices: Vec<u16>,
+    // Cell of every entity, only needed while building
+    cells: Vec<u16>,
+    // Largest entity, any entity touching a query is within this reach
+    max_radius: Scalar,
+}
+
+impl Grid {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            cell_start: [0; CELLS + 1],
+            indices: Vec::with_capacity(capacity),
+            cells: Vec::with_capacity(capacity),
+            max_radius: scalar(0.0),
+        }
+    }
+
+    /// Forgets what was there and buckets `entities`, which will be referred
+    /// to by their position in the iterator.
+    pub fn build<'a>(&mut self, entities: impl Iterator<Item = &'a Entity>) {
+        self.cell_start = [0; CELLS + 1];
+        self.cells.clear();
+        self.max_radius = scalar(0.0);
+        for entity in entities {
+            let center = entity.center();
+            let cell = Self::row_or_column(center.y) * COLUMNS + Self::row_or_column(center.x);
+            self.cells.push(cell as u16);
+            self.cell_start[cell + 1] += 1;
+            self.max_radius = self.max_radius.max(entity.radius());
+        }
+        for cell in 0..CELLS {
+            self.cell_start[cell + 1] += self.cell_start[cell];
+        }
+
+        // Counting sort, entities keep their order within a cell
+        let mut next = self.cell_start;
+        self.indices.clear();
+        self.indices.resize(self.cells.len(), 0);
+        for (i, &cell) in self.cells.iter().enumerate() {
+            self.indices[next[cell as usize] as usize] = i as u16;
+            next[cell as usize] += 1;
+        }
+    }
+
+    /// Calls `f` with every entity that may have collided with `entity` (as in
+    /// `Entity::collided_with`), plus a few that haven't: the actual check is
+    /// left to the caller.
+    pub fn query(&self, entity: &Entity, extra_reach: Scalar, mut f: impl FnMut(usize)) {
+        // Bounding box of the reach, with a margin for rounding errors
+        let center = entity.center();
+        let reach = entity.radius() + self.max_radius + extra_reach.max(scalar(0.0)) + scalar(1.0);
+        let first_column = Self::row_or_column(center.x - reach);
+        let last_column = Self::row_or_column(center.x + reach);
+        let first_row = Self::row_or_column(center.y - reach);
+        let last_row = Self::row_or_column(center.y + reach);
+
+        for row in first_row..=last_row {
+            // Consecutive cells of a row are next to each other in `indices`
+            let start = self.cell_start[row * COLUMNS + first_column] as usize;
+            let end = self.cell_start[row * COLUMNS + last_column + 1] as usize;
+            for &i in &self.indices[start..end] {
+                f(i as usize);
+            }
+        }
+    }
+
+    fn row_or_column(coordinate: Scalar) -> usize {
+        (coordinate / Scalar::from(CELL_SIZE))
+            .to_i32()
+            .clamp(0, COLUMNS as i32 - 1) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::Coord;
+    use fastrand::Rng;
+
+    fn entity(x: f64, y: f64, size: f64) -> Entity {
+        Entity {
+            position: Coord::new(x, y),
+            direction: Coord::default(),
+            size: scalar(size),
+            speed: scalar(0.0),
+            color: 0,
+            life: 1,
+        }
+    }
+
+    #[test]
+    fn finds_every_collision() {
+        let rng = Rng::with_seed(1);
+        let entities: Vec<Entity> = (0..300)
+            .map(|_| entity(rng.f64() * 200.0 - 20.0, rng.f64() * 200.0 - 20.0, 5.0))
+            .collect();
+        let mut grid = Grid::with_capacity(entities.len());
+        grid.build(entities.iter());
+
+        for _ in 0..100 {
+            let size = rng.f64() * 60.0 + 2.0;
+            let other = entity(rng.f64() * 180.0 - 20.0, rng.f64() * 180.0 - 20.0, size);
+            let mut found = vec![];
+            grid.query(&other, scalar(2.0), |i| found.push(i));
+
+            let colliding: Vec<usize> = (0..entities.len())
+                .filter(|&i| other.collided_with(&entities[i], scalar(2.0)))
+                .collect();
+            assert!(colliding.iter().all(|i| found.contains(i)));
+            // Each entity is found at most once
+            let len = found.len();
+            found.sort();
+            found.dedup();
+            assert_eq!(found.len(), len);
+        }
+    }
+
+    #[test]
+    fn skips_far_away_entities() {
+        let entities = [
+            entity(10.0, 10.0, 5.0),
+            entity(150.0, 150.0, 5.0),
+            entity(12.0, 10.0, 5.0),
+        ];
+        let mut grid = Grid::with_capacity(entities.len());
+        grid.build(entities.iter());
+        let mut found = vec![];
+        grid.query(&entity(8.0, 8.0, 5.0), scalar(2.0), |i| found.push(i));
+        assert_eq!(found, [0, 2]);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::common::types::{scalar, Coord, Scalar};
+
+use super::obstacle::{Obstacle, ObstacleKind};
+
+// Arenas are stored as bytes, all lengths in pixels:
+// - the number of enemy spawn points, then x and y of each of them
+// - then obstacles up to the end: kind (index in `ObstacleKind::ALL`), x, y,
+//   width and height. Bumpers go on with the end of their run (x and y) and
+//   their speed, in sixteenths of a pixel per frame.
+const W: u8 = ObstacleKind::Wall as u8;
+const P: u8 = ObstacleKind::Pillar as u8;
+const B: u8 = ObstacleKind::Bumper as u8;
+
+/// Arena setting: a different arena as the game gets harder (see
+/// `ARENA_ROTATION`), instead of one of `LAYOUTS`.
+pub const ROTATING: u8 = LAYOUTS.len() as u8;
+
+pub const LAYOUTS: [Layout; 4] = [
+    // Enemies come from the corners and the middle of the edges
+    Layout {
+        name: "Open field",
+        data: &[
+            8, 0, 0, 0, 80, 0, 159, 80, 0, 80, 159, 159, 0, 159, 80, 159, 159,
+        ],
+    },
+    Layout {
+        name: "Cross",
+        data: &[
+            8, 0, 0, 159, 0, 0, 159, 159, 159, 40, 0, 159, 40, 120, 159, 0, 120, //
+            W, 78, 16, 4, 44, //
+            W, 78, 100, 4, 44, //
+            W, 16, 78, 44, 4, //
+            W, 100, 78, 44, 4, //
+            P, 40, 40, 6, 6, //
+            P, 114, 40, 6, 6, //
+            P, 40, 114, 6, 6, //
+            P, 114, 114, 6, 6,
+        ],
+    },
+    // Rooms with a door to each neighbor, around a hall in the middle
+    Layout {
+        name: "Four rooms",
+        data: &[
+            8, 0, 0, 159, 0, 0, 159, 159, 159, 40, 0, 159, 40, 120, 159, 0, 120, //
+            W, 78, 0, 4, 28, //
+            W, 78, 48, 4, 16, //
+            W, 78, 96, 4, 16, //
+            W, 78, 132, 4, 28, //
+            W, 0, 78, 28, 4, //
+            W, 48, 78, 16, 4, //
+            W, 96, 78, 16, 4, //
+            W, 132, 78, 28, 4,
+        ],
+    },
+    // Four bumpers closing in on the middle and back, enemies slip in
+    // through the corners
+    Layout {
+        name: "Ring",
+        data: &[
+            8, 0, 0, 0, 80, 0, 159, 80, 0, 80, 159, 159, 0, 159, 80, 159, 159, //
+            B, 50, 14, 60, 4, 50, 40, 1, //
+            B, 50, 142, 60, 4, 50, 116, 1, //
+            B, 14, 50, 4, 60, 40, 50, 1, //
+            B, 142, 50, 4, 60, 116, 50, 1,
+        ],
+    },
+];
+
+fn pixels(x: u8, y: u8) -> Coord {
+    Coord {
+        x: Scalar::from(x as u32),
+        y: Scalar::from(y as u32),
+    }
+}
+
+/// A named arena: where enemies spawn, and the obstacles in the way.
+pub struct Layout {
+    pub name: &'static str,
+    data: &'static [u8],
+}
+
+impl Layout {
+    pub fn spawns(&self) -> impl ExactSizeIterator<Item = Coord> {
+        let count = self.data[0] as usize;
+        self.data[1..1 + 2 * count]
+            .chunks_exact(2)
+            .map(|xy| pixels(xy[0], xy[1]))
+    }
+
+    /// The obstacles, where they start.
+    pub fn obstacles(&self) -> Vec<Obstacle> {
+        let mut rest = &self.data[1 + 2 * self.data[0] as usize..];
+        let mut obstacles = vec![];
+        while let [kind, x, y, width, height, tail @ ..] = rest {
+            let kind = ObstacleKind::ALL[*kind as usize];
+            let mut obstacle = Obstacle::new(kind, pixels(*x, *y), pixels(*width, *height));
+            rest = tail;
+            if let (ObstacleKind::Bumper, [to_x, to_y, speed, tail @ ..]) = (kind, rest) {
+                let speed = Scalar::from(*speed as u32) / scalar(16.0);
+                obstacle = obstacle.moving(pixels(*to_x, *to_y), speed);
+                rest = tail;
+            }
+            obstacles.push(obstacle);
+        }
+        obstacles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entities::{enemy::Enemy, player::Player},
+        wasm4::SCREEN_SIZE,
+    };
+
+    #[test]
+    fn open_field_spawns_everywhere() {
+        let (middle, end) = (SCREEN_SIZE / 2, SCREEN_SIZE - 1);
+        let spawns: Vec<Coord> = LAYOUTS[0].spawns().collect();
+        let expected: Vec<Coord> = [
+            (0, 0),
+            (0, middle),
+            (0, end),
+            (middle, 0),
+            (middle, end),
+            (end, 0),
+            (end, middle),
+            (end, end),
+        ]
+        .into_iter()
+        .map(|(x, y)| pixels(x as u8, y as u8))
+        .collect();
+        assert_eq!(spawns, expected);
+        assert!(LAYOUTS[0].obstacles().is_empty());
+    }
+
+    #[test]
+    fn layouts_leave_room() {
+        let counts = LAYOUTS.map(|layout| layout.obstacles().len());
+        assert_eq!(counts, [0, 8, 8, 4]);
+        let ring = LAYOUTS[3].obstacles();
+        assert!(ring.iter().all(|o| o.kind == ObstacleKind::Bumper));
+
+        // Enemies spawn clear of the obstacles, players start clear of them
+        for layout in LAYOUTS.iter() {
+            let obstacles = layout.obstacles();
+            let player = Player::default();
+            assert!(obstacles
+                .iter()
+                .all(|o| player.entity.penetration(o).is_none()));
+            for spawn in layout.spawns() {
+                let enemy = Enemy::new(0, spawn, 0);
+                let mut entity = enemy.entity;
+                entity.resolve(&[]);
+                assert!(
+                    obstacles.iter().all(|o| entity.penetration(o).is_none()),
+                    "{} spawns into an obstacle",
+                    layout.name
+                );
+            }
+        }
+    }
+}
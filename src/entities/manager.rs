@@ -1,79 +1,276 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
 
 use crate::{
-    common::calibrations::{INIT_LIVES, MAX_BOMBS, MAX_ENEMIES},
+    common::{
+        calibrations::{
+            BOSS_BOMB_DAMAGE, MAGNET_PULL, MAGNET_RADIUS, MAX_BOMBS, MAX_ENEMIES, MAX_POWERUPS,
+        },
+        types::{scalar, Coord, Scalar},
+    },
     wasm4::SCREEN_SIZE,
 };
 
 use super::{
-    bomb::Bomb,
+    arena::Arena,
+    bomb::{Bomb, BombKind},
+    boss::Boss,
     enemy::Enemy,
+    grid::Grid,
+    obstacle::Obstacle,
     player::{Player, PlayerN},
-    traits::{Movable, Visible},
+    powerup::{PowerKind, PowerUp},
+    traits::{Movable, Pickup, Visible},
 };
 
+/// Enemies absorbed, bombs exploded (and their points) and power-ups picked up
+/// in a frame, by each player. Then the damage the boss took, all together.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Tally {
+    pub enemies: [u32; 4],
+    pub bombs: [u32; 4],
+    pub bomb_points: [u32; 4],
+    pub powerups: [u32; 4],
+    pub boss_damage: u32,
+    pub boss_defeated: bool,
+}
+
+impl Tally {
+    /// Enemies absorbed and bombs exploded by all the players together.
+    pub fn total(&self) -> (u32, u32) {
+        (self.enemies.iter().sum(), self.bombs.iter().sum())
+    }
+}
+
 /// Entities include the player, enemies and bombs
 pub struct EntityManager {
     pub players: [Option<Player>; 4],
-    pub life_counter: u32,
-    pub bombs: HashMap<usize, Box<Bomb>>,
-    pub enemies: HashMap<usize, Box<Enemy>>,
+    pub bombs: Arena<Bomb>,
+    pub enemies: Arena<Enemy>,
+    pub powerups: Arena<PowerUp>,
+    /// What's in the way of players and enemies, none by default
+    pub obstacles: Vec<Obstacle>,
+    /// Showing up at some difficulties, see `Boss`
+    pub boss: Option<Boss>,
     pub killer: Option<Enemy>,
+    /// Player hit by the killer
+    pub victim: Option<PlayerN>,
+    /// Versus rules: absorbed enemies are sent at the other players
+    pub versus: bool,
+    // Broad phase of the collisions, see `process_collisions`
+    grid: Grid,
+    converted_by: Vec<Option<usize>>,
+    near_players: Vec<bool>,
+    // Halves of the splitters split this frame
+    splits: Vec<Enemy>,
 }
 impl EntityManager {
     pub fn new() -> Self {
+        Self::with_capacity(MAX_ENEMIES, MAX_BOMBS)
+    }
+
+    pub fn with_capacity(max_enemies: usize, max_bombs: usize) -> Self {
         Self {
             players: [Some(Player::new(PlayerN::P1)), None, None, None],
-            life_counter: INIT_LIVES,
-            bombs: HashMap::with_capacity(MAX_BOMBS),
-            enemies: HashMap::with_capacity(MAX_ENEMIES),
+            bombs: Arena::with_capacity(max_bombs),
+            enemies: Arena::with_capacity(max_enemies),
+            powerups: Arena::with_capacity(MAX_POWERUPS),
+            obstacles: Vec::new(),
+            boss: None,
             killer: None,
+            victim: None,
+            versus: false,
+            grid: Grid::with_capacity(max_enemies),
+            converted_by: Vec::with_capacity(max_enemies),
+            near_players: Vec::with_capacity(max_enemies),
+            splits: Vec::with_capacity(max_bombs),
         }
     }
-    pub fn update(&mut self) -> (u32, u32) {
-        self.update_state();
 
-        let (enemies_killed, bombs_exploded) = self.process_collisions();
-        (enemies_killed, bombs_exploded)
+    /// Back to how `new` leaves things, reusing the arenas: the heap is too
+    /// small to hold a second set while the first one is dropped.
+    pub fn reset(&mut self) {
+        self.players = [Some(Player::new(PlayerN::P1)), None, None, None];
+        self.bombs.clear();
+        self.enemies.clear();
+        self.powerups.clear();
+        self.obstacles.clear();
+        self.boss = None;
+        self.killer = None;
+        self.victim = None;
+        self.versus = false;
+    }
+    pub fn update(&mut self) -> Tally {
+        self.update_state();
+        self.process_collisions()
     }
 
     fn update_state(&mut self) {
-        // Update players position
+        // Bumpers move first, what they run into is pushed out of the way
+        for obstacle in self.obstacles.iter_mut() {
+            obstacle.update();
+        }
+
+        // Update players position. The boss pushes them out of its way.
+        if let Some(boss) = self.boss.as_mut() {
+            boss.update_position(&self.obstacles);
+        }
         for player in self.players.iter_mut().flatten() {
-            player.update_position();
+            player.update_position(&self.obstacles);
             player.stop();
+            if let Some(boss) = self.boss.as_ref() {
+                boss.push_out(&mut player.entity);
+                player.entity.resolve(&self.obstacles);
+            }
         }
 
-        // Update enemy position
-        for enemy in self.enemies.values_mut() {
-            let mut to_follow = &self.players[PlayerN::P1 as usize];
+        // Update enemy position. Alone, P1 is always the closest player.
+        // Wanderers ignore players altogether.
+        // Enemies sent in versus go after the closest player but their owner.
+        // They all go at half speed while a player slows time down.
+        let multiplayer = self.players[1..].iter().any(Option::is_some);
+        let slow = self.has_power(PowerKind::SlowTime);
+        for enemy in self.enemies.iter_mut() {
+            let obstacles = &self.obstacles;
+            match multiplayer {
+                true => enemy.follow(Self::target(&self.players, enemy), obstacles),
+                false => enemy.follow(&self.players[PlayerN::P1 as usize], obstacles),
+            }
+            match slow {
+                true => enemy.update_position_slow(obstacles),
+                false => enemy.update_position(obstacles),
+            }
+        }
 
-            let mut temp_distance = (SCREEN_SIZE * 2) as f64;
-            for player in self.players.iter() {
-                if let Some(p) = player {
-                    let distance = enemy.entity.distance(&p.entity);
-                    if distance < temp_distance {
-                        temp_distance = distance;
-                        to_follow = player;
-                    };
+        // Magnets pull the enemies of their player's color
+        for player in self.players.iter().flatten() {
+            if !player.powers.active(PowerKind::Magnet) {
+                continue;
+            }
+            for enemy in self.enemies.iter_mut() {
+                if enemy.entity.color == player.entity.color
+                    && enemy.entity.distance(&player.entity) < MAGNET_RADIUS
+                {
+                    let pull = player.entity.center() - enemy.entity.center();
+                    enemy.entity.advance(pull, MAGNET_PULL);
+                    enemy.entity.resolve(&self.obstacles);
                 }
             }
-            enemy.follow(to_follow);
-            enemy.update_position();
         }
 
-        // Update bombs
-        for bomb in self.bombs.values_mut() {
+        // Update bombs and power-ups. Carried bombs go along with their
+        // player, and are lost with them.
+        for bomb in self.bombs.iter_mut() {
             bomb.update();
+            if let Some(player_n) = bomb.carrier() {
+                match self.players[player_n as usize].as_ref() {
+                    Some(player) => {
+                        let radius = bomb.entity.radius();
+                        bomb.entity.position = player.entity.center()
+                            - Coord {
+                                x: radius,
+                                y: radius,
+                            };
+                    }
+                    None => bomb.entity.life = 0,
+                }
+            }
+        }
+        for powerup in self.powerups.iter_mut() {
+            powerup.update();
         }
 
         // Cleanup dead entities
         self.prune();
     }
 
+    /// The player `enemy` goes after: the closest one, but the player who sent
+    /// it. P1 when there's nobody else.
+    fn target<'a>(players: &'a [Option<Player>; 4], enemy: &Enemy) -> &'a Option<Player> {
+        let mut to_follow = &players[PlayerN::P1 as usize];
+
+        let mut temp_distance = Scalar::from(SCREEN_SIZE * 2);
+        for player in players.iter() {
+            if let Some(p) = player
+                .as_ref()
+                .filter(|p| enemy.owner != Some(p.player_number))
+            {
+                let distance = enemy.entity.distance(&p.entity);
+                if distance < temp_distance {
+                    temp_distance = distance;
+                    to_follow = player;
+                };
+            }
+        }
+        to_follow
+    }
+
+    /// Takes `player_n` off the field. The enemies after them go after the
+    /// closest player left, and the ones they sent can now hurt anyone.
+    pub fn remove_player(&mut self, player_n: PlayerN) -> Option<Player> {
+        let player = self.players[player_n as usize].take()?;
+        for enemy in self.enemies.iter_mut() {
+            if enemy.owner == Some(player_n) {
+                enemy.owner = None;
+            }
+            if enemy.follows == Some(player_n) {
+                enemy.follows = Self::target(&self.players, enemy)
+                    .as_ref()
+                    .map(|p| p.player_number);
+            }
+        }
+        Some(player)
+    }
+
     fn prune(&mut self) {
-        self.enemies.retain(|_, enemy| enemy.entity.life > 0);
-        self.bombs.retain(|_, bomb| bomb.entity.life > 0);
+        self.enemies.retain(|enemy| enemy.entity.life > 0);
+        self.bombs.retain(|bomb| bomb.entity.life > 0);
+        self.powerups.retain(|powerup| powerup.entity.life > 0);
+    }
+
+    /// Whether any player has `kind` active.
+    pub fn has_power(&self, kind: PowerKind) -> bool {
+        self.players
+            .iter()
+            .flatten()
+            .any(|player| player.powers.active(kind))
+    }
+
+    /// Players carrying a bomb.
+    pub fn carriers(&self) -> [bool; 4] {
+        let mut carriers = [false; 4];
+        for player_n in self.bombs.iter().filter_map(Bomb::carrier) {
+            carriers[player_n as usize] = true;
+        }
+        carriers
+    }
+
+    /// `player_n` drops the bomb they carry, if any, lighting its fuse.
+    pub fn drop_bomb(&mut self, player_n: PlayerN) -> bool {
+        let bomb = self
+            .bombs
+            .iter_mut()
+            .find(|bomb| bomb.carrier() == Some(player_n));
+        bomb.map(Bomb::light_fuse).is_some()
+    }
+
+    /// The first player touching each pickup still available picks it up,
+    /// `picked` is told what and who.
+    fn pick_up<P: Pickup>(
+        pickups: &mut Arena<P>,
+        players: &[Option<Player>; 4],
+        extra_reach: Scalar,
+        mut picked: impl FnMut(&P, PlayerN),
+    ) {
+        'pickups_loop: for pickup in pickups.iter_mut() {
+            for player in players.iter().flatten() {
+                if pickup.available() && pickup.entity().collided_with(&player.entity, extra_reach)
+                {
+                    pickup.pick(player);
+                    picked(pickup, player.player_number);
+                    continue 'pickups_loop;
+                }
+            }
+        }
     }
 
     pub fn draw(&self) {
@@ -82,77 +279,689 @@ impl EntityManager {
         for p in self.players.iter().flatten() {
             p.draw();
         }
-        for e in self.enemies.values() {
+        for e in self.enemies.iter() {
             e.draw();
         }
-        for b in self.bombs.values() {
+        for p in self.powerups.iter() {
+            p.draw();
+        }
+        for b in self.bombs.iter() {
             b.draw();
         }
     }
 
-    fn process_collisions(&mut self) -> (u32, u32) {
-        // Returned variables
-        let mut enemies_killed = 0;
-        let mut bombs_exploded = 0;
+    fn process_collisions(&mut self) -> Tally {
+        // Returned variable
+        let mut tally = Tally::default();
 
-        // Player-Bomb collision
-        'bombs_loop: for bomb in self.bombs.values_mut() {
-            for player in self.players.iter().flatten() {
-                let extra_reach = 2.0; // Makes bombs easier to trigger
-                if bomb.entity.collided_with(&player.entity, extra_reach) && !bomb.exploded {
-                    bombs_exploded += 1;
-                    bomb.exploded = true;
-                    bomb.who_exploded = Some(player.player_number);
-                    continue 'bombs_loop;
+        // Player-Bomb collision. Delayed bombs are only carried away.
+        let extra_reach = scalar(2.0); // Makes bombs easier to trigger
+        let mut exploded = |bomb: &Bomb, player_n: PlayerN| {
+            if bomb.exploded {
+                tally.bombs[player_n as usize] += 1;
+                tally.bomb_points[player_n as usize] += bomb.kind.score();
+            }
+        };
+        Self::pick_up(&mut self.bombs, &self.players, extra_reach, &mut exploded);
+
+        // Dropped bombs whose fuse ran out go off, and the waves of chain
+        // bombs set off the bombs they reach
+        let bombs = self.bombs.as_mut_slice();
+        for b in 0..bombs.len() {
+            let owner = match bombs[b].primed() {
+                true => bombs[b].who_exploded,
+                false => bombs.iter().find_map(|chain| {
+                    let reached = chain.kind == BombKind::Chain
+                        && chain.exploded
+                        && bombs[b].available()
+                        && chain.entity.collided_with(&bombs[b].entity, scalar(0.0));
+                    reached.then_some(chain.who_exploded).flatten()
+                }),
+            };
+            if let Some(owner) = owner {
+                let heading = self.players[owner as usize]
+                    .as_ref()
+                    .map_or(Coord::new(1.0, 0.0), |player| player.heading);
+                bombs[b].explode(owner, heading);
+                exploded(&bombs[b], owner);
+            }
+        }
+
+        // Bomb waves reaching the boss's weak point hurt it, once each
+        if let Some(boss) = self.boss.as_ref() {
+            for bomb in self.bombs.iter_mut() {
+                if bomb.exploded && !bomb.struck_boss && boss.exposed_to(&bomb.entity) {
+                    bomb.struck_boss = true;
+                    tally.boss_damage += BOSS_BOMB_DAMAGE;
                 }
             }
         }
 
-        'enemies_loop: for enemy in self.enemies.values_mut() {
-            // Bomb-Enemy collision, convert enemies to player color
-            'bombs_loop: for bomb in self.bombs.values_mut() {
-                let extra_reach = 2.0; // Makes enemies easier to convert
-                if bomb.exploded && bomb.entity.collided_with(&enemy.entity, extra_reach) {
-                    enemy.entity.color = self.players[bomb
-                        .who_exploded
-                        .expect("Exploded bomb should have a 'owner'")
-                        as usize]
-                        .as_ref()
-                        .expect("Player should still be alive")
+        // Player-PowerUp collision
+        Self::pick_up(
+            &mut self.powerups,
+            &self.players,
+            scalar(0.0),
+            |_, player_n| tally.powerups[player_n as usize] += 1,
+        );
+        for powerup in self.powerups.iter() {
+            let player_n = powerup.picked_by.map(|n| n as usize);
+            if let Some(player) = player_n.and_then(|n| self.players[n].as_mut()) {
+                player.powers.grant(powerup.kind);
+            }
+        }
+
+        // Broad phase: only enemies close to exploded bombs and players need
+        // to be checked below, the grid tells which ones.
+        let Self {
+            grid,
+            enemies,
+            bombs,
+            players,
+            converted_by,
+            near_players,
+            ..
+        } = self;
+        grid.build(enemies.iter().map(|enemy| &enemy.entity));
+        let extra_reach = scalar(2.0); // Makes enemies easier to convert
+
+        // First exploded bomb converting each enemy
+        converted_by.clear();
+        converted_by.resize(enemies.len(), None);
+        for (b, bomb) in bombs.iter().enumerate().filter(|(_, bomb)| bomb.exploded) {
+            grid.query(&bomb.entity, extra_reach, |e| {
+                if converted_by[e].is_none()
+                    && bomb
                         .entity
-                        .color;
-                    break 'bombs_loop; // One exploded bomb <=> One player
+                        .collided_with(&enemies.as_slice()[e].entity, extra_reach)
+                {
+                    converted_by[e] = Some(b);
                 }
+            });
+        }
+        // Enemies that may touch a player
+        near_players.clear();
+        near_players.resize(enemies.len(), false);
+        for player in players.iter().flatten() {
+            grid.query(&player.entity, scalar(2.0), |e| near_players[e] = true);
+        }
+
+        'enemies_loop: for (e, enemy) in self.enemies.iter_mut().enumerate() {
+            // Bomb-Enemy collision, convert enemies to player color
+            // One exploded bomb <=> One player, who may be out of the game by now
+            // Repel bombs push enemies away instead.
+            if let Some(b) = self.converted_by[e] {
+                let bomb = &self.bombs.as_slice()[b];
+                let owner = bomb
+                    .who_exploded
+                    .expect("Exploded bomb should have a 'owner'");
+                if bomb.kind == BombKind::Repel {
+                    let away = enemy.entity.center() - bomb.entity.center();
+                    enemy.entity.advance(away, Bomb::REPEL_PUSH);
+                    enemy.entity.resolve(&self.obstacles);
+                } else if let Some(player) = self.players[owner as usize].as_ref() {
+                    enemy.entity.color = player.entity.color;
+                    enemy.owner = None;
+                }
+                self.splits.extend(enemy.split());
+            }
+            if !self.near_players[e] {
+                continue;
             }
 
-            // Enemy-Player collision (same color)
+            // Enemy-Player collision (same color). In versus it's sent at the
+            // closest opponent, if there's any left. Tanks take a few. Those
+            // of the boss's color hurt it.
             'players_loop: for player in self.players.iter().flatten() {
-                if enemy.entity.color == player.entity.color
-                    && enemy.entity.collided_with(&player.entity, 2.0)
+                if enemy.owner != Some(player.player_number)
+                    && enemy.entity.color == player.entity.color
+                    && enemy.entity.collided_with(&player.entity, scalar(2.0))
                 {
-                    enemy.kill();
-                    enemies_killed += 1;
+                    tally.enemies[player.player_number as usize] += 1;
+                    if let Some(boss) = self.boss.as_ref() {
+                        if boss.entity.color == enemy.entity.color {
+                            tally.boss_damage += 1;
+                        }
+                    }
+                    if enemy.withstand(player) {
+                        break 'players_loop;
+                    }
+                    let target = self
+                        .players
+                        .iter()
+                        .flatten()
+                        .filter(|p| self.versus && p.player_number != player.player_number)
+                        .min_by(|a, b| {
+                            let a = a.entity.distance(&player.entity);
+                            let b = b.entity.distance(&player.entity);
+                            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                        });
+                    match target {
+                        Some(target) => enemy.send(player.player_number, target),
+                        None => enemy.kill(),
+                    }
                     break 'players_loop; // Only one player should be able to "eat" one enemy
                 }
             }
 
-            // Enemy-Player collision (different colors)
+            // Enemy-Player collision (different colors). A shield takes
+            // the hit instead of the player, once.
             #[allow(unused_labels)]
-            'players_loop: for player in self.players.iter().flatten() {
-                if enemy.entity.color != player.entity.color
+            'players_loop: for player in self.players.iter_mut().flatten() {
+                if enemy.owner != Some(player.player_number)
+                    && enemy.entity.color != player.entity.color
                     && !enemy.just_spawned()
-                    && enemy.entity.collided_with(&player.entity, -2.0)
+                    && !player.invulnerable()
+                    && enemy.entity.collided_with(&player.entity, scalar(-2.0))
                 {
+                    if player.powers.take(PowerKind::Shield) {
+                        enemy.kill();
+                        continue 'enemies_loop;
+                    }
                     // Player dies
                     self.killer = Some(Enemy::new(0, enemy.entity.position, enemy.entity.color));
+                    self.victim = Some(player.player_number);
                     enemy.kill();
                     break 'enemies_loop; // Stop everything.
                 }
             }
         }
 
+        for half in self.splits.drain(..) {
+            self.enemies.insert(half);
+        }
         self.prune();
 
-        (enemies_killed, bombs_exploded)
+        if let Some(boss) = self.boss.as_mut() {
+            if boss.hit(tally.boss_damage) {
+                self.boss = None;
+                tally.boss_defeated = true;
+            }
+        }
+
+        tally
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::types::{Coord, ScalarExt},
+        entities::behavior::Behavior,
+        graphics::palette::{DRAW_COLOR_A, DRAW_COLOR_B},
+    };
+    use fastrand::Rng;
+    use std::time::{Duration, Instant};
+
+    const ALL_PLAYERS: [PlayerN; 4] = [PlayerN::P1, PlayerN::P2, PlayerN::P3, PlayerN::P4];
+
+    fn random_color(rng: &Rng) -> u16 {
+        if rng.bool() {
+            DRAW_COLOR_A
+        } else {
+            DRAW_COLOR_B
+        }
+    }
+
+    /// Four players in a crowded screen, with bombs exploding everywhere.
+    /// With `deadly` some enemies are old enough to kill.
+    fn crowded(seed: u64, enemies: usize, deadly: bool) -> EntityManager {
+        let rng = Rng::with_seed(seed);
+        let mut entities = EntityManager::with_capacity(enemies, MAX_BOMBS);
+        for player_n in ALL_PLAYERS {
+            let mut player = Player::new(player_n);
+            player.entity.position = Coord::new(rng.f64() * 150.0, rng.f64() * 150.0);
+            player.entity.color = random_color(&rng);
+            entities.players[player_n as usize] = Some(player);
+        }
+        for _ in 0..MAX_BOMBS {
+            let mut bomb = Bomb::new(&Coord::new(
+                rng.f64() * 140.0 + 10.0,
+                rng.f64() * 140.0 + 10.0,
+            ));
+            if rng.bool() {
+                bomb.exploded = true;
+                bomb.who_exploded = Some(ALL_PLAYERS[rng.usize(..4)]);
+                bomb.entity.size = scalar(rng.f64() * 70.0 + 9.0);
+            }
+            entities.bombs.insert(bomb);
+        }
+        for _ in 0..enemies {
+            let position = Coord::new(rng.f64() * 155.0, rng.f64() * 155.0);
+            let mut enemy = Enemy::new(0, position, random_color(&rng));
+            if deadly {
+                enemy.entity.life = rng.u32(1..=enemy.entity.life);
+            }
+            entities.enemies.insert(enemy);
+        }
+        entities
+    }
+
+    /// `process_collisions` without broad phase: every enemy against every
+    /// bomb and player.
+    fn brute_force_collisions(entities: &mut EntityManager) -> Tally {
+        let mut tally = Tally::default();
+        'bombs_loop: for bomb in entities.bombs.iter_mut() {
+            for player in entities.players.iter().flatten() {
+                if bomb.entity.collided_with(&player.entity, scalar(2.0)) && !bomb.exploded {
+                    tally.bombs[player.player_number as usize] += 1;
+                    tally.bomb_points[player.player_number as usize] += bomb.kind.score();
+                    bomb.exploded = true;
+                    bomb.who_exploded = Some(player.player_number);
+                    continue 'bombs_loop;
+                }
+            }
+        }
+        'enemies_loop: for enemy in entities.enemies.iter_mut() {
+            for bomb in entities.bombs.iter() {
+                if bomb.exploded && bomb.entity.collided_with(&enemy.entity, scalar(2.0)) {
+                    let owner = bomb
+                        .who_exploded
+                        .expect("Exploded bomb should have a 'owner'");
+                    if let Some(player) = entities.players[owner as usize].as_ref() {
+                        enemy.entity.color = player.entity.color;
+                    }
+                    break;
+                }
+            }
+            for player in entities.players.iter().flatten() {
+                if enemy.entity.color == player.entity.color
+                    && enemy.entity.collided_with(&player.entity, scalar(2.0))
+                {
+                    enemy.kill();
+                    tally.enemies[player.player_number as usize] += 1;
+                    break;
+                }
+            }
+            for player in entities.players.iter().flatten() {
+                if enemy.entity.color != player.entity.color
+                    && !enemy.just_spawned()
+                    && enemy.entity.collided_with(&player.entity, scalar(-2.0))
+                {
+                    entities.killer =
+                        Some(Enemy::new(0, enemy.entity.position, enemy.entity.color));
+                    entities.victim = Some(player.player_number);
+                    enemy.kill();
+                    break 'enemies_loop;
+                }
+            }
+        }
+        entities.prune();
+        tally
+    }
+
+    fn outcome(entities: &EntityManager) -> Vec<(Scalar, Scalar, u16, u32)> {
+        entities
+            .enemies
+            .iter()
+            .chain(entities.killer.as_ref())
+            .map(|e| {
+                let entity = &e.entity;
+                (
+                    entity.position.x,
+                    entity.position.y,
+                    entity.color,
+                    entity.life,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn broad_phase_changes_nothing() {
+        let mut kills = 0;
+        for seed in 0..200 {
+            let mut entities = crowded(seed, MAX_ENEMIES, seed % 2 == 0);
+            let mut reference = crowded(seed, MAX_ENEMIES, seed % 2 == 0);
+            assert_eq!(
+                entities.process_collisions(),
+                brute_force_collisions(&mut reference)
+            );
+            assert_eq!(outcome(&entities), outcome(&reference));
+            assert_eq!(entities.victim, reference.victim);
+            kills += entities.killer.is_some() as u32;
+        }
+        // Both ways of ending the frame are covered
+        assert!(kills > 0 && kills < 200);
+    }
+
+    /// Not run by default, use `cargo run_tests bench -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_collisions() {
+        const FRAMES: u64 = 500;
+        for enemies in [MAX_ENEMIES, 2 * MAX_ENEMIES, 4 * MAX_ENEMIES] {
+            let (mut grid_time, mut brute_time) = (Duration::ZERO, Duration::ZERO);
+            let mut checks = 0;
+            for seed in 0..FRAMES {
+                let mut entities = crowded(seed, enemies, false);
+                let start = Instant::now();
+                entities.process_collisions();
+                grid_time += start.elapsed();
+
+                // Narrow phase checks done by the grid (before pruning)
+                let entities = crowded(seed, enemies, false);
+                let mut grid = Grid::with_capacity(enemies);
+                grid.build(entities.enemies.iter().map(|enemy| &enemy.entity));
+                let queries = entities
+                    .bombs
+                    .iter()
+                    .filter(|bomb| bomb.exploded)
+                    .map(|bomb| &bomb.entity)
+                    .chain(entities.players.iter().flatten().map(|p| &p.entity));
+                for entity in queries {
+                    grid.query(entity, scalar(2.0), |_| checks += 1);
+                }
+
+                let mut reference = crowded(seed, enemies, false);
+                let start = Instant::now();
+                brute_force_collisions(&mut reference);
+                brute_time += start.elapsed();
+            }
+            println!(
+                "{enemies:4} enemies: grid {:6.1} µs/frame, {:5} checks/frame | \
+                 brute force {:6.1} µs/frame, {:5} checks/frame",
+                grid_time.as_secs_f64() * 1e6 / FRAMES as f64,
+                checks / FRAMES,
+                brute_time.as_secs_f64() * 1e6 / FRAMES as f64,
+                enemies * (MAX_BOMBS + 2 * ALL_PLAYERS.len()),
+            );
+        }
+    }
+
+    #[test]
+    fn versus_sends_enemies() {
+        let mut entities = EntityManager::new();
+        entities.versus = true;
+        for (player_n, x, color) in [
+            (PlayerN::P1, 50.0, DRAW_COLOR_B),
+            (PlayerN::P2, 100.0, DRAW_COLOR_B),
+        ] {
+            let mut player = Player::new(player_n);
+            player.entity.position = Coord::new(x, 50.0);
+            player.entity.color = color;
+            entities.players[player_n as usize] = Some(player);
+        }
+        let mut enemy = Enemy::new(0, Coord::new(50.0, 50.0), DRAW_COLOR_B);
+        enemy.entity.life = 100;
+        entities.enemies.insert(enemy);
+
+        // Absorbed by P1, it goes after P2 in the color that hurts them
+        let tally = entities.process_collisions();
+        assert_eq!(tally.enemies, [1, 0, 0, 0]);
+        let sent = entities.enemies.iter().next().expect("enemy sent");
+        assert_eq!(sent.owner, Some(PlayerN::P1));
+        assert_eq!(sent.entity.color, DRAW_COLOR_A);
+
+        // Its owner can't be hurt by it, even once it's old enough to
+        for enemy in entities.enemies.iter_mut() {
+            enemy.entity.life = 100;
+        }
+        entities.process_collisions();
+        assert!(entities.killer.is_none());
+        assert_eq!(entities.enemies.len(), 1);
+        entities.update_state();
+        let sent = entities.enemies.iter().next().expect("enemy sent");
+        assert_eq!(sent.follows, Some(PlayerN::P2));
+    }
+
+    #[test]
+    fn splitters_and_tanks() {
+        let mut entities = EntityManager::new();
+        let player = entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 always starts");
+        player.entity.position = Coord::new(20.0, 20.0);
+        player.entity.color = DRAW_COLOR_B;
+
+        // A bomb converts the splitter, which splits in two
+        let mut bomb = Bomb::new(&Coord::new(100.0, 100.0));
+        bomb.exploded = true;
+        bomb.who_exploded = Some(PlayerN::P1);
+        bomb.entity.size = scalar(30.0);
+        entities.bombs.insert(bomb);
+        let splitter =
+            Enemy::new(0, Coord::new(110.0, 110.0), DRAW_COLOR_A).with_behavior(Behavior::Splitter);
+        entities.enemies.insert(splitter);
+        entities.process_collisions();
+        assert_eq!(entities.enemies.len(), 2);
+        for half in entities.enemies.iter() {
+            assert_eq!(half.behavior, Behavior::Chaser);
+            assert_eq!(half.entity.color, DRAW_COLOR_B);
+        }
+        entities.process_collisions();
+        assert_eq!(entities.enemies.len(), 2);
+        entities.enemies.clear();
+
+        // Every absorb counts, the last one takes the tank
+        let tank = Enemy::new(0, Coord::new(22.0, 20.0), DRAW_COLOR_B)
+            .with_behavior(Behavior::Tank(Behavior::TANK_ABSORBS));
+        entities.enemies.insert(tank);
+        for _ in 0..Behavior::TANK_ABSORBS {
+            assert_eq!(entities.enemies.len(), 1);
+            for tank in entities.enemies.iter_mut() {
+                tank.entity.position = Coord::new(22.0, 20.0);
+            }
+            assert_eq!(entities.process_collisions().enemies, [1, 0, 0, 0]);
+        }
+        assert_eq!(entities.enemies.len(), 0);
+    }
+
+    #[test]
+    fn bomb_kinds() {
+        let mut entities = EntityManager::new();
+        let player = entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 always starts");
+        player.entity.position = Coord::new(20.0, 20.0);
+
+        // A chain bomb sets off the bombs its wave reaches, they all score
+        let chain = Bomb::new(&Coord::new(20.0, 20.0)).with_kind(BombKind::Chain);
+        entities.bombs.insert(chain);
+        entities.bombs.insert(Bomb::new(&Coord::new(40.0, 20.0)));
+        let tally = entities.process_collisions();
+        assert_eq!(tally.bombs, [1, 0, 0, 0]);
+        assert_eq!(tally.bomb_points, [BombKind::Chain.score(), 0, 0, 0]);
+        for _ in 0..8 {
+            entities.update_state();
+        }
+        let tally = entities.process_collisions();
+        assert_eq!(tally.bomb_points, [BombKind::Standard.score(), 0, 0, 0]);
+        entities.bombs.clear();
+
+        // A repel bomb pushes enemies away, in their color
+        let mut repel = Bomb::new(&Coord::new(20.0, 20.0)).with_kind(BombKind::Repel);
+        repel.explode(PlayerN::P1, Coord::default());
+        repel.entity.size = scalar(30.0);
+        entities.bombs.insert(repel);
+        entities
+            .enemies
+            .insert(Enemy::new(0, Coord::new(40.0, 22.0), DRAW_COLOR_A));
+        entities.process_collisions();
+        let enemy = entities.enemies.iter().next().expect("not absorbed");
+        assert!(enemy.entity.position.x > scalar(40.0));
+        assert_eq!(enemy.entity.color, DRAW_COLOR_A);
+        entities.bombs.clear();
+        entities.enemies.clear();
+
+        // A delayed bomb goes along with its player until dropped, and goes
+        // off for them a while later
+        let delayed = Bomb::new(&Coord::new(20.0, 20.0)).with_kind(BombKind::Delayed);
+        entities.bombs.insert(delayed);
+        assert_eq!(entities.process_collisions().bombs, [0; 4]);
+        assert_eq!(entities.carriers(), [true, false, false, false]);
+        entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 always starts")
+            .right();
+        entities.update_state();
+        let center = |entities: &EntityManager| {
+            let bomb = entities.bombs.iter().next().expect("bomb");
+            bomb.entity.center()
+        };
+        let player = entities.players[PlayerN::P1 as usize].as_ref();
+        assert_eq!(center(&entities), player.expect("P1").entity.center());
+        assert!(!entities.drop_bomb(PlayerN::P2));
+        assert!(entities.drop_bomb(PlayerN::P1));
+        assert_eq!(entities.carriers(), [false; 4]);
+        for _ in 1..Bomb::FUSE_FRAMES {
+            assert_eq!(entities.update().bombs, [0; 4]);
+        }
+        let tally = entities.update();
+        assert_eq!(tally.bomb_points, [BombKind::Delayed.score(), 0, 0, 0]);
+    }
+
+    #[test]
+    fn bosses_take_absorbs_and_bomb_waves() {
+        let mut entities = EntityManager::new();
+        let player = entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 always starts");
+        player.entity.position = Coord::new(20.0, 20.0);
+        player.entity.color = DRAW_COLOR_B;
+        let mut boss = Boss::new(Coord::new(100.0, 100.0), 6);
+        let center = boss.entity.center();
+        boss.entity.color = DRAW_COLOR_A;
+        entities.boss = Some(boss);
+        let absorb = |entities: &mut EntityManager| {
+            let enemy = Enemy::new(0, Coord::new(22.0, 20.0), DRAW_COLOR_B);
+            entities.enemies.insert(enemy);
+            entities.process_collisions()
+        };
+
+        // Only enemies of its color hurt it
+        assert_eq!(absorb(&mut entities).boss_damage, 0);
+        let boss = entities.boss.as_mut().expect("boss");
+        boss.entity.color = DRAW_COLOR_B;
+        assert_eq!(absorb(&mut entities).boss_damage, 1);
+
+        // A bomb wave hurts it once
+        let mut bomb = Bomb::new(&(center - Coord::new(4.0, 4.0)));
+        bomb.explode(PlayerN::P1, Coord::default());
+        entities.bombs.insert(bomb);
+        assert_eq!(entities.process_collisions().boss_damage, BOSS_BOMB_DAMAGE);
+        assert_eq!(entities.process_collisions().boss_damage, 0);
+        assert_eq!(entities.boss.as_ref().map(|boss| boss.health), Some(1));
+
+        let tally = absorb(&mut entities);
+        assert!(tally.boss_defeated);
+        assert!(entities.boss.is_none());
+    }
+
+    #[test]
+    fn power_ups() {
+        let mut entities = EntityManager::new();
+        let player = entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 should always exist");
+        player.entity.position = Coord::new(50.0, 50.0);
+        player.entity.color = DRAW_COLOR_B;
+        let center = player.entity.center();
+        let has = |entities: &EntityManager, kind| entities.has_power(kind);
+
+        // Picked up by touching it
+        let powerup = PowerUp::new(&Coord::new(48.0, 48.0), PowerKind::Shield);
+        entities.powerups.insert(powerup);
+        let tally = entities.update();
+        assert_eq!(tally.powerups, [1, 0, 0, 0]);
+        assert!(has(&entities, PowerKind::Shield));
+        entities.update();
+        assert_eq!(entities.powerups.len(), 0);
+
+        // The shield takes a hit, once
+        for _ in 0..2 {
+            let mut enemy = Enemy::new(0, center, DRAW_COLOR_A);
+            enemy.entity.life = 100;
+            entities.enemies.insert(enemy);
+        }
+        entities.process_collisions();
+        assert!(!has(&entities, PowerKind::Shield));
+        assert!(entities.killer.is_some());
+        assert_eq!(
+            entities
+                .enemies
+                .iter()
+                .filter(|e| e.entity.life > 0)
+                .count(),
+            0
+        );
+
+        // Enemies at half speed while time is slowed, magnets pull the ones
+        // of the player's color
+        entities.killer = None;
+        entities.enemies.clear();
+        let player = entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 should always exist");
+        player.powers.grant(PowerKind::SlowTime);
+        player.powers.grant(PowerKind::Magnet);
+        for (x, color) in [(70.0, DRAW_COLOR_B), (30.0, DRAW_COLOR_A)] {
+            entities
+                .enemies
+                .insert(Enemy::new(0, Coord::new(x, 52.0), color));
+        }
+        entities.update_state();
+        let xs: Vec<f64> = entities
+            .enemies
+            .iter()
+            .map(|e| e.entity.position.x.to_f64())
+            .collect();
+        // Half a step towards the player is at most 0.35, the magnet pulls more
+        assert!(xs[0] < 69.5);
+        assert!(xs[1] > 30.0 && xs[1] < 30.36);
+    }
+
+    #[test]
+    fn leaving_players_are_forgotten() {
+        let mut entities = EntityManager::new();
+        for (player_n, x) in [(PlayerN::P2, 100.0), (PlayerN::P3, 140.0)] {
+            let mut player = Player::new(player_n);
+            player.entity.position = Coord::new(x, 50.0);
+            entities.players[player_n as usize] = Some(player);
+        }
+        let mut enemy = Enemy::new(0, Coord::new(110.0, 50.0), DRAW_COLOR_B);
+        enemy.owner = Some(PlayerN::P3);
+        entities.enemies.insert(enemy);
+        entities.update_state();
+        let enemy = entities.enemies.iter().next().expect("enemy");
+        assert_eq!(enemy.follows, Some(PlayerN::P2));
+
+        // P2 leaves: the enemy goes after P1, the closest one left but its owner
+        assert!(entities.remove_player(PlayerN::P2).is_some());
+        assert!(entities.remove_player(PlayerN::P2).is_none());
+        let enemy = entities.enemies.iter().next().expect("enemy");
+        assert_eq!(enemy.follows, Some(PlayerN::P1));
+
+        // Enemies sent by a player who left can hurt anyone
+        entities.remove_player(PlayerN::P3);
+        let enemy = entities.enemies.iter().next().expect("enemy");
+        assert_eq!(enemy.owner, None);
+    }
+
+    #[test]
+    fn collisions_follow_spawn_order() {
+        let mut entities = EntityManager::new();
+        let player = entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 should always exist");
+        player.entity.position = Coord::new(50.0, 50.0);
+        player.entity.color = DRAW_COLOR_B;
+        // Two enemies touching the player, the oldest one is the killer
+        for x in [50.0, 51.0] {
+            let mut enemy = Enemy::new(0, Coord::new(x, 50.0), DRAW_COLOR_A);
+            enemy.entity.life = 100;
+            assert!(entities.enemies.insert(enemy));
+        }
+
+        entities.process_collisions();
+        let killer = entities.killer.as_ref().expect("player should be dead");
+        assert_eq!(entities.victim, Some(PlayerN::P1));
+        assert_eq!(killer.entity.position.x, scalar(50.0));
+        assert_eq!(entities.enemies.len(), 1);
+        assert!(entities
+            .enemies
+            .iter()
+            .all(|enemy| enemy.entity.position.x == scalar(51.0)));
     }
 }
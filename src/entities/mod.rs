@@ -1,6 +1,13 @@
+pub mod arena;
+pub mod behavior;
 pub mod bomb;
+pub mod boss;
 pub mod enemy;
 mod entity;
+mod grid;
+pub mod layout;
 pub mod manager;
+pub mod obstacle;
 pub mod player;
+pub mod powerup;
 pub mod traits;
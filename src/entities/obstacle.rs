@@ -0,0 +1,123 @@
+use crate::{
+    common::types::{scalar, Coord, Scalar, ScalarExt},
+    graphics::palette,
+    wasm4,
+};
+
+/// What an obstacle looks like, and whether it moves
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ObstacleKind {
+    Wall,
+    Pillar,
+    /// Goes back and forth, pushing what's in its way
+    Bumper,
+}
+
+impl ObstacleKind {
+    /// In the order of their code in arena layouts
+    pub const ALL: [ObstacleKind; 3] = [
+        ObstacleKind::Wall,
+        ObstacleKind::Pillar,
+        ObstacleKind::Bumper,
+    ];
+}
+
+/// A rectangle in the arena that players and enemies can't go through (bomb
+/// waves can).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Obstacle {
+    pub kind: ObstacleKind,
+    pub position: Coord,
+    pub size: Coord,
+    /// Bumpers: the ends of their run, the one they're heading to first
+    from: Coord,
+    to: Coord,
+    speed: Scalar,
+}
+
+impl Obstacle {
+    /// An obstacle standing still, until it's given a run to go back and
+    /// forth along.
+    pub fn new(kind: ObstacleKind, position: Coord, size: Coord) -> Self {
+        Self {
+            kind,
+            position,
+            size,
+            from: position,
+            to: position,
+            speed: scalar(0.0),
+        }
+    }
+
+    /// Goes back and forth from where it is to `to`.
+    pub fn moving(self, to: Coord, speed: Scalar) -> Self {
+        Self { to, speed, ..self }
+    }
+
+    /// Moves a bumper along its run, turning back at its ends. Steps are
+    /// taken along each axis, so that they land exactly on the ends.
+    pub fn update(&mut self) {
+        let to_go = self.to - self.position;
+        let (speed, zero) = (self.speed, scalar(0.0));
+        self.position += Coord {
+            x: to_go.x.clamp(zero - speed, speed),
+            y: to_go.y.clamp(zero - speed, speed),
+        };
+        if self.position == self.to {
+            std::mem::swap(&mut self.from, &mut self.to);
+        }
+    }
+
+    pub fn center(&self) -> Coord {
+        self.position + self.size.scale(scalar(0.5))
+    }
+
+    /// Whether `point` is inside the obstacle, or less than `margin` away.
+    pub fn contains(&self, point: Coord, margin: Scalar) -> bool {
+        let end = self.position + self.size;
+        point.x > self.position.x - margin
+            && point.x < end.x + margin
+            && point.y > self.position.y - margin
+            && point.y < end.y + margin
+    }
+
+    pub fn draw(&self) {
+        let (x, y) = (self.position.x.to_i32(), self.position.y.to_i32());
+        let (width, height) = (self.size.x.to_i32() as u32, self.size.y.to_i32() as u32);
+        match self.kind {
+            ObstacleKind::Wall => palette::set_draw_color(0x44),
+            ObstacleKind::Pillar => palette::set_draw_color(0x43),
+            ObstacleKind::Bumper => palette::set_draw_color(0x41),
+        }
+        wasm4::rect(x, y, width, height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bumpers_go_back_and_forth() {
+        let bumper = Coord::new(10.0, 10.0);
+        let mut bumper = Obstacle::new(ObstacleKind::Bumper, bumper, Coord::new(8.0, 4.0))
+            .moving(Coord::new(12.0, 10.0), scalar(0.5));
+        for _ in 0..4 {
+            bumper.update();
+        }
+        assert_eq!(bumper.position, Coord::new(12.0, 10.0));
+        bumper.update();
+        assert_eq!(bumper.position, Coord::new(11.5, 10.0));
+        for _ in 0..3 {
+            bumper.update();
+        }
+        assert_eq!(bumper.position, Coord::new(10.0, 10.0));
+        assert!(bumper.contains(Coord::new(17.5, 13.5), scalar(0.0)));
+        assert!(!bumper.contains(Coord::new(19.0, 12.0), scalar(0.0)));
+        assert!(bumper.contains(Coord::new(19.0, 12.0), scalar(2.0)));
+
+        let mut wall = Obstacle::new(ObstacleKind::Wall, Coord::default(), Coord::new(10.0, 2.0));
+        wall.update();
+        assert_eq!(wall.position, Coord::new(0.0, 0.0));
+    }
+}
@@ -1,14 +1,16 @@
 use super::{
     entity::Entity,
+    obstacle::Obstacle,
+    powerup::{PowerKind, Powers},
     traits::{Movable, Visible},
 };
 use crate::{
-    common::types::Coord,
+    common::types::{scalar, Coord, Scalar, ScalarExt},
     graphics::{
         draw_utils,
         palette::{self, DRAW_COLOR_A, DRAW_COLOR_B, DRAW_COLOR_PLAIN},
     },
-    wasm4::SCREEN_SIZE,
+    wasm4::{self, SCREEN_SIZE},
 };
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -21,9 +23,28 @@ pub enum PlayerN {
 pub struct Player {
     pub entity: Entity,
     pub player_number: PlayerN,
+    /// Frames before the next dash, the dash itself being the first ones
+    pub dash_cooldown: u32,
+    /// Where the dash in progress goes
+    dash_direction: Coord,
+    /// Power-ups picked up, while they last
+    pub powers: Powers,
+    /// Where the player last moved
+    pub heading: Coord,
 }
 
 impl Player {
+    /// Frames a dash lasts
+    const DASH_FRAMES: u32 = 8;
+    /// Speed of a dash, compared to the normal one
+    const DASH_SPEED: Scalar = scalar(3.0);
+    /// Frames enemies can't hurt the player, from the start of a dash
+    const DASH_I_FRAMES: u32 = 14;
+    /// Frames from a dash to the next one
+    pub const DASH_COOLDOWN: u32 = 90;
+    /// Disks left behind by a dash
+    const TRAIL_LENGTH: u32 = 3;
+
     pub fn new(player_number: PlayerN) -> Self {
         Player {
             player_number,
@@ -33,14 +54,14 @@ impl Player {
 
     pub fn left(&mut self) {
         self.entity.direction = Coord {
-            x: -1.0,
+            x: scalar(-1.0),
             y: self.entity.direction.y,
         };
     }
 
     pub fn right(&mut self) {
         self.entity.direction = Coord {
-            x: 1.0,
+            x: scalar(1.0),
             y: self.entity.direction.y,
         };
     }
@@ -48,18 +69,41 @@ impl Player {
     pub fn up(&mut self) {
         self.entity.direction = Coord {
             x: self.entity.direction.x,
-            y: -1.0,
+            y: scalar(-1.0),
         };
     }
 
     pub fn down(&mut self) {
         self.entity.direction = Coord {
             x: self.entity.direction.x,
-            y: 1.0,
+            y: scalar(1.0),
         };
     }
     pub fn stop(&mut self) {
-        self.entity.direction = Coord { x: 0.0, y: 0.0 };
+        self.entity.direction = Coord::new(0.0, 0.0);
+    }
+
+    /// Bursts forward, in the direction the player is steering, if the last
+    /// dash is far enough behind. Returns whether it did.
+    pub fn dash(&mut self) -> bool {
+        let direction = self.entity.direction;
+        if self.dash_cooldown > 0 || direction.norm() <= Scalar::EPSILON {
+            return false;
+        }
+        self.dash_direction = direction;
+        self.dash_cooldown = Self::DASH_COOLDOWN;
+        true
+    }
+
+    /// Frames of the dash in progress done, None when not dashing.
+    fn dash_frame(&self) -> Option<u32> {
+        let done = Self::DASH_COOLDOWN - self.dash_cooldown;
+        (self.dash_cooldown > 0 && done < Self::DASH_FRAMES).then_some(done)
+    }
+
+    /// Enemies can't hurt the player during a dash, and right after it.
+    pub fn invulnerable(&self) -> bool {
+        self.dash_cooldown > Self::DASH_COOLDOWN - Self::DASH_I_FRAMES
     }
 
     pub fn toggle_color(&mut self) {
@@ -73,37 +117,89 @@ impl Player {
 
 impl Default for Player {
     fn default() -> Self {
-        const DEFAULT_SIZE: f64 = 7.0;
-        const DEFAULT_SPEED: f64 = 1.40;
+        const DEFAULT_SIZE: Scalar = scalar(7.0);
+        const DEFAULT_SPEED: Scalar = scalar(1.40);
 
         Self {
             entity: Entity {
                 position: Coord {
-                    x: (SCREEN_SIZE as f64 - DEFAULT_SIZE) / 2.0,
-                    y: (SCREEN_SIZE as f64 - DEFAULT_SIZE) / 2.0,
+                    x: (Scalar::from(SCREEN_SIZE) - DEFAULT_SIZE) / scalar(2.0),
+                    y: (Scalar::from(SCREEN_SIZE) - DEFAULT_SIZE) / scalar(2.0),
                 },
-                direction: Coord { x: 0.0, y: 0.0 },
+                direction: Coord::new(0.0, 0.0),
                 size: DEFAULT_SIZE,
                 speed: DEFAULT_SPEED,
                 color: DRAW_COLOR_B,
                 life: 1,
             },
             player_number: PlayerN::P1,
+            dash_cooldown: 0,
+            dash_direction: Coord::new(0.0, 0.0),
+            powers: Powers::default(),
+            heading: Coord::new(1.0, 0.0),
         }
     }
 }
 
 impl Movable for Player {
-    fn update_position(&mut self) {
-        self.entity.update_position();
+    fn update_position(&mut self, obstacles: &[Obstacle]) {
+        if self.dash_frame().is_some() {
+            self.entity
+                .advance(self.dash_direction, self.entity.speed * Self::DASH_SPEED);
+            self.entity.resolve(obstacles);
+        } else {
+            self.entity.update_position(obstacles);
+        }
+        if self.entity.direction.norm() > Scalar::EPSILON {
+            self.heading = self.entity.direction;
+        }
+        self.dash_cooldown = self.dash_cooldown.saturating_sub(1);
+        self.powers.tick();
     }
 }
 
 impl Visible for Player {
     fn draw(&self) {
+        // The trail of a dash, smaller and smaller disks behind it
+        if let Some(done) = self.dash_frame() {
+            let step = self
+                .dash_direction
+                .scale(self.entity.speed * Self::DASH_SPEED / self.dash_direction.norm());
+            let center = self.entity.center();
+            palette::set_draw_color(self.entity.color & 0xf);
+            for k in 1..=Self::TRAIL_LENGTH.min(done + 1) {
+                let size = self.entity.size.to_i32() - 1 - k as i32;
+                let position = center - step.scale(Scalar::from(k));
+                wasm4::oval(
+                    position.x.to_i32() - size / 2,
+                    position.y.to_i32() - size / 2,
+                    size as u32,
+                    size as u32,
+                );
+            }
+        }
         self.entity.draw();
+        if self.powers.active(PowerKind::Shield) {
+            palette::set_draw_color(0x40);
+            wasm4::oval(
+                self.entity.position.x.to_i32() - 2,
+                self.entity.position.y.to_i32() - 2,
+                self.entity.size.to_i32() as u32 + 4,
+                self.entity.size.to_i32() as u32 + 4,
+            );
+        }
+        // How far the next dash is, around the disk
+        if self.dash_cooldown > 0 && self.dash_frame().is_none() {
+            let center = self.entity.center();
+            let charged = Self::DASH_COOLDOWN - self.dash_cooldown;
+            draw_utils::draw_meter(
+                center.x.to_i32(),
+                center.y.to_i32(),
+                charged * draw_utils::METER_DOTS / Self::DASH_COOLDOWN,
+            );
+        }
 
-        let radius = self.entity.size / 2.0 - 0.5;
+        let radius = self.entity.radius();
         let center_coord = self.entity.position
             + Coord {
                 x: radius,
@@ -117,50 +213,50 @@ impl Visible for Player {
             PlayerN::P1 => dots.push(center_coord),
             PlayerN::P2 => {
                 dots.push(Coord {
-                    x: center_coord.x - 1.0,
+                    x: center_coord.x - scalar(1.0),
                     y: center_coord.y,
                 });
                 dots.push(Coord {
-                    x: center_coord.x + 1.0,
+                    x: center_coord.x + scalar(1.0),
                     y: center_coord.y,
                 });
             }
             PlayerN::P3 => {
                 dots.push(Coord {
-                    x: center_coord.x - 1.0,
+                    x: center_coord.x - scalar(1.0),
                     y: center_coord.y,
                 });
                 dots.push(Coord {
                     x: center_coord.x,
-                    y: center_coord.y - 1.0,
+                    y: center_coord.y - scalar(1.0),
                 });
                 dots.push(Coord {
-                    x: center_coord.x + 1.0,
+                    x: center_coord.x + scalar(1.0),
                     y: center_coord.y,
                 });
             }
             PlayerN::P4 => {
                 dots.push(Coord {
-                    x: center_coord.x - 1.0,
+                    x: center_coord.x - scalar(1.0),
                     y: center_coord.y,
                 });
                 dots.push(Coord {
                     x: center_coord.x,
-                    y: center_coord.y + 1.0,
+                    y: center_coord.y + scalar(1.0),
                 });
                 dots.push(Coord {
                     x: center_coord.x,
-                    y: center_coord.y - 1.0,
+                    y: center_coord.y - scalar(1.0),
                 });
                 dots.push(Coord {
-                    x: center_coord.x + 1.0,
+                    x: center_coord.x + scalar(1.0),
                     y: center_coord.y,
                 });
             }
         }
         palette::set_draw_color(DRAW_COLOR_PLAIN);
         for center_coord in dots {
-            draw_utils::pixel(center_coord.x as i32, center_coord.y as i32);
+            draw_utils::pixel(center_coord.x.to_i32(), center_coord.y.to_i32());
         }
     }
 }
@@ -168,55 +264,83 @@ impl Visible for Player {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use approx::assert_abs_diff_eq;
 
     #[test]
     fn collided_with() {
         let mut player = Player::new(PlayerN::P1);
-        player.entity.position = Coord { x: 50.0, y: 50.0 };
+        player.entity.position = Coord::new(50.0, 50.0);
         let mut entity = Entity {
-            position: Coord { x: 0.0, y: 0.0 },
-            direction: Coord { x: 0.0, y: 0.0 },
-            size: 10.0,
-            speed: 0.0,
+            position: Coord::new(0.0, 0.0),
+            direction: Coord::new(0.0, 0.0),
+            size: scalar(10.0),
+            speed: scalar(0.0),
             color: DRAW_COLOR_B,
             life: 1,
         };
 
-        assert!(!player.entity.collided_with(&entity, 0.0));
+        assert!(!player.entity.collided_with(&entity, scalar(0.0)));
+
+        entity.position = Coord::new(45.0, 50.0);
+        assert!(player.entity.collided_with(&entity, scalar(0.0)));
+
+        entity.position = Coord::new(50.0, 45.0);
+        entity.size = scalar(10.0);
+        assert!(player.entity.collided_with(&entity, scalar(0.0)));
+    }
 
-        entity.position = Coord { x: 45.0, y: 50.0 };
-        assert!(player.entity.collided_with(&entity, 0.0));
+    #[test]
+    fn dash() {
+        let mut player = Player::new(PlayerN::P1);
+        // Standing still, there's nowhere to dash to
+        assert!(!player.dash());
+        player.right();
+        assert!(player.dash());
+        player.stop();
+        let start = player.entity.position.x;
+        for _ in 0..Player::DASH_FRAMES {
+            assert!(player.invulnerable());
+            player.update_position(&[]);
+            assert!(!player.dash());
+        }
+        let dashed = player.entity.position.x - start;
+        assert_abs_diff_eq!(dashed.to_f64(), 1.4 * 3.0 * 8.0, epsilon = 1e-3);
+        player.update_position(&[]);
+        assert_eq!(player.entity.position.x - start, dashed);
 
-        entity.position = Coord { x: 50.0, y: 45.0 };
-        entity.size = 10.0;
-        assert!(player.entity.collided_with(&entity, 0.0));
+        for _ in Player::DASH_FRAMES + 1..Player::DASH_COOLDOWN {
+            player.update_position(&[]);
+        }
+        assert!(!player.invulnerable());
+        player.up();
+        assert!(player.dash());
     }
 
     #[test]
     fn up() {
         let mut player = Player::new(PlayerN::P1);
         player.up();
-        assert_eq!(player.entity.direction, Coord { x: 0.0, y: -1.0 });
+        assert_eq!(player.entity.direction, Coord::new(0.0, -1.0));
     }
 
     #[test]
     fn down() {
         let mut player = Player::new(PlayerN::P1);
         player.down();
-        assert_eq!(player.entity.direction, Coord { x: 0.0, y: 1.0 });
+        assert_eq!(player.entity.direction, Coord::new(0.0, 1.0));
     }
 
     #[test]
     fn left() {
         let mut player = Player::new(PlayerN::P1);
         player.left();
-        assert_eq!(player.entity.direction, Coord { x: -1.0, y: 0.0 });
+        assert_eq!(player.entity.direction, Coord::new(-1.0, 0.0));
     }
 
     #[test]
     fn right() {
         let mut player = Player::new(PlayerN::P1);
         player.right();
-        assert_eq!(player.entity.direction, Coord { x: 1.0, y: 0.0 });
+        assert_eq!(player.entity.direction, Coord::new(1.0, 0.0));
     }
 }
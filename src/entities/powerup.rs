@@ -0,0 +1,160 @@
+use super::{
+    entity::Entity,
+    player::{Player, PlayerN},
+    traits::{Pickup, Visible},
+};
+use crate::{
+    common::{
+        calibrations::{POWERUP_DURATION, POWERUP_LIFE_SPAN},
+        types::{scalar, Coord, ScalarExt},
+    },
+    graphics::palette,
+    wasm4,
+};
+
+/// What a power-up does to the player who picks it up, for a while
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PowerKind {
+    /// Takes the hit of one enemy of the wrong color
+    Shield,
+    /// Pulls the enemies of the player's color
+    Magnet,
+    /// Enemies move at half their speed
+    SlowTime,
+    /// Enemies and bombs give double points
+    DoublePoints,
+}
+
+impl PowerKind {
+    pub const ALL: [PowerKind; 4] = [
+        PowerKind::Shield,
+        PowerKind::Magnet,
+        PowerKind::SlowTime,
+        PowerKind::DoublePoints,
+    ];
+
+    fn glyph(self) -> &'static str {
+        match self {
+            PowerKind::Shield => "S",
+            PowerKind::Magnet => "M",
+            PowerKind::SlowTime => "T",
+            PowerKind::DoublePoints => "2",
+        }
+    }
+}
+
+/// The power-ups a player has, with the frames each one has left
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Powers {
+    frames: [u32; PowerKind::ALL.len()],
+}
+
+impl Powers {
+    /// Starts `kind`, or starts it over.
+    pub fn grant(&mut self, kind: PowerKind) {
+        self.frames[kind as usize] = POWERUP_DURATION;
+    }
+
+    pub fn active(&self, kind: PowerKind) -> bool {
+        self.frames[kind as usize] > 0
+    }
+
+    /// Uses `kind` up, returns whether it was active.
+    pub fn take(&mut self, kind: PowerKind) -> bool {
+        let active = self.active(kind);
+        self.frames[kind as usize] = 0;
+        active
+    }
+
+    /// One frame less for every power-up.
+    pub fn tick(&mut self) {
+        for frames in self.frames.iter_mut() {
+            *frames = frames.saturating_sub(1);
+        }
+    }
+}
+
+/// A pickup that gives a power-up to the player who touches it first. It goes
+/// away if nobody does for a while.
+pub struct PowerUp {
+    pub entity: Entity,
+    pub kind: PowerKind,
+    pub picked_by: Option<PlayerN>,
+}
+
+impl PowerUp {
+    // Last frames before going away, blinking
+    const BLINK_FRAMES: u32 = 120;
+
+    pub fn new(pos: &Coord, kind: PowerKind) -> Self {
+        Self {
+            entity: Entity {
+                position: *pos,
+                direction: Coord::default(),
+                size: scalar(10.0),
+                speed: scalar(0.0),
+                color: 0x41,
+                life: POWERUP_LIFE_SPAN,
+            },
+            kind,
+            picked_by: None,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.entity.life = self.entity.life.saturating_sub(1);
+    }
+}
+
+impl Pickup for PowerUp {
+    fn entity(&self) -> &Entity {
+        &self.entity
+    }
+
+    fn available(&self) -> bool {
+        self.picked_by.is_none()
+    }
+
+    fn pick(&mut self, player: &Player) {
+        self.picked_by = Some(player.player_number);
+        self.entity.life = 0;
+    }
+}
+
+impl Visible for PowerUp {
+    fn draw(&self) {
+        if self.entity.life < Self::BLINK_FRAMES && (self.entity.life / 8).is_multiple_of(2) {
+            return;
+        }
+        let (x, y) = (
+            self.entity.position.x.to_i32(),
+            self.entity.position.y.to_i32(),
+        );
+        let size = self.entity.size.to_i32() as u32;
+        palette::set_draw_color(self.entity.color);
+        wasm4::rect(x, y, size, size);
+        palette::set_draw_color(0x4);
+        wasm4::text(self.kind.glyph(), x + 1, y + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn powers_run_out() {
+        let mut powers = Powers::default();
+        powers.grant(PowerKind::Magnet);
+        powers.grant(PowerKind::Shield);
+        assert!(powers.take(PowerKind::Shield));
+        assert!(!powers.take(PowerKind::Shield));
+        for _ in 1..POWERUP_DURATION {
+            powers.tick();
+        }
+        assert!(powers.active(PowerKind::Magnet));
+        assert!(!powers.active(PowerKind::SlowTime));
+        powers.tick();
+        assert!(!powers.active(PowerKind::Magnet));
+    }
+}
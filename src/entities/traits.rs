@@ -1,8 +1,18 @@
-// Trait for anything that can move on the screen
+use super::{entity::Entity, obstacle::Obstacle, player::Player};
+
+// Trait for anything that can move on the screen, around the obstacles
 pub trait Movable {
-    fn update_position(&mut self);
+    fn update_position(&mut self, obstacles: &[Obstacle]);
 }
 // Trait for anything that's visible on screen
 pub trait Visible {
     fn draw(&self);
 }
+// Trait for what players pick up by touching it: bombs and power-ups
+pub trait Pickup {
+    fn entity(&self) -> &Entity;
+    // Whether it can still be picked up
+    fn available(&self) -> bool;
+    // `player` is the first to touch it
+    fn pick(&mut self, player: &Player);
+}
@@ -3,10 +3,79 @@ use crate::{
     wasm4::{
         BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP, GAMEPAD1, GAMEPAD2,
         GAMEPAD3, GAMEPAD4, MOUSE_BUTTONS, MOUSE_LEFT, MOUSE_MIDDLE, MOUSE_RIGHT, MOUSE_X, MOUSE_Y,
-        SCREEN_SIZE,
+        NETPLAY, SCREEN_SIZE,
     },
 };
 
+// NETPLAY register: bit 2 is set during netplay, bits 0-1 hold the local player
+const NETPLAY_ACTIVE: u8 = 0b100;
+const NETPLAY_PLAYER: u8 = 0b11;
+
+/// Whether the game is played over netplay. The same on every client, so the
+/// simulation can depend on it.
+pub fn netplay() -> bool {
+    unsafe { *NETPLAY & NETPLAY_ACTIVE != 0 }
+}
+
+/// The player this client controls during netplay. It's different on every
+/// client: only for what's drawn, the simulation must never depend on it.
+pub fn local_player() -> Option<PlayerN> {
+    if !netplay() {
+        return None;
+    }
+    let players = [PlayerN::P1, PlayerN::P2, PlayerN::P3, PlayerN::P4];
+    Some(players[unsafe { *NETPLAY & NETPLAY_PLAYER } as usize])
+}
+
+/// Whether `player_n` may change what every client shows alike, like the
+/// palette: only the host can in netplay, anybody otherwise.
+pub fn in_charge(player_n: PlayerN) -> bool {
+    player_n == PlayerN::P1 || !netplay()
+}
+
+/// Raw content of the input registers during a frame. This is all a frame
+/// needs from the outside world, replays are made of these.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RawInput {
+    pub gamepads: [u8; 4],
+    pub mouse_x: i16,
+    pub mouse_y: i16,
+    pub mouse_buttons: u8,
+}
+
+impl RawInput {
+    /// Size of the serialized input, see `to_bytes`.
+    pub const SIZE: usize = 9;
+
+    /// Read the registers as they are right now.
+    pub fn read() -> Self {
+        unsafe {
+            Self {
+                gamepads: [*GAMEPAD1, *GAMEPAD2, *GAMEPAD3, *GAMEPAD4],
+                mouse_x: *MOUSE_X,
+                mouse_y: *MOUSE_Y,
+                mouse_buttons: *MOUSE_BUTTONS,
+            }
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let [x0, x1] = self.mouse_x.to_le_bytes();
+        let [y0, y1] = self.mouse_y.to_le_bytes();
+        let [g1, g2, g3, g4] = self.gamepads;
+        [g1, g2, g3, g4, x0, x1, y0, y1, self.mouse_buttons]
+    }
+
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        Self {
+            gamepads: [bytes[0], bytes[1], bytes[2], bytes[3]],
+            mouse_x: i16::from_le_bytes([bytes[4], bytes[5]]),
+            mouse_y: i16::from_le_bytes([bytes[6], bytes[7]]),
+            mouse_buttons: bytes[8],
+        }
+    }
+}
+
 pub enum ControlEvent {
     MouseLeftHold((i16, i16)),
     MouseLeftClick,
@@ -18,67 +87,95 @@ pub enum ControlEvent {
     Right(PlayerN),
     Btn1(PlayerN),
     Btn2(PlayerN),
+    /// X and Z together on any gamepad, or a double middle click
+    Pause,
+    /// X held alone on a gamepad for `Controls::LEAVE_FRAMES`
+    Leave(PlayerN),
+    /// A direction tapped twice quickly, or a double left click for P1
+    Dash(PlayerN),
 }
 /// Handles user actions (mainly keyboard and mouse actions)
 pub struct Controls {
-    pub prev_mouse: u8,
-    pub prev_gamepad1: u8,
-    pub prev_gamepad2: u8,
-    pub prev_gamepad3: u8,
-    pub prev_gamepad4: u8,
+    pub prev: RawInput,
+    // Frames since the last middle click, to tell double clicks
+    since_middle_click: u8,
+    // Frames X has been held alone on each gamepad, to tell leaving
+    btn1_held: [u8; 4],
+    // Frames since the last left click, to tell double clicks
+    since_left_click: u8,
+    // Last direction tapped on each gamepad and frames since, to tell dashes
+    last_tap: [u8; 4],
+    since_tap: [u8; 4],
 }
 impl Controls {
     const MOUSE_AREA_PADDING: i16 = 20; // Extra space around play area to allow mouse events.
+    pub const DOUBLE_CLICK_FRAMES: u8 = 20;
+    pub const LEAVE_FRAMES: u8 = 120;
+    const DOUBLE_TAP_FRAMES: u8 = 15;
     pub fn new() -> Self {
+        Self::with_previous(RawInput::read())
+    }
+
+    /// Controls that remember `prev` as the input of the previous frame.
+    pub fn with_previous(prev: RawInput) -> Self {
         Self {
-            prev_mouse: unsafe { *MOUSE_BUTTONS },
-            prev_gamepad1: unsafe { *GAMEPAD1 },
-            prev_gamepad2: unsafe { *GAMEPAD2 },
-            prev_gamepad3: unsafe { *GAMEPAD3 },
-            prev_gamepad4: unsafe { *GAMEPAD4 },
+            prev,
+            since_middle_click: Self::DOUBLE_CLICK_FRAMES,
+            btn1_held: [0; 4],
+            since_left_click: Self::DOUBLE_CLICK_FRAMES,
+            last_tap: [0; 4],
+            since_tap: [Self::DOUBLE_TAP_FRAMES; 4],
         }
     }
 
-    /// Read from peripherals and return everything that's happening
-    pub fn update(&mut self) -> Vec<ControlEvent> {
+    /// Turn what the peripherals read this frame into everything that's happening
+    pub fn update(&mut self, input: RawInput) -> Vec<ControlEvent> {
         // Return value
         let mut event = vec![];
 
         // Local vars
-        let mouse = unsafe { *MOUSE_BUTTONS };
-        let just_pressed_mouse = mouse & (mouse ^ self.prev_mouse);
+        let mouse = input.mouse_buttons;
+        let just_pressed_mouse = mouse & (mouse ^ self.prev.mouse_buttons);
 
-        let gamepad1 = unsafe { *GAMEPAD1 };
-        let gamepad2 = unsafe { *GAMEPAD2 };
-        let gamepad3 = unsafe { *GAMEPAD3 };
-        let gamepad4 = unsafe { *GAMEPAD4 };
+        let [gamepad1, gamepad2, gamepad3, gamepad4] = input.gamepads;
+        let [prev_gamepad1, prev_gamepad2, prev_gamepad3, prev_gamepad4] = self.prev.gamepads;
 
-        let just_pressed_gamepad1 = gamepad1 & (gamepad1 ^ self.prev_gamepad1);
-        let just_pressed_gamepad2 = gamepad2 & (gamepad2 ^ self.prev_gamepad2);
-        let just_pressed_gamepad3 = gamepad3 & (gamepad3 ^ self.prev_gamepad3);
-        let just_pressed_gamepad4 = gamepad4 & (gamepad4 ^ self.prev_gamepad4);
+        let just_pressed_gamepad1 = gamepad1 & (gamepad1 ^ prev_gamepad1);
+        let just_pressed_gamepad2 = gamepad2 & (gamepad2 ^ prev_gamepad2);
+        let just_pressed_gamepad3 = gamepad3 & (gamepad3 ^ prev_gamepad3);
+        let just_pressed_gamepad4 = gamepad4 & (gamepad4 ^ prev_gamepad4);
 
         // Check mouse
-        if mouse & MOUSE_LEFT != 0
-            && self.mouse_in_play_area_within_padding(Self::MOUSE_AREA_PADDING)
-        {
-            let mouse_pos = self.get_mouse_pos();
+        let mouse_pos = (input.mouse_x, input.mouse_y);
+        let mouse_in_area =
+            Self::mouse_in_play_area_within_padding(mouse_pos, Self::MOUSE_AREA_PADDING);
+        if mouse & MOUSE_LEFT != 0 && mouse_in_area {
             event.push(ControlEvent::MouseLeftHold(mouse_pos));
         }
-        if just_pressed_mouse & MOUSE_RIGHT != 0
-            && self.mouse_in_play_area_within_padding(Self::MOUSE_AREA_PADDING)
-        {
+        if just_pressed_mouse & MOUSE_RIGHT != 0 && mouse_in_area {
             event.push(ControlEvent::MouseRightClick);
         }
-        if just_pressed_mouse & MOUSE_LEFT != 0
-            && self.mouse_in_play_area_within_padding(Self::MOUSE_AREA_PADDING)
-        {
+        self.since_left_click = self.since_left_click.saturating_add(1);
+        if just_pressed_mouse & MOUSE_LEFT != 0 && mouse_in_area {
             event.push(ControlEvent::MouseLeftClick);
+            if self.since_left_click < Self::DOUBLE_CLICK_FRAMES {
+                event.push(ControlEvent::Dash(PlayerN::P1));
+                // A third click starts over
+                self.since_left_click = Self::DOUBLE_CLICK_FRAMES;
+            } else {
+                self.since_left_click = 0;
+            }
         }
-        if just_pressed_mouse & MOUSE_MIDDLE != 0
-            && self.mouse_in_play_area_within_padding(Self::MOUSE_AREA_PADDING)
-        {
-            event.push(ControlEvent::MouseMiddleClick);
+        self.since_middle_click = self.since_middle_click.saturating_add(1);
+        if just_pressed_mouse & MOUSE_MIDDLE != 0 && mouse_in_area {
+            if self.since_middle_click < Self::DOUBLE_CLICK_FRAMES {
+                event.push(ControlEvent::Pause);
+                // A third click starts over
+                self.since_middle_click = Self::DOUBLE_CLICK_FRAMES;
+            } else {
+                event.push(ControlEvent::MouseMiddleClick);
+                self.since_middle_click = 0;
+            }
         }
 
         // Check gamepads
@@ -88,6 +185,15 @@ impl Controls {
             (gamepad3, just_pressed_gamepad3, PlayerN::P3),
             (gamepad4, just_pressed_gamepad4, PlayerN::P4),
         ] {
+            // Once, when X has been held long enough
+            let held = &mut self.btn1_held[player_n as usize];
+            *held = match gamepad & (BUTTON_1 | BUTTON_2) == BUTTON_1 {
+                true => held.saturating_add(1),
+                false => 0,
+            };
+            if *held == Self::LEAVE_FRAMES {
+                event.push(ControlEvent::Leave(player_n));
+            }
             if gamepad & BUTTON_LEFT != 0 {
                 event.push(ControlEvent::Left(player_n));
             }
@@ -100,6 +206,26 @@ impl Controls {
             if gamepad & BUTTON_RIGHT != 0 {
                 event.push(ControlEvent::Right(player_n));
             }
+            // The same direction tapped again soon enough, a third tap starts over
+            let n = player_n as usize;
+            let tapped = just_pressed & (BUTTON_LEFT | BUTTON_RIGHT | BUTTON_UP | BUTTON_DOWN);
+            self.since_tap[n] = self.since_tap[n].saturating_add(1);
+            if tapped != 0 {
+                if tapped & self.last_tap[n] != 0 && self.since_tap[n] < Self::DOUBLE_TAP_FRAMES {
+                    event.push(ControlEvent::Dash(player_n));
+                    self.last_tap[n] = 0;
+                } else {
+                    self.last_tap[n] = tapped;
+                }
+                self.since_tap[n] = 0;
+            }
+            // Both buttons down, as soon as the second one is pressed. The
+            // first one pressed, if any, already had its event.
+            let both_buttons = BUTTON_1 | BUTTON_2;
+            if gamepad & both_buttons == both_buttons && just_pressed & both_buttons != 0 {
+                event.push(ControlEvent::Pause);
+                continue;
+            }
             if just_pressed & BUTTON_1 != 0 {
                 event.push(ControlEvent::Btn1(player_n));
             }
@@ -108,11 +234,7 @@ impl Controls {
             }
         }
 
-        self.prev_gamepad1 = gamepad1;
-        self.prev_gamepad2 = gamepad2;
-        self.prev_gamepad3 = gamepad3;
-        self.prev_gamepad4 = gamepad4;
-        self.prev_mouse = mouse;
+        self.prev = input;
 
         event
     }
@@ -131,15 +253,149 @@ impl Controls {
     /// slightly outside the play area and still register inputs. It's
     /// frustrating to lose control of the disk and die if your mouse pointer
     /// was ever so slighty outside!
-    fn mouse_in_play_area_within_padding(&self, padding: i16) -> bool {
-        let mouse_pos = self.get_mouse_pos();
+    fn mouse_in_play_area_within_padding(mouse_pos: (i16, i16), padding: i16) -> bool {
         mouse_pos.0 >= -padding
             && mouse_pos.0 <= SCREEN_SIZE as i16 + padding
             && mouse_pos.1 >= -padding
             && mouse_pos.1 <= SCREEN_SIZE as i16 + padding
     }
+}
+
+/// Turns a held direction into presses, like a keyboard does: one as soon as
+/// it's held, then one every `DELAY` frames until it's released. For menus.
+#[derive(Default)]
+pub struct Repeater {
+    wait: u8,
+}
+
+impl Repeater {
+    pub const DELAY: u8 = 10;
+
+    /// Whether holding (or not) in this frame counts as a press.
+    pub fn fire(&mut self, held: bool) -> bool {
+        if !held {
+            self.wait = 0;
+            return false;
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            return false;
+        }
+        self.wait = Self::DELAY;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm4::MOUSE_MIDDLE;
+
+    fn is_pause(events: &[ControlEvent]) -> bool {
+        events.iter().any(|e| matches!(e, ControlEvent::Pause))
+    }
+
+    fn gamepad(buttons: u8) -> RawInput {
+        RawInput {
+            gamepads: [0, buttons, 0, 0],
+            ..Default::default()
+        }
+    }
+
+    fn mouse(buttons: u8) -> RawInput {
+        RawInput {
+            mouse_x: 80,
+            mouse_y: 80,
+            mouse_buttons: buttons,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pause_with_both_buttons() {
+        let mut controls = Controls::with_previous(RawInput::default());
+        assert!(is_pause(&controls.update(gamepad(BUTTON_1 | BUTTON_2))));
+        assert!(!is_pause(&controls.update(gamepad(BUTTON_1 | BUTTON_2))));
+        assert!(controls.update(gamepad(0)).is_empty());
+
+        // X first, then Z while holding X
+        let events = controls.update(gamepad(BUTTON_1));
+        assert!(matches!(events[..], [ControlEvent::Btn1(PlayerN::P2)]));
+        let events = controls.update(gamepad(BUTTON_1 | BUTTON_2));
+        assert!(matches!(events[..], [ControlEvent::Pause]));
+    }
+
+    #[test]
+    fn hold_x_to_leave() {
+        let mut controls = Controls::with_previous(RawInput::default());
+        let is_leave = |events: &[ControlEvent]| {
+            events
+                .iter()
+                .any(|e| matches!(e, ControlEvent::Leave(PlayerN::P2)))
+        };
+        let leaves: Vec<bool> = (0..2 * Controls::LEAVE_FRAMES)
+            .map(|_| is_leave(&controls.update(gamepad(BUTTON_1))))
+            .collect();
+        let frames: Vec<usize> = (0..leaves.len()).filter(|&i| leaves[i]).collect();
+        assert_eq!(frames, [Controls::LEAVE_FRAMES as usize - 1]);
+
+        // Letting go, or pressing Z too, starts over
+        controls.update(gamepad(0));
+        for _ in 0..Controls::LEAVE_FRAMES - 1 {
+            assert!(!is_leave(&controls.update(gamepad(BUTTON_1))));
+        }
+        assert!(!is_leave(&controls.update(gamepad(BUTTON_1 | BUTTON_2))));
+        assert!(!is_leave(&controls.update(gamepad(BUTTON_1))));
+    }
+
+    #[test]
+    fn double_tap_to_dash() {
+        let mut controls = Controls::with_previous(RawInput::default());
+        let is_dash = |events: &[ControlEvent]| {
+            events
+                .iter()
+                .any(|e| matches!(e, ControlEvent::Dash(PlayerN::P2)))
+        };
+        let mut tap = |buttons: u8, idle_frames: usize| {
+            for _ in 0..idle_frames {
+                controls.update(gamepad(0));
+            }
+            is_dash(&controls.update(gamepad(buttons)))
+        };
+        assert!(!tap(BUTTON_LEFT, 1));
+        assert!(tap(BUTTON_LEFT, 5));
+        assert!(!tap(BUTTON_LEFT, 5));
+        // Another direction, or too slow
+        assert!(!tap(BUTTON_UP, 5));
+        assert!(!tap(BUTTON_UP, 20));
+        assert!(tap(BUTTON_UP, 5));
+    }
+
+    #[test]
+    fn pause_with_double_click() {
+        let mut controls = Controls::with_previous(RawInput::default());
+        let click = |controls: &mut Controls, idle_frames: usize| {
+            for _ in 0..idle_frames {
+                controls.update(mouse(0));
+            }
+            controls.update(mouse(MOUSE_MIDDLE))
+        };
+        assert!(matches!(
+            click(&mut controls, 1)[..],
+            [ControlEvent::MouseMiddleClick]
+        ));
+        assert!(is_pause(&click(&mut controls, 5)));
+        assert!(!is_pause(&click(&mut controls, 5)));
+        assert!(!is_pause(&click(&mut controls, 30)));
+    }
 
-    fn get_mouse_pos(&self) -> (i16, i16) {
-        unsafe { (*MOUSE_X, *MOUSE_Y) }
+    #[test]
+    fn repeater() {
+        let mut repeater = Repeater::default();
+        let presses: Vec<bool> = (0..25).map(|_| repeater.fire(true)).collect();
+        let frames: Vec<usize> = (0..25).filter(|&i| presses[i]).collect();
+        assert_eq!(frames, [0, 11, 22]);
+        assert!(!repeater.fire(false));
+        assert!(repeater.fire(true));
     }
 }
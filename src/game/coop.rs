@@ -0,0 +1,191 @@
+use super::scores::Scores;
+use crate::{
+    common::calibrations::{DOUBLE_POINTS, INIT_LIVES, NEXT_LIFE_SCORE, SCORE_BOSS},
+    entities::{
+        manager::{EntityManager as Entities, Tally},
+        player::{Player, PlayerN},
+        powerup::PowerKind,
+    },
+};
+
+/// A player of an individual co-op run
+pub struct Contender {
+    pub scores: Scores,
+    /// Score that gives the next extra life
+    pub next_life: u32,
+}
+
+/// Individual co-op: every player has their own lives, score and multiplier,
+/// and is out of the game when their lives run out. Players who joined are
+/// kept here once out, for the results.
+#[derive(Default)]
+pub struct Coop {
+    pub contenders: [Option<Contender>; 4],
+}
+
+impl Coop {
+    /// Adds `player_n`, with their own lives, unless they were already in this
+    /// run: players who are out can't join again.
+    pub fn join(&mut self, entities: &mut Entities, player_n: PlayerN) -> bool {
+        let contender = &mut self.contenders[player_n as usize];
+        if contender.is_some() {
+            return false;
+        }
+        *contender = Some(Contender {
+            scores: Scores::new(),
+            next_life: NEXT_LIFE_SCORE,
+        });
+        let mut player = Player::new(player_n);
+        player.entity.life = INIT_LIVES;
+        entities.players[player_n as usize] = Some(player);
+        true
+    }
+
+    /// Scores what each player did in the frame. Returns whether someone got
+    /// an extra life. Defeating a boss gives every player still in its bonus
+    /// and a life.
+    pub fn update(&mut self, entities: &mut Entities, tally: &Tally) -> bool {
+        let mut extra_life = false;
+        for (n, contender) in self.contenders.iter_mut().enumerate() {
+            let Some(contender) = contender else {
+                continue;
+            };
+            let factor = match &entities.players[n] {
+                Some(player) if player.powers.active(PowerKind::DoublePoints) => DOUBLE_POINTS,
+                _ => 1,
+            };
+            contender.scores.update(
+                tally.enemies[n],
+                tally.bombs[n],
+                tally.bomb_points[n],
+                factor,
+            );
+            if let Some(player) = entities.players[n].as_mut().filter(|_| tally.boss_defeated) {
+                contender.scores.bonus(SCORE_BOSS, factor);
+                player.entity.life = player.entity.life.saturating_add(1);
+                extra_life = true;
+            }
+            if contender.scores.current > contender.next_life {
+                if let Some(player) = entities.players[n].as_mut() {
+                    player.entity.life = player.entity.life.saturating_add(1);
+                    extra_life = true;
+                }
+                contender.next_life = contender.next_life.saturating_mul(2);
+            }
+        }
+        extra_life
+    }
+
+    /// Takes the players without lives left out of the game. Returns whether
+    /// nobody is left.
+    pub fn eliminate(&self, entities: &mut Entities) -> bool {
+        for player in entities.players.iter_mut() {
+            if player.as_ref().is_some_and(|p| p.entity.life == 0) {
+                *player = None;
+            }
+        }
+        entities.players.iter().all(Option::is_none)
+    }
+
+    /// Scores of the best player of the run.
+    pub fn best(&self) -> Option<&Scores> {
+        self.contenders
+            .iter()
+            .flatten()
+            .map(|contender| &contender.scores)
+            .max_by_key(|scores| scores.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::calibrations::SCORE_BOMB;
+
+    #[test]
+    fn players_play_on_their_own() {
+        let mut entities = Entities::new();
+        entities.players[PlayerN::P1 as usize] = None;
+        let mut coop = Coop::default();
+        assert!(coop.join(&mut entities, PlayerN::P1));
+        assert!(coop.join(&mut entities, PlayerN::P3));
+        assert!(!coop.join(&mut entities, PlayerN::P3));
+
+        let mut tally = Tally::default();
+        tally.enemies[PlayerN::P3 as usize] = 2;
+        tally.bombs[PlayerN::P1 as usize] = 1;
+        tally.bomb_points[PlayerN::P1 as usize] = SCORE_BOMB;
+        assert!(!coop.update(&mut entities, &tally));
+        let score = |coop: &Coop, n: PlayerN| {
+            coop.contenders[n as usize]
+                .as_ref()
+                .map(|c| c.scores.current)
+        };
+        assert_eq!(score(&coop, PlayerN::P1), Some(10));
+        assert_eq!(score(&coop, PlayerN::P3), Some(3));
+        assert_eq!(score(&coop, PlayerN::P2), None);
+        assert_eq!(coop.best().map(|s| s.current), Some(10));
+
+        // P3 runs out of lives, and can't come back
+        entities.players[PlayerN::P3 as usize]
+            .as_mut()
+            .expect("P3 joined")
+            .entity
+            .life = 0;
+        assert!(!coop.eliminate(&mut entities));
+        assert!(entities.players[PlayerN::P3 as usize].is_none());
+        assert!(!coop.join(&mut entities, PlayerN::P3));
+        assert_eq!(score(&coop, PlayerN::P3), Some(3));
+
+        entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 joined")
+            .entity
+            .life = 0;
+        assert!(coop.eliminate(&mut entities));
+    }
+
+    #[test]
+    fn extra_lives_are_earned_alone() {
+        let mut entities = Entities::new();
+        let mut coop = Coop::default();
+        coop.join(&mut entities, PlayerN::P1);
+        coop.join(&mut entities, PlayerN::P2);
+        coop.contenders[PlayerN::P2 as usize]
+            .as_mut()
+            .expect("P2 joined")
+            .scores
+            .current = NEXT_LIFE_SCORE;
+        let mut tally = Tally::default();
+        tally.enemies[PlayerN::P2 as usize] = 1;
+        assert!(coop.update(&mut entities, &tally));
+        let lives = |entities: &Entities, n: PlayerN| {
+            entities.players[n as usize].as_ref().map(|p| p.entity.life)
+        };
+        assert_eq!(lives(&entities, PlayerN::P1), Some(INIT_LIVES));
+        assert_eq!(lives(&entities, PlayerN::P2), Some(INIT_LIVES + 1));
+    }
+
+    #[test]
+    fn bosses_reward_everyone_in() {
+        let mut entities = Entities::new();
+        let mut coop = Coop::default();
+        coop.join(&mut entities, PlayerN::P1);
+        coop.join(&mut entities, PlayerN::P2);
+        coop.join(&mut entities, PlayerN::P4);
+        entities.players[PlayerN::P4 as usize] = None;
+        let tally = Tally {
+            boss_defeated: true,
+            ..Default::default()
+        };
+        assert!(coop.update(&mut entities, &tally));
+        for n in [PlayerN::P1, PlayerN::P2] {
+            let player = entities.players[n as usize].as_ref().expect("joined");
+            assert_eq!(player.entity.life, INIT_LIVES + 1);
+            let scores = &coop.contenders[n as usize].as_ref().expect("joined").scores;
+            assert_eq!(scores.current, SCORE_BOSS);
+        }
+        let out = coop.contenders[PlayerN::P4 as usize].as_ref();
+        assert_eq!(out.map(|c| c.scores.current), Some(0));
+    }
+}
@@ -9,6 +9,9 @@ use crate::{
 };
 use fastrand::Rng;
 
+/// Volume of the music (percent of its normal volume) when it's quiet
+const QUIET_MUSIC_VOLUME: u32 = 30;
+
 /// The Environment is responsible for drawing the play area (space), adjust
 /// palette colours, and play music and sound effects. The HUD (score,
 /// lives, etc..) is not part of the enviroment.
@@ -16,6 +19,11 @@ pub struct Environment {
     pub space: Vec<(u8, u8)>,
     pub palette_n: u8,
     pub song_nr: u8,
+    /// Music is played softly, e.g. while the game is paused
+    pub quiet: bool,
+    /// Volume (percent) of the music and of the sound effects
+    pub music_volume: u32,
+    pub sfx_volume: u32,
 }
 impl Environment {
     pub fn new(rng: &Rng) -> Self {
@@ -31,6 +39,9 @@ impl Environment {
             space,
             palette_n: 0,
             song_nr: INTRO_SONG,
+            quiet: false,
+            music_volume: 100,
+            sfx_volume: 100,
         }
     }
 
@@ -42,20 +53,38 @@ impl Environment {
     }
 
     pub fn update(&self, _tick: usize, song_tick: usize) {
-        music::play(song_tick / MUSIC_SPEED_CTRL, self.song_nr);
+        let volume = if self.quiet {
+            self.music_volume * QUIET_MUSIC_VOLUME / 100
+        } else {
+            self.music_volume
+        };
+        music::play(song_tick / MUSIC_SPEED_CTRL, self.song_nr, volume);
         self.draw_space();
     }
 
-    pub fn play_sound_effects(&self, bombs_exploded: bool, extra_life: bool, player_died: bool) {
+    pub fn play_sound_effects(
+        &self,
+        bombs_exploded: bool,
+        extra_life: bool,
+        player_died: bool,
+        player_revived: bool,
+        power_up: bool,
+    ) {
         // We just have very few sound effects.
         if bombs_exploded {
-            effects::bomb_explode();
+            effects::bomb_explode(self.sfx_volume);
         }
         if extra_life {
-            effects::extra_life();
+            effects::extra_life(self.sfx_volume);
         }
         if player_died {
-            effects::death();
+            effects::death(self.sfx_volume);
+        }
+        if player_revived {
+            effects::revive(self.sfx_volume);
+        }
+        if power_up {
+            effects::power_up(self.sfx_volume);
         }
     }
 
@@ -0,0 +1,167 @@
+use super::controls::Repeater;
+
+/// Number of runs kept on the leaderboard
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// A run that made it to the leaderboard
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Entry {
+    pub initials: [u8; 3],
+    pub score: u32,
+    /// Multiplier at the end of the run, the highest it got
+    pub multiplier: u32,
+    pub difficulty: u8,
+    pub players: u8,
+}
+
+/// Best runs played, highest score first. Runs with the same score are
+/// ordered by when they were played, so a tie doesn't take the place of an
+/// older run.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Leaderboard {
+    entries: Vec<Entry>,
+}
+
+impl Leaderboard {
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Score of the #1 run, 0 when nothing was played yet.
+    pub fn high_score(&self) -> u32 {
+        self.entries.first().map_or(0, |entry| entry.score)
+    }
+
+    /// Position (0 based) a run with `score` would take, None if it doesn't
+    /// make it to the leaderboard.
+    pub fn rank(&self, score: u32) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| score > entry.score)
+            .unwrap_or(self.entries.len());
+        (rank < LEADERBOARD_SIZE).then_some(rank)
+    }
+
+    /// Adds `entry` where it belongs, pushing the last run out if the
+    /// leaderboard is full. Returns the position it took.
+    pub fn insert(&mut self, entry: Entry) -> Option<usize> {
+        let rank = self.rank(entry.score)?;
+        self.entries.insert(rank, entry);
+        self.entries.truncate(LEADERBOARD_SIZE);
+        Some(rank)
+    }
+
+    pub fn initials_mut(&mut self, rank: usize) -> Option<&mut [u8; 3]> {
+        self.entries.get_mut(rank).map(|entry| &mut entry.initials)
+    }
+}
+
+/// Arcade style initials entry: up/down change the letter under the cursor,
+/// left/right move the cursor and X confirms the letter.
+pub struct InitialsEntry {
+    pub rank: usize,
+    pub cursor: usize,
+    repeat: Repeater,
+}
+
+impl InitialsEntry {
+    pub fn new(rank: usize) -> Self {
+        Self {
+            rank,
+            cursor: 0,
+            repeat: Repeater::default(),
+        }
+    }
+
+    /// All three letters were confirmed.
+    pub fn is_done(&self) -> bool {
+        self.cursor == 3
+    }
+
+    /// Applies the directions held in this frame, repeated while held.
+    pub fn steer(&mut self, initials: &mut [u8; 3], dx: i8, dy: i8) {
+        if !self.repeat.fire((dx, dy) != (0, 0)) || self.is_done() {
+            return;
+        }
+        self.cursor = (self.cursor as i32 + dx.signum() as i32).clamp(0, 2) as usize;
+        let letter = &mut initials[self.cursor];
+        let index = (*letter as i32 - b'A' as i32 - dy.signum() as i32).rem_euclid(26);
+        *letter = b'A' + index as u8;
+    }
+
+    pub fn confirm(&mut self) {
+        self.cursor = (self.cursor + 1).min(3);
+        self.repeat = Repeater::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32) -> Entry {
+        Entry {
+            initials: *b"AAA",
+            score,
+            multiplier: 1,
+            difficulty: 0,
+            players: 1,
+        }
+    }
+
+    #[test]
+    fn keeps_best_runs_in_order() {
+        let mut leaderboard = Leaderboard::default();
+        assert_eq!(leaderboard.high_score(), 0);
+        assert_eq!(leaderboard.rank(0), None);
+        for score in [50, 10, 30, 70, 20, 60, 40, 90, 80, 100] {
+            assert!(leaderboard.insert(entry(score)).is_some());
+        }
+        assert_eq!(leaderboard.high_score(), 100);
+        assert_eq!(leaderboard.rank(5), None);
+        assert_eq!(leaderboard.insert(entry(35)), Some(7));
+        assert_eq!(leaderboard.entries().len(), LEADERBOARD_SIZE);
+        let scores: Vec<u32> = leaderboard.entries().iter().map(|e| e.score).collect();
+        assert_eq!(scores, [100, 90, 80, 70, 60, 50, 40, 35, 30, 20]);
+    }
+
+    #[test]
+    fn ties_go_below() {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.insert(Entry {
+            initials: *b"OLD",
+            ..entry(10)
+        });
+        assert_eq!(leaderboard.insert(entry(10)), Some(1));
+        assert_eq!(leaderboard.entries()[0].initials, *b"OLD");
+    }
+
+    #[test]
+    fn initials_entry() {
+        let mut initials = *b"AAA";
+        let mut entry = InitialsEntry::new(0);
+        // Down goes back from A to Z, held keys wait before repeating
+        entry.steer(&mut initials, 0, 1);
+        entry.steer(&mut initials, 0, 1);
+        assert_eq!(initials, *b"ZAA");
+        entry.steer(&mut initials, 0, 0);
+        entry.steer(&mut initials, 0, -1);
+        assert_eq!(initials, *b"AAA");
+        entry.confirm();
+        entry.steer(&mut initials, 0, 0);
+        entry.steer(&mut initials, 0, -1);
+        for _ in 0..=Repeater::DELAY {
+            entry.steer(&mut initials, 0, -1);
+        }
+        assert_eq!(initials, *b"ACA");
+        entry.confirm();
+        entry.confirm();
+        assert!(entry.is_done());
+        entry.steer(&mut initials, 0, -1);
+        assert_eq!(initials, *b"ACA");
+    }
+}
@@ -1,36 +1,56 @@
 mod controls;
+mod coop;
 mod environment;
-mod flags;
+mod leaderboard;
+pub mod replay;
+mod revive;
+mod save;
 mod scores;
+mod screens;
 mod timers;
+mod versus;
+mod waves;
 
-use controls::{ControlEvent, Controls};
+use controls::{ControlEvent, Controls, RawInput};
+use coop::Coop;
 use environment::Environment;
-use flags::Flags;
+use leaderboard::Entry;
+use replay::{Playback, Replay};
+use revive::Revive;
+use save::{GameMode, Save, Settings, MAX_VOLUME};
 use scores::Scores;
+use screens::{HowToPlay, ScreenName, ScreenStack, Title};
 use timers::Timers;
+use versus::Versus;
+use waves::{Waves, BOSS_ATTACKS};
 
 use crate::{
     common::calibrations::{
-        Calibrations, BOMB_FRAME_FREQ, DEATH_COUNTDOWN_DURATION, DIFF_MUL_PROGRESSION, ENEMY_FRAME,
-        EN_COL_FRAME, INIT_LIVES, MAX_BOMBS, MAX_ENEMIES, MUSIC_SPEED_CTRL, RESPITE_DURATION,
+        Calibrations, ARENA_ROTATION, BOMB_FRAME_FREQ, BOSS_HEALTH, DEATH_COUNTDOWN_DURATION,
+        DIFF_MUL_PROGRESSION, DOUBLE_POINTS, ENEMY_FRAME, EN_COL_FRAME, INIT_LIVES, MAX_BOMBS,
+        MAX_ENEMIES, MAX_POWERUPS, MUSIC_SPEED_CTRL, POWERUP_FRAME_FREQ, RESPITE_DURATION,
+        SCORE_BOSS,
     },
-    common::types::Coord,
+    common::types::{scalar, Coord, Scalar, ScalarExt},
     entities::{
-        bomb::Bomb,
+        behavior::Behavior,
+        bomb::{Bomb, BombKind},
+        boss::Boss,
         enemy::Enemy,
-        manager::EntityManager as Entities,
+        layout::{LAYOUTS, ROTATING},
+        manager::{EntityManager as Entities, Tally},
         player::{Player, PlayerN},
+        powerup::{PowerKind, PowerUp},
         traits::Visible,
     },
     graphics::{
         draw_utils::{self},
         palette::{DRAW_COLOR_A, DRAW_COLOR_B},
-        screen::{self, ScreenName},
+        screen,
     },
     sound::{
         effects,
-        music::{GAME_OVER_SONG, GAME_SONG_START, VOICE_NOTES},
+        music::{BOSS_SONG, GAME_SONG_START, VOICE_NOTES},
     },
     wasm4::{self, SCREEN_SIZE},
 };
@@ -40,9 +60,33 @@ pub struct Game {
     timers: Timers,
     calibrations: Calibrations,
     scores: Scores,
-    flags: Flags,
+    /// Players of an individual co-op run, None when P1 holds everybody's lives
+    coop: Option<Coop>,
+    /// The match being played in versus mode
+    versus: Option<Versus>,
+    /// Downed players of a co-op run with revives
+    revive: Option<Revive>,
+    /// Bursts of enemies on top of the steady spawns
+    waves: Waves,
+    /// Index in `LAYOUTS` of the arena played in
+    arena: usize,
+    /// Whether the arena changes with the difficulty, for the whole run
+    rotating_arenas: bool,
     environment: Environment,
     controls: Controls,
+    /// Frames left before the palette changes, as asked with Z or a middle
+    /// click. Either may be the start of a pause, which cancels it.
+    palette_change: Option<u8>,
+    /// What's on disk, loaded once and written back when something changes
+    save: Save,
+    /// This client's own saved settings, set aside during netplay (see `new`)
+    own_settings: Option<Settings>,
+    /// Screens shown, see `screens`
+    screens: ScreenStack,
+    /// Record a replay of every run (see `replay` feature)
+    recording: bool,
+    replay: Option<Replay>,
+    playback: Option<Playback>,
 }
 
 // Ideally it should be refactored quite a bit, maybe with the addition of an
@@ -52,8 +96,16 @@ impl Game {
         let entities = Entities::new();
         let timers = Timers::new();
         let calibrations = Calibrations::new(0);
+        let mut save = Save::load();
+        // The simulation can't depend on each client's own disk in netplay:
+        // the session starts from the default mode, arena and palette, which
+        // only the menus change from there, through the inputs every client
+        // shares.
+        let own_settings = controls::netplay().then_some(save.settings);
+        if own_settings.is_some() {
+            save.settings = save.settings.sharing(Settings::default());
+        }
         let scores = Scores::new();
-        let flags = Flags::new();
         let environment = Environment::new(&calibrations.rng);
         let controls = Controls::new();
 
@@ -62,234 +114,522 @@ impl Game {
             timers,
             calibrations,
             scores,
-            flags,
+            coop: None,
+            versus: None,
+            revive: None,
+            waves: Waves::default(),
+            arena: 0,
+            rotating_arenas: false,
             environment,
             controls,
+            palette_change: None,
+            save,
+            own_settings,
+            screens: ScreenStack::new(Box::new(Title)),
+            recording: cfg!(feature = "replay"),
+            replay: None,
+            playback: None,
         }
     }
 
+    /// Called once, when the cart starts: applies what was saved on disk.
+    pub fn start(&mut self) {
+        self.apply_settings();
+    }
+
+    /// A game that plays `replay` back: it starts the run with the recorded
+    /// seed and then reads inputs from the replay instead of the registers.
+    // Not used by the cart itself, replays are played back natively.
+    #[allow(dead_code)]
+    pub fn from_replay(replay: &Replay) -> Self {
+        let mut game = Self::new();
+        game.controls = Controls::with_previous(replay.previous);
+        game.screens = ScreenStack::new(Box::new(HowToPlay));
+        game.playback = Some(Playback::new(replay));
+        game
+    }
+
     /// A game restarts when the player runs out of lives and decides to play
     /// again. Use a new random seed for the rng, to keep the universe fresh.
+    /// The co-op mode and the arena are picked from the settings here, for
+    /// the whole run.
     pub fn restart(&mut self) {
-        self.entities = Entities::new();
-        self.calibrations = Calibrations::new(self.timers.frame_count);
+        self.entities.reset();
+        self.calibrations = match self.playback.as_mut().and_then(|p| p.seed.take()) {
+            Some(seed) => Calibrations::with_seed(seed),
+            None => Calibrations::new(self.timers.frame_count),
+        };
         self.environment = Environment::new(&self.calibrations.rng);
+        self.apply_settings();
         self.timers = Timers::new();
         self.scores = Scores::new();
-        self.flags = Flags::new();
-        self.flags.current_screen = ScreenName::MainGame;
-        self.entities.players[PlayerN::P1 as usize]
-            .as_mut()
-            .expect("P1 should always exist")
-            .entity
-            .life = INIT_LIVES;
-    }
-
-    /// Read what actions the user has done and update the game accordingly.
-    pub fn process_inputs(&mut self) {
-        let control_events = self.controls.update();
-        let mut continue_action = false;
-        let movement_enabled = self.entities.killer.is_none();
-
-        for event in control_events {
-            match event {
-                ControlEvent::Left(player_n) => {
-                    if movement_enabled {
-                        if let Some(player) = self.entities.players[player_n as usize].as_mut() {
-                            player.left();
-                        }
-                    }
-                }
-                ControlEvent::Down(player_n) => {
-                    if movement_enabled {
-                        if let Some(player) = self.entities.players[player_n as usize].as_mut() {
-                            player.down();
-                        }
-                    }
-                }
-                ControlEvent::Up(player_n) => {
-                    if movement_enabled {
-                        if let Some(player) = self.entities.players[player_n as usize].as_mut() {
-                            player.up();
-                        }
-                    }
-                }
-                ControlEvent::Right(player_n) => {
-                    if movement_enabled {
-                        if let Some(player) = self.entities.players[player_n as usize].as_mut() {
-                            player.right();
-                        }
+        self.waves = Waves::default();
+        let mode = self.mode();
+        self.coop = (mode == GameMode::Individual).then(Coop::default);
+        self.versus = None;
+        self.revive = (mode == GameMode::Revive).then(Revive::default);
+        let arena = self.arena_setting();
+        self.rotating_arenas = arena == ROTATING;
+        match self.rotating_arenas {
+            true => self.enter_arena(ARENA_ROTATION[self.calibrations.difficulty as usize]),
+            false => self.enter_arena(arena as usize),
+        }
+        match self.coop.as_mut() {
+            Some(coop) => {
+                coop.join(&mut self.entities, PlayerN::P1);
+            }
+            // No lives with revives, players are downed instead
+            None if self.revive.is_some() => (),
+            None => {
+                self.entities.players[PlayerN::P1 as usize]
+                    .as_mut()
+                    .expect("P1 should always exist")
+                    .entity
+                    .life = INIT_LIVES;
+            }
+        }
+    }
+
+    /// The mode runs are played in: the one of the replay played back, or
+    /// the saved one.
+    fn mode(&self) -> GameMode {
+        match &self.playback {
+            Some(playback) => playback.mode,
+            None => self.save.settings.mode,
+        }
+    }
+
+    /// The arena setting runs are played with, like `mode`.
+    fn arena_setting(&self) -> u8 {
+        match &self.playback {
+            Some(playback) => playback.arena,
+            None => self.save.settings.arena,
+        }
+    }
+
+    /// Turns the game that just restarted into a versus match, which P1 joined.
+    fn start_versus(&mut self) {
+        let mut versus = Versus::default();
+        versus.join(&mut self.entities, PlayerN::P1);
+        self.versus = Some(versus);
+        self.entities.versus = true;
+    }
+
+    /// Adds `player_n` to the game. In shared co-op their lives go to P1's
+    /// pool, in individual co-op they bring their own, unless they were
+    /// already out of this run. A versus match can only be joined before it
+    /// starts, and downed players wait for a teammate to come back.
+    fn join(&mut self, player_n: PlayerN) -> bool {
+        if let Some(coop) = self.coop.as_mut() {
+            return coop.join(&mut self.entities, player_n);
+        }
+        if let Some(revive) = self.revive.as_mut() {
+            return revive.join(&mut self.entities, player_n);
+        }
+        if let Some(versus) = self.versus.as_mut() {
+            return versus.join(&mut self.entities, player_n);
+        }
+        self.entities.players[player_n as usize] = Some(Player::new(player_n));
+        if let Some(player) = self.entities.players[PlayerN::P1 as usize].as_mut() {
+            player.entity.life += INIT_LIVES;
+        }
+        true
+    }
+
+    /// Takes `player_n` out of the game. In shared co-op the lives they
+    /// brought leave the pool with them, though never the last one. In
+    /// individual co-op they forfeit the lives they had left, in versus the
+    /// match, with revives the rest of the run. P1 hosts the game and can't
+    /// leave, nor can anyone while the world is stopped by a death.
+    fn leave(&mut self, player_n: PlayerN) -> bool {
+        if player_n == PlayerN::P1 || self.entities.killer.is_some() {
+            return false;
+        }
+        if let Some(versus) = self.versus.as_mut() {
+            versus.leave(&mut self.entities, player_n);
+            return true;
+        }
+        if let Some(revive) = self.revive.as_mut() {
+            return revive.leave(&mut self.entities, player_n);
+        }
+        if self.entities.remove_player(player_n).is_none() {
+            return false;
+        }
+        if self.coop.is_none() && self.revive.is_none() {
+            if let Some(player) = self.entities.players[PlayerN::P1 as usize].as_mut() {
+                player.entity.life = player.entity.life.saturating_sub(INIT_LIVES).max(1);
+            }
+        }
+        true
+    }
+
+    /// What the players can do in game, whatever the mode: join or leave it
+    /// and change the palette. Everything else is up to the screen. The
+    /// `carriers` of a bomb at the start of the frame dropped it with Btn2
+    /// instead of changing the palette.
+    fn process_inputs(&mut self, events: &[ControlEvent], carriers: [bool; 4]) {
+        for event in events {
+            match *event {
+                // New player joins!
+                ControlEvent::Btn1(player_n)
+                    if self.entities.players[player_n as usize].is_none() =>
+                {
+                    if !self.join(player_n) {
+                        continue;
                     }
+                    wasm4::trace(
+                        "Player ".to_owned()
+                            + (player_n as u8 + 1).to_string().as_str()
+                            + " joined!",
+                    );
+                    effects::new_player(self.environment.sfx_volume);
                 }
-                ControlEvent::Btn1(player_n) => {
-                    if movement_enabled {
-                        if let Some(player) = self.entities.players[player_n as usize].as_mut() {
-                            player.toggle_color();
-                        }
-                    }
-                    // New player joins!
-                    if self.entities.players[player_n as usize].is_none() {
-                        wasm4::trace(
-                            "Player ".to_owned()
-                                + (player_n as u8 + 1).to_string().as_str()
-                                + " joined!",
-                        );
-                        self.entities.players[player_n as usize] = Some(Player::new(player_n));
-                        if let Some(player) = self.entities.players[PlayerN::P1 as usize].as_mut() {
-                            player.entity.life += INIT_LIVES;
-                        }
-                        effects::new_player();
+                ControlEvent::Leave(player_n)
+                    if self.entities.players[player_n as usize].is_some()
+                        || self.downed(player_n) =>
+                {
+                    if !self.leave(player_n) {
+                        continue;
                     }
-                    continue_action = true;
+                    wasm4::trace(
+                        "Player ".to_owned() + (player_n as u8 + 1).to_string().as_str() + " left!",
+                    );
+                    effects::leave(self.environment.sfx_volume);
                 }
-
-                ControlEvent::Btn2(player_n) => {
+                ControlEvent::Btn2(player_n) if carriers[player_n as usize] => (),
+                ControlEvent::Btn2(player_n) if controls::in_charge(player_n) => {
                     wasm4::trace(
                         "Player ".to_owned()
                             + (player_n as u8 + 1).to_string().as_str()
                             + " changed palette!",
                     );
-                    self.environment.set_palette(self.environment.palette_n + 1);
-                }
-
-                ControlEvent::MouseRightClick => {
-                    if movement_enabled {
-                        if let Some(player) = self.entities.players[PlayerN::P1 as usize].as_mut() {
-                            player.toggle_color();
-                        }
-                    }
-                }
-                ControlEvent::MouseLeftClick => {
-                    continue_action = true;
+                    self.palette_change = Some(Controls::DOUBLE_CLICK_FRAMES);
                 }
                 ControlEvent::MouseMiddleClick => {
-                    self.environment.set_palette(self.environment.palette_n + 1);
-                }
-                ControlEvent::MouseLeftHold((mouse_x, mouse_y)) => {
-                    if movement_enabled {
-                        if let Some(player) = self.entities.players[PlayerN::P1 as usize].as_mut() {
-                            let new_d_x = mouse_x as f64
-                                - player.entity.position.x
-                                - player.entity.size / 2.0;
-                            let new_d_y = mouse_y as f64
-                                - player.entity.position.y
-                                - player.entity.size / 2.0;
-                            if new_d_x.abs() > 1.0 || new_d_y.abs() > 1.0 {
-                                player.entity.direction = Coord {
-                                    x: new_d_x,
-                                    y: new_d_y,
-                                };
-                            }
-                        }
-                    }
+                    self.palette_change = Some(Controls::DOUBLE_CLICK_FRAMES)
                 }
+                _ => (),
             }
         }
+    }
 
-        // In a non-playing screen, waiting for input (pretty much the start
-        // screen and the "Press X to start/continue" ones)
-        if continue_action {
-            match self.flags.current_screen {
-                ScreenName::Title => self.flags.current_screen = ScreenName::HowToPlay,
-                ScreenName::HowToPlay | ScreenName::GameOver => self.restart(),
-                ScreenName::MainGame => (),
-            }
+    /// Changes the palette once nothing made a pause of the Z press or the
+    /// middle click that asked for it in time.
+    fn palette_tick(&mut self, events: &[ControlEvent]) {
+        if events.iter().any(|e| matches!(e, ControlEvent::Pause)) {
+            self.palette_change = None;
+        }
+        self.palette_change = self.palette_change.map(|frames| frames - 1);
+        if self.palette_change == Some(0) {
+            self.palette_change = None;
+            self.next_palette();
         }
     }
 
     /// Called at every frame. It's the beating heart of the game. It must call
     /// self.timers.tick() exactly once every time it's called. It's called at
-    /// 60fps and it returns early depending on the current screen.
+    /// 60fps, what happens then is up to the screens on the stack.
     pub fn update(&mut self) {
         self.timers.tick();
         self.environment
             .update(self.timers.frame_count, self.timers.song_tick);
-        self.process_inputs();
+        let input = self.next_input();
+        let previous_input = self.controls.prev;
+        let events = self.controls.update(input);
+        let was_running = self.screens.contains(ScreenName::MainGame);
+        // The inputs were for the screen shown until now
+        let in_game = matches!(
+            self.screens.top(),
+            Some(ScreenName::MainGame | ScreenName::Versus)
+        );
 
-        // First we show the title screen
-        if self.flags.current_screen == ScreenName::Title {
-            screen::title(self.timers.frame_count);
-            return;
+        // Screens get the game, so they're out of it while they run
+        let mut screens = std::mem::take(&mut self.screens);
+        let carriers = self.entities.carriers();
+        screens.update(self, &events);
+        self.palette_tick(&events);
+        if in_game {
+            self.process_inputs(&events, carriers);
         }
-        // Then how to play
-        if self.flags.current_screen == ScreenName::HowToPlay {
-            screen::how_to_play(self.timers.frame_count);
+        screens.draw(self);
+        self.screens = screens;
+
+        self.record(was_running, previous_input, input);
+    }
+
+    /// Whether `player_n` is down, waiting for help, in a run with revives.
+    fn downed(&self, player_n: PlayerN) -> bool {
+        self.revive
+            .as_ref()
+            .is_some_and(|revive| revive.ghosts[player_n as usize].is_some())
+    }
+
+    /// The HUD shown in game and on the game over screen. Individual co-op
+    /// has a HUD of its own, without the high score. With revives the hearts
+    /// are the players standing, and the downed ones blinking after them.
+    /// The health of the boss shows under the scores.
+    fn draw_hud(&self, show_high_score: bool) {
+        if let Some(boss) = &self.entities.boss {
+            draw_utils::draw_boss_bar(boss.health, boss.max_health, boss.entity.color);
+        }
+        if let Some(coop) = &self.coop {
+            let corners = std::array::from_fn(|n| {
+                let contender = coop.contenders[n].as_ref()?;
+                let lives = self.entities.players[n]
+                    .as_ref()
+                    .map_or(0, |player| player.entity.life);
+                Some((lives, contender.scores.current, contender.scores.multiplier))
+            });
+            draw_utils::draw_coop_hud(&corners);
             return;
         }
-
-        // When the game starts the HUD will be always visible
+        let lives = match &self.revive {
+            Some(revive) => {
+                let standing = self.entities.players.iter().flatten().count();
+                let downed = match (self.timers.frame_count / 15).is_multiple_of(2) {
+                    true => revive.ghosts.iter().flatten().count(),
+                    false => 0,
+                };
+                (standing + downed) as u32
+            }
+            None => {
+                self.entities.players[PlayerN::P1 as usize]
+                    .as_ref()
+                    .expect("P1 should always exist")
+                    .entity
+                    .life
+            }
+        };
         draw_utils::draw_hud(
-            self.entities.players[PlayerN::P1 as usize]
-                .as_ref()
-                .expect("P1 should always exist")
-                .entity
-                .life,
+            lives,
             self.scores.current,
-            self.scores.high,
+            self.save.leaderboard.high_score(),
             self.scores.multiplier,
-            self.flags.current_screen != ScreenName::GameOver
-                || !self.flags.new_high_score
-                || self.flags.current_screen == ScreenName::GameOver
-                    && (self.timers.frame_count / 2) % 10 < 5,
+            show_high_score,
         );
+    }
 
-        // Game over screen
-        if self.flags.current_screen == ScreenName::GameOver {
-            screen::game_over(self.timers.frame_count);
-            return;
+    /// Entities, or only the players and the blinking killer while the world
+    /// is stopped by a death. Obstacles, the boss and downed players lie
+    /// under everything else, in netplay a pointer shows each client the
+    /// player they control.
+    fn draw_world(&self) {
+        for obstacle in self.entities.obstacles.iter() {
+            obstacle.draw();
+        }
+        if let Some(boss) = &self.entities.boss {
+            boss.draw();
+        }
+        if let Some(revive) = &self.revive {
+            revive.draw(self.timers.frame_count);
+        }
+        if let Some(killer) = &self.entities.killer {
+            for player in self.entities.players.iter().flatten() {
+                player.draw();
+            }
+            if !(self.timers.death_countdown / 10).is_multiple_of(2) {
+                killer.draw()
+            }
+        } else {
+            self.entities.draw();
         }
+        let local =
+            controls::local_player().and_then(|n| self.entities.players[n as usize].as_ref());
+        if let Some(player) = local {
+            draw_utils::draw_pointer(
+                player.entity.center().x.to_i32(),
+                player.entity.position.y.to_i32(),
+            );
+        }
+    }
 
-        // Stop-the-world death event
-        if self.entities.killer.is_some() {
-            self.death_tick();
+    /// Round wins of the versus match, in the players' corners.
+    fn draw_versus_hud(&self) {
+        let Some(versus) = &self.versus else {
             return;
-        }
+        };
+        let wins = std::array::from_fn(|n| versus.entrants[n].then_some(versus.wins[n]));
+        draw_utils::draw_versus_hud(versus.round, &wins);
+    }
 
-        // End-game. Player ran out of lives, save high score and flag for game-over
-        if self.entities.players[PlayerN::P1 as usize]
-            .as_ref()
-            .expect("P1 should always exist")
-            .entity
-            .life
-            == 0
-        {
-            self.flags.new_high_score = self.scores.current > self.scores.high;
-            self.scores.high = std::cmp::max(self.scores.current, self.scores.high);
-            self.flags.current_screen = ScreenName::GameOver;
-            self.environment.song_nr = GAME_OVER_SONG;
-            self.timers.song_tick = 0;
-
-            // Save high score
-            let high_score: u32 = self.scores.high;
-            self.scores.save_high_score(high_score);
+    /// Round wins of every player, for the end of a versus match.
+    fn draw_versus_results(&self) {
+        let Some(versus) = &self.versus else {
+            return;
+        };
+        let rows: Vec<(u8, u8)> = (0..versus.entrants.len())
+            .filter(|&n| versus.entrants[n])
+            .map(|n| (n as u8 + 1, versus.wins[n]))
+            .collect();
+        let champion = versus.champion().map(|player_n| player_n as u8 + 1);
+        let best = rows.iter().position(|row| Some(row.0) == champion);
+        screen::versus_results(self.timers.frame_count, &rows, best);
+    }
+
+    /// Results of an individual co-op run, for the game over screen.
+    fn draw_coop_results(&self) {
+        let Some(coop) = &self.coop else {
             return;
+        };
+        let rows: Vec<(u8, u32, u32)> = coop
+            .contenders
+            .iter()
+            .enumerate()
+            .filter_map(|(n, contender)| {
+                let scores = &contender.as_ref()?.scores;
+                Some((n as u8 + 1, scores.current, scores.multiplier))
+            })
+            .collect();
+        let best = rows
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, row)| (row.1, std::cmp::Reverse(*i)))
+            .map_or(0, |(i, _)| i);
+        screen::coop_results(&rows, best);
+    }
+
+    /// Whether the run is over: P1 ran out of the shared lives, in individual
+    /// co-op every player is out, with revives nobody is left standing.
+    /// Players out of lives leave here.
+    fn run_over(&mut self) -> bool {
+        match &self.coop {
+            Some(coop) => coop.eliminate(&mut self.entities),
+            None if self.revive.is_some() => self.entities.players.iter().all(Option::is_none),
+            None => {
+                self.entities.players[PlayerN::P1 as usize]
+                    .as_ref()
+                    .expect("P1 should always exist")
+                    .entity
+                    .life
+                    == 0
+            }
         }
+    }
 
-        self.update_difficulty();
-        let (enemies_killed, bombs_exploded) = self.entities.update();
+    /// Score and multiplier the run goes on the leaderboard with: the team's,
+    /// or the best player's in individual co-op.
+    fn run_score(&self) -> (u32, u32) {
+        match self.coop.as_ref().and_then(Coop::best) {
+            Some(best) => (best.current, best.multiplier),
+            None => (self.scores.current, self.scores.multiplier),
+        }
+    }
 
-        self.update_score(enemies_killed, bombs_exploded);
+    /// Adds the run that just ended to the leaderboard and statistics, and
+    /// returns the position it got there. If it made it to the leaderboard
+    /// it's saved right away, with initials to be changed on the next screen.
+    fn save_run(&mut self) -> Option<usize> {
+        let (score, multiplier) = self.run_score();
+        let players = match (&self.coop, &self.revive) {
+            (Some(coop), _) => coop.contenders.iter().flatten().count(),
+            (None, Some(revive)) => revive.players(&self.entities),
+            (None, None) => self.entities.players.iter().flatten().count(),
+        };
+        let rank = self.save.leaderboard.insert(Entry {
+            initials: *b"AAA",
+            score,
+            multiplier,
+            difficulty: self.calibrations.difficulty as u8,
+            players: players as u8,
+        });
+        let stats = &mut self.save.stats;
+        stats.games_played = stats.games_played.saturating_add(1);
+        stats.enemies_absorbed = stats.enemies_absorbed.saturating_add(self.scores.enemies);
+        stats.bombs_exploded = stats.bombs_exploded.saturating_add(self.scores.bombs);
+        stats.frames_played = stats
+            .frames_played
+            .saturating_add(self.timers.frame_count as u32);
+        stats.best_multiplier = stats.best_multiplier.max(multiplier);
+        self.store_save();
+        rank
+    }
 
-        if self.entities.killer.is_some() {
-            if let Some(player) = self.entities.players[PlayerN::P1 as usize].as_mut() {
-                player.entity.life = player.entity.life.saturating_sub(1);
-            }
+    /// Switches to the next palette, which is also used next time the game starts.
+    fn next_palette(&mut self) {
+        self.environment.set_palette(self.environment.palette_n + 1);
+        self.save.settings.palette = self.environment.palette_n;
+        self.store_save();
+    }
+
+    /// Writes the save on the disk. In netplay this client's own mode, arena
+    /// and palette are kept there, not the ones of the session.
+    fn store_save(&self) {
+        let mut save = self.save.clone();
+        if let Some(own_settings) = self.own_settings {
+            save.settings = save.settings.sharing(own_settings);
         }
+        save.store();
+    }
 
-        self.spawn_enemies();
-        self.spawn_bombs();
-        self.entities.draw();
+    /// Makes the settings take effect: palette, volumes and gamepad overlay.
+    /// Mouse control is applied as the input is read.
+    fn apply_settings(&mut self) {
+        let settings = &self.save.settings;
+        self.environment.set_palette(settings.palette);
+        self.environment.music_volume = settings.music_volume as u32 * 100 / MAX_VOLUME as u32;
+        self.environment.sfx_volume = settings.sfx_volume as u32 * 100 / MAX_VOLUME as u32;
+        unsafe {
+            if settings.gamepad_overlay {
+                *wasm4::SYSTEM_FLAGS &= !wasm4::SYSTEM_HIDE_GAMEPAD_OVERLAY;
+            } else {
+                *wasm4::SYSTEM_FLAGS |= wasm4::SYSTEM_HIDE_GAMEPAD_OVERLAY;
+            }
+        }
+    }
 
-        self.sounds_and_music_tick(bombs_exploded);
+    /// Input of this frame, from the registers or from the replay being played.
+    /// Mouse buttons are ignored when mouse control is off, and in netplay
+    /// where the mouse isn't shared between clients. A replay has them already
+    /// left out.
+    fn next_input(&mut self) -> RawInput {
+        match self.playback.as_mut() {
+            Some(playback) => playback.next_input().unwrap_or_default(),
+            None => {
+                let mut input = RawInput::read();
+                if !self.save.settings.mouse_control || controls::netplay() {
+                    input.mouse_buttons = 0;
+                }
+                input
+            }
+        }
+    }
 
-        // Print Statistics
-        #[cfg(debug_assertions)]
-        self.print_statistics();
+    /// Keep track of the inputs of the current run, from the frame the game
+    /// restarts to the one it's over (or quit, or restarted), when the replay
+    /// is printed. A run starts on the frame the game restarts, which is the
+    /// only time the frame count is 0 here.
+    fn record(&mut self, was_running: bool, previous_input: RawInput, input: RawInput) {
+        let running = self.screens.contains(ScreenName::MainGame);
+        let restarted = running && self.timers.frame_count == 0;
+        if was_running {
+            if let Some(replay) = self.replay.as_mut() {
+                replay.push(input);
+                if restarted || !running {
+                    wasm4::trace(format!(
+                        "Replay ({} frames): {}",
+                        replay.frames(),
+                        replay.to_hex()
+                    ));
+                }
+            }
+        }
+        if restarted {
+            self.replay = self.recording.then(|| {
+                let mut replay = Replay::new(
+                    self.calibrations.seed,
+                    self.mode(),
+                    self.arena_setting(),
+                    previous_input,
+                );
+                replay.push(input);
+                replay
+            });
+        }
     }
 
     #[cfg(debug_assertions)]
     fn print_statistics(&mut self) {
-        if self.timers.frame_count % 60 == 0 {
+        if self.timers.frame_count.is_multiple_of(60) {
             wasm4::trace(
                 "Enemies:".to_owned()
                     + self.entities.enemies.len().to_string().as_str()
@@ -299,16 +639,26 @@ impl Game {
         }
     }
 
-    fn sounds_and_music_tick(&mut self, bombs_exploded: u32) {
+    fn sounds_and_music_tick(
+        &mut self,
+        tally: &Tally,
+        extra_life: bool,
+        player_died: bool,
+        player_revived: bool,
+    ) {
         // Play relevant sounds
         self.environment.play_sound_effects(
-            bombs_exploded > 0,
-            self.scores.current > self.calibrations.score_next_life,
-            self.entities.killer.is_some(),
+            tally.total().1 > 0,
+            extra_life,
+            player_died,
+            player_revived,
+            tally.powerups.iter().sum::<u32>() > 0,
         );
-        // Update music appropriately with difficulty level
-        if ((self.timers.frame_count + 1) / MUSIC_SPEED_CTRL) % VOICE_NOTES == 0 {
+        // Update music appropriately with difficulty level, bosses have a
+        // song of their own
+        if ((self.timers.frame_count + 1) / MUSIC_SPEED_CTRL).is_multiple_of(VOICE_NOTES) {
             match self.calibrations.difficulty {
+                _ if self.entities.boss.is_some() => self.environment.song_nr = BOSS_SONG,
                 0..=1 => self.environment.song_nr = GAME_SONG_START,
                 2 => self.environment.song_nr = GAME_SONG_START + 1,
                 3 => self.environment.song_nr = GAME_SONG_START + 2,
@@ -320,18 +670,94 @@ impl Game {
         }
     }
 
-    fn update_score(&mut self, enemies_killed: u32, bombs_exploded: u32) {
-        self.scores.update(enemies_killed, bombs_exploded);
+    /// Scores what the players did in the frame, all together and, in
+    /// individual co-op, each on their own. Returns whether an extra life was
+    /// earned. Defeating a boss gives bonus points and a life.
+    fn update_score(&mut self, tally: &Tally) -> bool {
+        let (enemies_killed, bombs_exploded) = tally.total();
+        let bomb_points = tally.bomb_points.iter().sum();
+        let factor = match self.entities.has_power(PowerKind::DoublePoints) {
+            true => DOUBLE_POINTS,
+            false => 1,
+        };
+        self.scores
+            .update(enemies_killed, bombs_exploded, bomb_points, factor);
+        if tally.boss_defeated {
+            self.scores.bonus(SCORE_BOSS, factor);
+        }
+        if let Some(coop) = self.coop.as_mut() {
+            return coop.update(&mut self.entities, tally);
+        }
+        // One life per round in versus, none at all with revives
+        if self.versus.is_some() || self.revive.is_some() {
+            return false;
+        }
+        let mut extra_lives = tally.boss_defeated as u32;
         if self.scores.current > self.calibrations.score_next_life {
-            if let Some(player) = self.entities.players[PlayerN::P1 as usize].as_mut() {
-                player.entity.life = player.entity.life.saturating_add(1);
-            }
             self.calibrations.score_next_life = self.calibrations.score_next_life.saturating_mul(2);
+            extra_lives += 1;
+        }
+        if let Some(player) = self.entities.players[PlayerN::P1 as usize].as_mut() {
+            player.entity.life = player.entity.life.saturating_add(extra_lives);
         }
+        extra_lives > 0
+    }
+
+    /// The player hit by the killer loses a life, from P1's pool in shared
+    /// co-op. With revives they're downed instead, and the world only stops
+    /// for the last player standing.
+    fn lose_life(&mut self) {
+        if let Some(revive) = self.revive.as_mut() {
+            if let Some(victim) = self.entities.victim {
+                revive.down(&mut self.entities, victim);
+            }
+            if self.entities.players.iter().any(Option::is_some) {
+                self.entities.killer = None;
+                self.entities.victim = None;
+            }
+            return;
+        }
+        let player_n = match self.coop.is_some() || self.versus.is_some() {
+            true => self.entities.victim,
+            false => Some(PlayerN::P1),
+        };
+        if let Some(player) = player_n.and_then(|n| self.entities.players[n as usize].as_mut()) {
+            player.entity.life = player.entity.life.saturating_sub(1);
+        }
+    }
+
+    /// One frame of the world: entities move and collide, enemies and bombs
+    /// spawn, scores and lives change, downed players are revived.
+    fn step_world(&mut self) {
+        self.update_difficulty();
+        let tally = self.entities.update();
+
+        let extra_life = self.update_score(&tally);
+
+        let died = self.entities.killer.is_some();
+        if died {
+            self.lose_life();
+        }
+        let revived = self
+            .revive
+            .as_mut()
+            .is_some_and(|revive| revive.update(&mut self.entities));
+
+        self.spawn_enemies();
+        self.spawn_waves();
+        self.spawn_bombs();
+        self.spawn_powerups();
+
+        self.sounds_and_music_tick(&tally, extra_life, died, revived);
+
+        // Print Statistics
+        #[cfg(debug_assertions)]
+        self.print_statistics();
     }
 
     fn update_difficulty(&mut self) {
         // Set difficulty. It simply depends on the current multiplier.
+        let previous = self.calibrations.difficulty;
         for (i, mul) in DIFF_MUL_PROGRESSION.iter().enumerate() {
             if self.scores.multiplier < *mul {
                 self.calibrations.difficulty =
@@ -339,24 +765,135 @@ impl Game {
                 break;
             }
         }
+        let arena = ARENA_ROTATION[self.calibrations.difficulty as usize];
+        if self.rotating_arenas && arena != self.arena {
+            self.enter_arena(arena);
+        }
+        // Some difficulties are reached with a boss, not in versus matches
+        let health = BOSS_HEALTH[self.calibrations.difficulty as usize];
+        if self.calibrations.difficulty != previous
+            && health > 0
+            && self.entities.boss.is_none()
+            && self.versus.is_none()
+        {
+            self.spawn_boss(health);
+        }
+    }
+
+    /// A boss with `health`, in the middle of the half of the screen P1
+    /// isn't in.
+    fn spawn_boss(&mut self, health: u32) {
+        let size = SCREEN_SIZE as f64;
+        let p1 = self.entities.players[PlayerN::P1 as usize]
+            .as_ref()
+            .map_or(scalar(0.0), |player| player.entity.center().y);
+        let y = match p1 < Scalar::from(SCREEN_SIZE / 2) {
+            true => size * 0.75,
+            false => size * 0.25,
+        };
+        let mut boss = Boss::new(Coord::default(), health);
+        let radius = boss.entity.radius();
+        boss.entity.position = Coord::new(size / 2.0, y)
+            - Coord {
+                x: radius,
+                y: radius,
+            };
+        boss.entity.resolve(&self.entities.obstacles);
+        self.entities.boss = Some(boss);
+    }
+
+    /// Swaps the obstacles for those of `arena`, pushing the bombs and
+    /// power-ups under them out of the way.
+    fn enter_arena(&mut self, arena: usize) {
+        self.arena = arena;
+        self.entities.obstacles = LAYOUTS[arena].obstacles();
+        let obstacles = &self.entities.obstacles;
+        for bomb in self.entities.bombs.iter_mut() {
+            bomb.entity.resolve(obstacles);
+        }
+        for powerup in self.entities.powerups.iter_mut() {
+            powerup.entity.resolve(obstacles);
+        }
     }
 
     fn spawn_bombs(&mut self) {
         // Bombs are spawned with a similar logic to the enemies, but in random positions on screen.
-        if self.timers.frame_count % BOMB_FRAME_FREQ == 0 && self.entities.bombs.len() < MAX_BOMBS {
-            self.entities.bombs.insert(
-                self.timers.frame_count,
-                Box::new(Bomb::new(&Coord {
-                    x: self.calibrations.rng.f64() * (SCREEN_SIZE - 20) as f64 + 10.0,
-                    y: self.calibrations.rng.f64() * (SCREEN_SIZE - 20) as f64 + 10.0,
-                })),
+        if self.timers.frame_count.is_multiple_of(BOMB_FRAME_FREQ)
+            && self.entities.bombs.len() < MAX_BOMBS
+        {
+            let mut bomb = Bomb::new(&Coord::new(
+                self.calibrations.rng.f64() * (SCREEN_SIZE - 20) as f64 + 10.0,
+                self.calibrations.rng.f64() * (SCREEN_SIZE - 20) as f64 + 10.0,
+            ));
+            // Out of the obstacles, where players can reach it
+            bomb.entity.resolve(&self.entities.obstacles);
+            // The harder the game, the more bombs of other kinds
+            let kind = BombKind::random(&self.calibrations.rng, self.calibrations.difficulty);
+            self.entities.bombs.insert(bomb.with_kind(kind));
+        }
+    }
+    fn spawn_powerups(&mut self) {
+        // Like bombs, but none at the start, and with a random power.
+        if self.timers.frame_count > 0
+            && self.timers.frame_count.is_multiple_of(POWERUP_FRAME_FREQ)
+            && self.entities.powerups.len() < MAX_POWERUPS
+        {
+            let kind = PowerKind::ALL[self.calibrations.rng.usize(0..PowerKind::ALL.len())];
+            let mut powerup = PowerUp::new(
+                &Coord::new(
+                    self.calibrations.rng.f64() * (SCREEN_SIZE - 20) as f64 + 10.0,
+                    self.calibrations.rng.f64() * (SCREEN_SIZE - 20) as f64 + 10.0,
+                ),
+                kind,
             );
+            powerup.entity.resolve(&self.entities.obstacles);
+            self.entities.powerups.insert(powerup);
+        }
+    }
+    /// Enemies of the waves of the difficulty (see `waves`), around P1 or
+    /// the middle of the screen, and of the attacks of the boss. Waves wait
+    /// for the respite to end.
+    fn spawn_waves(&mut self) {
+        if self.timers.respite > 0 {
+            return;
+        }
+        if let Some(boss) = &self.entities.boss {
+            let attack = BOSS_ATTACKS[boss.phase()];
+            if boss.age.is_multiple_of(attack.after as u32) {
+                let (center, color) = (boss.entity.center(), boss.entity.color);
+                self.waves
+                    .burst(attack.wave, self.timers.frame_count, center, color);
+            }
+        }
+        let middle = Scalar::from(SCREEN_SIZE) / scalar(2.0);
+        let target = self.entities.players.iter().flatten().next().map_or(
+            Coord {
+                x: middle,
+                y: middle,
+            },
+            |player| player.entity.center(),
+        );
+        let enemies = self.waves.update(
+            self.calibrations.difficulty,
+            self.timers.frame_count,
+            target,
+            self.calibrations.enemy_color,
+        );
+        for enemy in enemies {
+            if self.entities.enemies.len() < MAX_ENEMIES {
+                self.entities.enemies.insert(enemy);
+            }
         }
     }
+
     fn spawn_enemies(&mut self) {
         // Enemy color depends on time, so we can have nice sections of enemies
         // with same colour, while keeping some element of randomness (their position).
-        if self.timers.frame_count % EN_COL_FRAME[self.calibrations.difficulty as usize] == 0 {
+        if self
+            .timers
+            .frame_count
+            .is_multiple_of(EN_COL_FRAME[self.calibrations.difficulty as usize])
+        {
             self.calibrations.enemy_color = match self.calibrations.enemy_color {
                 DRAW_COLOR_A => DRAW_COLOR_B,
                 _ => DRAW_COLOR_A,
@@ -365,54 +902,1355 @@ impl Game {
         // We only spawn a maximum of 1 enemy per frame, at an interval decided
         // by ENEMY_FRAME. It works fine and even at 1 enemy per frame (60
         // enemies per second) the pressure is high.
-        // Enemies are randomly spawned at 8 fixed locations (corners and mid-edges)
-        if self.timers.frame_count % ENEMY_FRAME[self.calibrations.difficulty as usize] == 0
+        // Enemies are randomly spawned at the spawn points of the arena
+        if self
+            .timers
+            .frame_count
+            .is_multiple_of(ENEMY_FRAME[self.calibrations.difficulty as usize])
             && self.entities.enemies.len() < MAX_ENEMIES
             && self.timers.respite == 0
         {
-            let positions = [
-                (0, 0),
-                (0, SCREEN_SIZE / 2),
-                (0, SCREEN_SIZE - 1),
-                (SCREEN_SIZE / 2, 0),
-                (SCREEN_SIZE / 2, SCREEN_SIZE - 1),
-                (SCREEN_SIZE - 1, 0),
-                (SCREEN_SIZE - 1, SCREEN_SIZE / 2),
-                (SCREEN_SIZE - 1, SCREEN_SIZE - 1),
-            ];
-            let pos = positions[self.calibrations.rng.usize(0..positions.len())];
-
-            self.entities.enemies.insert(
-                self.timers.frame_count,
-                Box::new(Enemy::new(
-                    self.timers.frame_count,
-                    Coord {
-                        x: pos.0 as f64,
-                        y: pos.1 as f64,
-                    },
-                    self.calibrations.enemy_color,
-                )),
-            );
+            let mut spawns = LAYOUTS[self.arena].spawns();
+            let n = self.calibrations.rng.usize(0..spawns.len());
+            let pos = spawns.nth(n).expect("spawn points of the arena");
+            // The harder the game, the more archetypes other than chasers
+            let rng = &self.calibrations.rng;
+            let behavior = Behavior::random(rng, self.calibrations.difficulty);
+            let mut enemy = Enemy::new(self.timers.frame_count, pos, self.calibrations.enemy_color)
+                .with_behavior(behavior);
+            if behavior == Behavior::Wanderer {
+                enemy.entity.direction = Coord::new(rng.f64() - 0.5, rng.f64() - 0.5);
+            }
+            self.entities.enemies.insert(enemy);
         }
     }
 
     fn death_tick(&mut self) {
-        // Just shows players and blink killer
-        for player in self.entities.players.iter().flatten() {
-            player.draw();
-        }
-
-        if let Some(killer) = &self.entities.killer {
-            if self.timers.death_countdown / 10 % 2 != 0 {
-                killer.draw()
-            }
-        }
         self.timers.death_countdown = self.timers.death_countdown.saturating_sub(1);
         if self.timers.death_countdown == 0 {
             self.entities.enemies.clear();
             self.entities.killer = None;
+            self.entities.victim = None;
             self.timers.death_countdown = DEATH_COUNTDOWN_DURATION;
             self.timers.respite = RESPITE_DURATION;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::snapshot::assert_snapshot;
+    use crate::wasm4::{
+        host, BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP, MOUSE_LEFT,
+        MOUSE_MIDDLE,
+    };
+    use controls::Repeater;
+    use fastrand::Rng;
+    use leaderboard::LEADERBOARD_SIZE;
+    use revive::{DOWNED_FRAMES, REVIVE_FRAMES};
+
+    fn run_frames(game: &mut Game, frames: usize) {
+        for _ in 0..frames {
+            host::begin_frame();
+            game.update();
+        }
+    }
+
+    fn press(game: &mut Game, gamepad: usize, buttons: u8) {
+        host::set_gamepad(gamepad, buttons);
+        run_frames(game, 1);
+        host::set_gamepad(gamepad, 0);
+        run_frames(game, 1);
+    }
+
+    /// Presses Z alone, then waits for the palette change it asks for.
+    fn press_z(game: &mut Game, gamepad: usize) {
+        press(game, gamepad, BUTTON_2);
+        run_frames(game, Controls::DOUBLE_CLICK_FRAMES as usize);
+    }
+
+    fn start_game(game: &mut Game) {
+        run_frames(game, 10);
+        press(game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::HowToPlay));
+        press(game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+    }
+
+    #[test]
+    fn idle_player_reaches_game_over() {
+        let _session = host::lock();
+        let mut game = Game::new();
+        start_game(&mut game);
+
+        for _ in 0..20_000 {
+            if game.screens.top() == Some(ScreenName::GameOver) {
+                break;
+            }
+            run_frames(&mut game, 1);
+        }
+        assert!(game.screens.top() == Some(ScreenName::GameOver));
+        assert!(!host::take_tones().is_empty());
+        let save = Save::from_disk(&host::disk());
+        assert_eq!(save.leaderboard, game.save.leaderboard);
+        assert_eq!(save.stats.games_played, 1);
+        assert_eq!(save.stats.frames_played, game.timers.frame_count as u32);
+        assert_eq!(save.stats.enemies_absorbed, game.scores.enemies);
+    }
+
+    #[test]
+    fn save_survives_restart() {
+        let _session = host::lock();
+        host::set_disk(&4321u32.to_le_bytes());
+        let mut game = Game::new();
+        assert_eq!(game.save.leaderboard.high_score(), 4321);
+
+        start_game(&mut game);
+        press_z(&mut game, 0);
+        let save = Save::from_disk(&host::disk());
+        assert_eq!(save.leaderboard.high_score(), 4321);
+        assert_eq!(save.settings.palette, 1);
+
+        let mut game = Game::new();
+        game.start();
+        assert_eq!(game.environment.palette_n, 1);
+        assert_eq!(game.save.leaderboard.high_score(), 4321);
+    }
+
+    fn game_over(game: &mut Game, score: u32) {
+        game.scores.current = score;
+        game.entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 should always exist")
+            .entity
+            .life = 0;
+        run_frames(game, 30);
+        assert!(game.screens.top() == Some(ScreenName::GameOver));
+    }
+
+    #[test]
+    fn initials_entry() {
+        let _session = host::lock();
+        let mut game = Game::new();
+        start_game(&mut game);
+        game_over(&mut game, 500);
+        // Saved before the initials are in, in case the game is closed
+        assert_eq!(Save::from_disk(&host::disk()).leaderboard.high_score(), 500);
+
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::EnterInitials));
+        // M: A -> Z -> Y... by going back 14 letters, two presses then held
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_DOWN);
+        hold(&mut game, 0, BUTTON_DOWN, 11 * 11 + 1);
+        press(&mut game, 0, BUTTON_1);
+        // A stays, moving around doesn't change letters
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_LEFT);
+        press(&mut game, 0, BUTTON_RIGHT);
+        for _ in 0..3 {
+            press(&mut game, 0, BUTTON_DOWN);
+        }
+        press(&mut game, 0, BUTTON_1);
+        let leaderboard = Save::from_disk(&host::disk()).leaderboard;
+        assert_eq!(leaderboard.entries()[0].initials, *b"MAX");
+
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+
+        // A worse run takes the second place, a run without points none
+        game_over(&mut game, 100);
+        assert_eq!(game.save.leaderboard.entries()[1].score, 100);
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::EnterInitials));
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_1);
+        game_over(&mut game, 0);
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+        let scores: Vec<u32> = game
+            .save
+            .leaderboard
+            .entries()
+            .iter()
+            .map(|e| e.score)
+            .collect();
+        assert_eq!(scores, [500, 100]);
+    }
+
+    fn positions(game: &Game) -> Vec<(Scalar, Scalar)> {
+        let enemies = game.entities.enemies.iter().map(|e| &e.entity);
+        let players = game.entities.players.iter().flatten().map(|p| &p.entity);
+        enemies
+            .chain(players)
+            .map(|e| (e.position.x, e.position.y))
+            .collect()
+    }
+
+    /// X picks an option of the pause menu, it doesn't join the game too.
+    #[test]
+    fn pause_menu_keeps_players_out() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+
+        press(&mut game, 0, BUTTON_1 | BUTTON_2);
+        assert!(game.screens.top() == Some(ScreenName::Pause));
+        press(&mut game, 1, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+        assert!(game.entities.players[PlayerN::P2 as usize].is_none());
+
+        press(&mut game, 1, BUTTON_1);
+        assert!(game.entities.players[PlayerN::P2 as usize].is_some());
+    }
+
+    /// Z or a middle click changes the palette, unless it turns out to be
+    /// the start of a pause.
+    #[test]
+    fn pausing_keeps_the_palette() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+
+        // Z, then X while holding Z
+        host::set_gamepad(0, BUTTON_2);
+        run_frames(&mut game, 5);
+        host::set_gamepad(0, BUTTON_1 | BUTTON_2);
+        run_frames(&mut game, 1);
+        host::set_gamepad(0, 0);
+        run_frames(&mut game, 30);
+        assert!(game.screens.top() == Some(ScreenName::Pause));
+        assert_eq!(game.environment.palette_n, 0);
+        press(&mut game, 0, BUTTON_1 | BUTTON_2);
+
+        // Middle double click
+        for buttons in [MOUSE_MIDDLE, 0, 0, 0, MOUSE_MIDDLE, 0] {
+            host::set_mouse(80, 80, buttons);
+            run_frames(&mut game, 1);
+        }
+        run_frames(&mut game, 30);
+        assert!(game.screens.top() == Some(ScreenName::Pause));
+        assert_eq!(game.environment.palette_n, 0);
+        assert_eq!(Save::from_disk(&host::disk()).settings.palette, 0);
+        press(&mut game, 0, BUTTON_1 | BUTTON_2);
+
+        // A single middle click once the window is over
+        host::set_mouse(80, 80, MOUSE_MIDDLE);
+        run_frames(&mut game, 1);
+        host::set_mouse(80, 80, 0);
+        run_frames(&mut game, Controls::DOUBLE_CLICK_FRAMES as usize - 1);
+        assert_eq!(game.environment.palette_n, 0);
+        run_frames(&mut game, 1);
+        assert_eq!(game.environment.palette_n, 1);
+    }
+
+    #[test]
+    fn pause_menu() {
+        let _session = host::lock();
+        let mut game = Game::new();
+        start_game(&mut game);
+        hold(&mut game, 0, BUTTON_RIGHT, 100);
+
+        // Frozen, music aside
+        press(&mut game, 0, BUTTON_1 | BUTTON_2);
+        assert!(game.screens.top() == Some(ScreenName::Pause));
+        let frame_count = game.timers.frame_count;
+        let song_tick = game.timers.song_tick;
+        let frozen = positions(&game);
+        hold(&mut game, 0, BUTTON_LEFT, 100);
+        assert_eq!(game.timers.frame_count, frame_count);
+        assert_eq!(game.timers.song_tick, song_tick + 100);
+        assert_eq!(positions(&game), frozen);
+
+        // Palette, then resume with both buttons again
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_1);
+        assert_eq!(game.environment.palette_n, 1);
+        press(&mut game, 0, BUTTON_1 | BUTTON_2);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+        assert_eq!(game.timers.frame_count, frame_count + 1);
+        assert!(!game.environment.quiet);
+
+        // Restart
+        press(&mut game, 0, BUTTON_1 | BUTTON_2);
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+        assert_eq!(game.timers.frame_count, 1);
+        assert!(!game.timers.paused);
+
+        // Quit, wrapping around the menu
+        press(&mut game, 0, BUTTON_1 | BUTTON_2);
+        press(&mut game, 0, BUTTON_UP);
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::Title));
+        assert!(!game.screens.contains(ScreenName::MainGame));
+        assert_eq!(game.save.stats.games_played, 0);
+    }
+
+    #[test]
+    fn settings_menu() {
+        let _session = host::lock();
+        let mut game = boot();
+        assert_eq!(game.environment.music_volume, 100);
+        press(&mut game, 0, BUTTON_DOWN);
+        assert!(game.screens.top() == Some(ScreenName::Settings));
+
+        // Music stops at 0, the sound effects at full volume
+        hold(
+            &mut game,
+            0,
+            BUTTON_LEFT,
+            12 * (Repeater::DELAY as usize + 1),
+        );
+        assert_eq!(game.environment.music_volume, 0);
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_RIGHT);
+        press(&mut game, 0, BUTTON_LEFT);
+        assert_eq!(game.environment.sfx_volume, 90);
+
+        // X cycles through the values
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_1);
+        assert_eq!(game.environment.palette_n, 2);
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_RIGHT);
+        assert_ne!(
+            unsafe { *wasm4::SYSTEM_FLAGS } & wasm4::SYSTEM_HIDE_GAMEPAD_OVERLAY,
+            0
+        );
+
+        // Saved on the way out, and used next time the game starts
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::ArenaSelect));
+        press(&mut game, 0, BUTTON_LEFT);
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::Settings));
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::Title));
+        let settings = Save::from_disk(&host::disk()).settings;
+        assert_eq!(
+            settings,
+            save::Settings {
+                palette: 2,
+                music_volume: 0,
+                sfx_volume: 9,
+                mouse_control: false,
+                gamepad_overlay: false,
+                mode: GameMode::Shared,
+                arena: ROTATING,
+            }
+        );
+        unsafe { *wasm4::SYSTEM_FLAGS = 0 };
+        let mut game = boot();
+        assert_eq!(game.environment.palette_n, 2);
+        assert_eq!(game.environment.music_volume, 0);
+        assert_ne!(
+            unsafe { *wasm4::SYSTEM_FLAGS } & wasm4::SYSTEM_HIDE_GAMEPAD_OVERLAY,
+            0
+        );
+
+        // Without mouse control, clicks don't start the game
+        for buttons in [MOUSE_LEFT, 0, MOUSE_LEFT, 0] {
+            host::set_mouse(80, 80, buttons);
+            run_frames(&mut game, 1);
+        }
+        assert!(game.screens.top() == Some(ScreenName::Title));
+    }
+
+    #[test]
+    fn netplay() {
+        let _session = host::lock();
+        host::set_netplay(0b101);
+        assert_eq!(controls::local_player(), Some(PlayerN::P2));
+        let mut game = boot();
+
+        // No mouse, and only the host changes the palette
+        for buttons in [MOUSE_LEFT, 0, MOUSE_LEFT, 0] {
+            host::set_mouse(80, 80, buttons);
+            run_frames(&mut game, 1);
+        }
+        assert!(game.screens.top() == Some(ScreenName::Title));
+        start_game(&mut game);
+        press_z(&mut game, 1);
+        assert_eq!(game.environment.palette_n, 0);
+        press_z(&mut game, 0);
+        assert_eq!(game.environment.palette_n, 1);
+
+        // The pointer over P2's disk tells this client which one is theirs
+        press(&mut game, 1, BUTTON_1);
+        let player = &game.entities.players[PlayerN::P2 as usize]
+            .as_ref()
+            .expect("P2 joined")
+            .entity;
+        let (x, y) = (player.center().x.to_i32(), player.position.y.to_i32());
+        assert_eq!(host::pixel(x, y - 3), 3);
+
+        // Not through the pause menu or the settings either
+        press(&mut game, 0, BUTTON_1 | BUTTON_2);
+        for _ in 0..2 {
+            press(&mut game, 1, BUTTON_DOWN);
+        }
+        press(&mut game, 1, BUTTON_1);
+        assert_eq!(game.environment.palette_n, 1);
+        press(&mut game, 0, BUTTON_1);
+        assert_eq!(game.environment.palette_n, 2);
+        press(&mut game, 1, BUTTON_DOWN);
+        press(&mut game, 1, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::Settings));
+        for _ in 0..2 {
+            press(&mut game, 1, BUTTON_DOWN);
+        }
+        for buttons in [BUTTON_RIGHT, BUTTON_1] {
+            press(&mut game, 1, buttons);
+        }
+        assert_eq!(game.environment.palette_n, 2);
+        press(&mut game, 0, BUTTON_RIGHT);
+        assert_eq!(game.environment.palette_n, 3);
+    }
+
+    /// Every client of a netplay game plays the same inputs, each one knowing
+    /// it controls a different player: they must all stay in step.
+    #[test]
+    fn netplay_clients_stay_in_step() {
+        let play = |netplay: u8| {
+            let _session = host::lock();
+            host::set_netplay(netplay);
+            let mut game = boot();
+            start_game(&mut game);
+            press(&mut game, 1, BUTTON_1);
+            let rng = Rng::with_seed(13);
+            for _ in 0..600 {
+                host::set_gamepad(0, rng.u8(..) & !BUTTON_2);
+                host::set_gamepad(1, rng.u8(..));
+                host::set_mouse(rng.i16(0..160), rng.i16(0..160), rng.u8(..8));
+                run_frames(&mut game, 1);
+            }
+            (
+                positions(&game),
+                game.scores.current,
+                game.environment.palette_n,
+            )
+        };
+        let host = play(0b100);
+        assert_eq!(play(0b101), host);
+        assert_eq!(play(0b111), host);
+    }
+
+    /// Clients of a netplay game start from different disks: they must still
+    /// play the same runs, and go on with the same screens after game over.
+    #[test]
+    fn netplay_clients_ignore_their_disk() {
+        let play = |netplay: u8, save: &Save| {
+            let _session = host::lock();
+            host::set_netplay(netplay);
+            save.store();
+            let mut game = boot();
+            start_game(&mut game);
+            let rng = Rng::with_seed(17);
+            let mut screens = vec![];
+            for _ in 0..20_000 {
+                if rng.u8(..) < 10 {
+                    host::set_gamepad(0, rng.u8(..) & !BUTTON_2);
+                }
+                run_frames(&mut game, 1);
+                if screens.last() != game.screens.top().as_ref() {
+                    screens.extend(game.screens.top());
+                }
+                if screens.len() > 3 {
+                    break;
+                }
+            }
+            // Their own settings are still on their disk
+            assert_eq!(Save::from_disk(&host::disk()).settings, save.settings);
+            (
+                screens,
+                positions(&game),
+                game.scores.current,
+                game.arena,
+                game.environment.palette_n,
+            )
+        };
+        let host = play(0b100, &Save::default());
+        assert!(host.0.contains(&ScreenName::GameOver));
+        let mut save = Save::default();
+        save.settings.mode = GameMode::Revive;
+        save.settings.arena = 2;
+        save.settings.palette = 3;
+        for _ in 0..LEADERBOARD_SIZE {
+            save.leaderboard.insert(Entry {
+                initials: *b"TOP",
+                score: u32::MAX,
+                multiplier: 1,
+                difficulty: 9,
+                players: 1,
+            });
+        }
+        assert_eq!(play(0b101, &save), host);
+    }
+
+    #[test]
+    fn settings_from_pause_menu() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        press(&mut game, 0, BUTTON_1 | BUTTON_2);
+        for _ in 0..3 {
+            press(&mut game, 0, BUTTON_DOWN);
+        }
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::Settings));
+        press(&mut game, 0, BUTTON_LEFT);
+        assert_eq!(game.environment.music_volume, 90);
+        // Still quiet, the game is still paused
+        assert!(game.environment.quiet);
+        press(&mut game, 0, BUTTON_UP);
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::Pause));
+        assert_eq!(Save::from_disk(&host::disk()).settings.music_volume, 9);
+    }
+
+    fn lives(game: &Game, player_n: PlayerN) -> Option<u32> {
+        game.entities.players[player_n as usize]
+            .as_ref()
+            .map(|player| player.entity.life)
+    }
+
+    #[test]
+    fn players_leave() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        press(&mut game, 1, BUTTON_1);
+        assert_eq!(lives(&game, PlayerN::P1), Some(2 * INIT_LIVES));
+
+        // P2's lives leave the pool with them, P1 can't leave
+        host::take_tones();
+        hold(&mut game, 1, BUTTON_1, Controls::LEAVE_FRAMES as usize);
+        assert_eq!(lives(&game, PlayerN::P2), None);
+        assert_eq!(lives(&game, PlayerN::P1), Some(INIT_LIVES));
+        assert!(host::take_tones()
+            .iter()
+            .any(|tone| tone.frequency == 1000 | 400 << 16));
+        hold(&mut game, 0, BUTTON_1, Controls::LEAVE_FRAMES as usize);
+        assert_eq!(lives(&game, PlayerN::P1), Some(INIT_LIVES));
+
+        // In individual co-op leaving forfeits the lives left, for the run
+        game.save.settings.mode = GameMode::Individual;
+        game.restart();
+        press(&mut game, 1, BUTTON_1);
+        assert_eq!(lives(&game, PlayerN::P2), Some(INIT_LIVES));
+        hold(&mut game, 1, BUTTON_1, Controls::LEAVE_FRAMES as usize);
+        assert_eq!(lives(&game, PlayerN::P2), None);
+        press(&mut game, 1, BUTTON_1);
+        assert_eq!(lives(&game, PlayerN::P2), None);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+    }
+
+    #[test]
+    fn dash() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        let x = |game: &Game| {
+            let player = game.entities.players[PlayerN::P1 as usize].as_ref();
+            player.expect("P1 should always exist").entity.position.x
+        };
+        let start = x(&game);
+        press(&mut game, 0, BUTTON_RIGHT);
+        host::take_tones();
+        press(&mut game, 0, BUTTON_RIGHT);
+        assert!(!host::take_tones().is_empty());
+        let player = game.entities.players[PlayerN::P1 as usize]
+            .as_ref()
+            .expect("P1 should always exist");
+        assert!(player.invulnerable());
+        // Much further than the three steps it took
+        assert!(x(&game) - start > Scalar::from(8));
+
+        // Enemies can't hurt a dashing player
+        let mut enemy = Enemy::new(0, player.entity.position, DRAW_COLOR_A);
+        enemy.entity.life = 100;
+        game.entities.enemies.insert(enemy);
+        run_frames(&mut game, 1);
+        assert!(game.entities.killer.is_none());
+        run_frames(&mut game, 30);
+        press(&mut game, 0, BUTTON_RIGHT);
+        press(&mut game, 0, BUTTON_RIGHT);
+        assert!(!game.entities.players[PlayerN::P1 as usize]
+            .as_ref()
+            .expect("P1 should always exist")
+            .invulnerable());
+    }
+
+    #[test]
+    fn carry_and_drop_bombs() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        game.entities.bombs.clear();
+        let player = game.entities.players[PlayerN::P1 as usize]
+            .as_ref()
+            .expect("P1 should always exist");
+        let bomb = Bomb::new(&player.entity.position).with_kind(BombKind::Delayed);
+        game.entities.bombs.insert(bomb);
+        run_frames(&mut game, 1);
+        assert_eq!(game.entities.carriers(), [true, false, false, false]);
+
+        // Btn2 drops the bomb instead of changing the palette, then changes it
+        let palette = game.environment.palette_n;
+        host::take_tones();
+        press(&mut game, 0, BUTTON_2);
+        assert!(!host::take_tones().is_empty());
+        assert_eq!(game.environment.palette_n, palette);
+        assert_eq!(game.entities.carriers(), [false; 4]);
+        press_z(&mut game, 0);
+        assert_ne!(game.environment.palette_n, palette);
+
+        assert_eq!(game.scores.bombs, 0);
+        run_frames(&mut game, Bomb::FUSE_FRAMES as usize);
+        assert_eq!(game.scores.bombs, 1);
+    }
+
+    /// P1 and P2 in individual co-op, with P1 out of lives.
+    fn coop_with_p1_out(game: &mut Game) {
+        game.save.settings.mode = GameMode::Individual;
+        start_game(game);
+        press(game, 1, BUTTON_1);
+        assert_eq!(lives(game, PlayerN::P1), Some(INIT_LIVES));
+        assert_eq!(lives(game, PlayerN::P2), Some(INIT_LIVES));
+        game.entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 joined")
+            .entity
+            .life = 0;
+        run_frames(game, 1);
+    }
+
+    #[test]
+    fn individual_coop() {
+        let _session = host::lock();
+        let mut game = boot();
+        coop_with_p1_out(&mut game);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+        assert_eq!(lives(&game, PlayerN::P1), None);
+        // Out for the rest of the run
+        press(&mut game, 0, BUTTON_1);
+        assert_eq!(lives(&game, PlayerN::P1), None);
+
+        // The best player's run goes on the leaderboard
+        let coop = game.coop.as_mut().expect("individual co-op");
+        coop.contenders[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 joined")
+            .scores
+            .current = 300;
+        coop.contenders[PlayerN::P2 as usize]
+            .as_mut()
+            .expect("P2 joined")
+            .scores
+            .current = 700;
+        game.entities.players[PlayerN::P2 as usize]
+            .as_mut()
+            .expect("P2 joined")
+            .entity
+            .life = 0;
+        run_frames(&mut game, 30);
+        assert!(game.screens.top() == Some(ScreenName::GameOver));
+        let entry = game.save.leaderboard.entries()[0];
+        assert_eq!((entry.score, entry.players), (700, 2));
+
+        // Back to shared lives once the setting is off
+        game.save.settings.mode = GameMode::Shared;
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+        assert!(game.coop.is_none());
+        press(&mut game, 1, BUTTON_1);
+        assert_eq!(lives(&game, PlayerN::P1), Some(2 * INIT_LIVES));
+    }
+
+    /// P1 and P2 in a run with revives, without enemies coming.
+    fn start_revive(game: &mut Game) {
+        game.save.settings.mode = GameMode::Revive;
+        start_game(game);
+        press(game, 1, BUTTON_1);
+        game.timers.respite = usize::MAX;
+    }
+
+    /// `player_n` is hit, as if by an enemy.
+    fn hit(game: &mut Game, player_n: PlayerN) {
+        game.entities.killer = Some(Enemy::new(0, Coord::new(0.0, 0.0), DRAW_COLOR_A));
+        game.entities.victim = Some(player_n);
+        game.lose_life();
+    }
+
+    #[test]
+    fn downed_players_are_revived() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_revive(&mut game);
+        let ghost = |game: &Game, n: PlayerN| {
+            let revive = game.revive.as_ref().expect("revive mode");
+            revive.ghosts[n as usize].is_some()
+        };
+
+        // The world goes on while P2 is down, and P1 is right on them
+        hit(&mut game, PlayerN::P2);
+        assert!(ghost(&game, PlayerN::P2));
+        assert!(game.entities.players[PlayerN::P2 as usize].is_none());
+        assert!(game.entities.killer.is_none());
+        press(&mut game, 1, BUTTON_1);
+        assert!(game.entities.players[PlayerN::P2 as usize].is_none());
+        host::take_tones();
+        run_frames(&mut game, REVIVE_FRAMES as usize);
+        assert!(!ghost(&game, PlayerN::P2));
+        assert!(game.entities.players[PlayerN::P2 as usize].is_some());
+        let revive_tone = host::take_tones()
+            .into_iter()
+            .any(|tone| tone.frequency == 300 | (900 << 16));
+        assert!(revive_tone);
+
+        // Nobody helps P1, who's out for the rest of the run
+        hold(&mut game, 1, BUTTON_RIGHT, 30);
+        hit(&mut game, PlayerN::P1);
+        run_frames(&mut game, DOWNED_FRAMES as usize);
+        assert!(!ghost(&game, PlayerN::P1));
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.entities.players[PlayerN::P1 as usize].is_none());
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+
+        // The world stops for the last player standing
+        game.scores.current = 500;
+        hit(&mut game, PlayerN::P2);
+        assert!(game.entities.killer.is_some());
+        run_frames(&mut game, DEATH_COUNTDOWN_DURATION + 10);
+        assert!(game.screens.top() == Some(ScreenName::GameOver));
+        assert_eq!(game.save.leaderboard.entries()[0].players, 2);
+    }
+
+    /// Leaving a run with revives is for good, downed or not.
+    #[test]
+    fn leaving_revives_is_for_good() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_revive(&mut game);
+        press(&mut game, 2, BUTTON_1);
+        hold(&mut game, 1, BUTTON_RIGHT, 30);
+        hold(&mut game, 2, BUTTON_LEFT, 30);
+
+        hit(&mut game, PlayerN::P2);
+        hold(&mut game, 1, BUTTON_1, Controls::LEAVE_FRAMES as usize);
+        hold(&mut game, 2, BUTTON_1, Controls::LEAVE_FRAMES as usize);
+        let revive = game.revive.as_ref().expect("revive mode");
+        assert!(revive.ghosts[PlayerN::P2 as usize].is_none());
+        assert_eq!(revive.out, [false, true, true, false]);
+
+        press(&mut game, 1, BUTTON_1);
+        press(&mut game, 2, BUTTON_1);
+        assert!(game.entities.players[PlayerN::P2 as usize].is_none());
+        assert!(game.entities.players[PlayerN::P3 as usize].is_none());
+    }
+
+    /// A versus match with P1 and P2 in the lobby.
+    fn start_versus(game: &mut Game) {
+        game.save.settings.mode = GameMode::Versus;
+        run_frames(game, 10);
+        press(game, 0, BUTTON_1);
+        press(game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::Versus));
+        press(game, 1, BUTTON_1);
+    }
+
+    /// Plays past the countdown of a round, which `loser` then loses, and
+    /// past the outcome of the round.
+    fn lose_round(game: &mut Game, loser: PlayerN) {
+        run_frames(game, 200);
+        game.entities.players[loser as usize]
+            .as_mut()
+            .expect("loser is in the round")
+            .entity
+            .life = 0;
+        run_frames(game, 140);
+    }
+
+    #[test]
+    fn versus_match() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_versus(&mut game);
+        fn versus(game: &Game) -> &Versus {
+            game.versus.as_ref().expect("versus match")
+        }
+        assert_eq!(versus(&game).entrants(), 2);
+        assert!(game.entities.versus);
+        press(&mut game, 0, BUTTON_1);
+        assert_eq!(versus(&game).round, 1);
+        // Too late to join
+        press(&mut game, 2, BUTTON_1);
+        assert_eq!(versus(&game).entrants(), 2);
+        assert!(game.entities.players[PlayerN::P3 as usize].is_none());
+
+        lose_round(&mut game, PlayerN::P1);
+        assert_eq!(versus(&game).wins, [0, 1, 0, 0]);
+        assert_eq!(versus(&game).round, 2);
+        assert!(game.entities.players[PlayerN::P1 as usize].is_some());
+        for _ in 0..versus::WINS_NEEDED {
+            lose_round(&mut game, PlayerN::P2);
+        }
+        assert!(game.screens.top() == Some(ScreenName::VersusResults));
+        assert_eq!(versus(&game).wins, [versus::WINS_NEEDED, 1, 0, 0]);
+        // Versus doesn't count for the leaderboard
+        assert_eq!(game.save.stats.games_played, 0);
+
+        // Rematch, players join again
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::Versus));
+        assert_eq!(versus(&game).round, 0);
+        assert_eq!(versus(&game).entrants(), 1);
+    }
+
+    #[test]
+    fn multiplayer_session() {
+        let _session = host::lock();
+        let mut game = Game::new();
+        start_game(&mut game);
+        press(&mut game, 1, BUTTON_1);
+        assert!(game.entities.players[PlayerN::P2 as usize].is_some());
+
+        let rng = Rng::with_seed(7);
+        for _ in 0..5_000 {
+            // No Btn1, it would restart the game once it's over
+            host::set_gamepad(0, rng.u8(..) & (BUTTON_LEFT | BUTTON_UP | BUTTON_2));
+            host::set_gamepad(1, rng.u8(..) & !BUTTON_1);
+            run_frames(&mut game, 1);
+        }
+        assert!(game.timers.frame_count > 5_000);
+    }
+
+    #[test]
+    fn replay_reproduces_run() {
+        let _session = host::lock();
+        let mut game = Game::new();
+        let replay = record_run(&mut game);
+        assert_eq!(replay.mode, GameMode::Shared);
+        assert_replays(&replay, &game);
+    }
+
+    #[test]
+    fn replays_keep_their_mode() {
+        let _session = host::lock();
+        for mode in [GameMode::Individual, GameMode::Revive] {
+            let mut game = Game::new();
+            game.save.settings.mode = mode;
+            let replay = record_run(&mut game);
+            assert_eq!(replay.mode, mode);
+            // Played back with another mode on disk
+            let mut save = Save::load();
+            save.settings.mode = GameMode::Shared;
+            save.store();
+            assert_replays(&replay, &game);
+        }
+    }
+
+    #[test]
+    fn replays_keep_their_arena() {
+        let _session = host::lock();
+        let mut game = Game::new();
+        game.save.settings.arena = 1;
+        let replay = record_run(&mut game);
+        assert_eq!(replay.arena, 1);
+        // Played back with the open field on disk
+        let mut save = Save::load();
+        save.settings.arena = 0;
+        save.store();
+        assert_replays(&replay, &game);
+    }
+
+    /// Plays a run with random inputs until it's over, returns its replay.
+    fn record_run(game: &mut Game) -> Replay {
+        host::take_traces();
+        host::set_gamepad(0, 0);
+        host::set_mouse(0, 0, 0);
+        game.recording = true;
+        run_frames(game, 37);
+        start_game(game);
+
+        // Mouse and gamepad, held for a while like a player would
+        let rng = Rng::with_seed(11);
+        for _ in 0..20_000 {
+            if game.screens.top() == Some(ScreenName::GameOver) {
+                break;
+            }
+            if rng.u8(..) < 10 {
+                host::set_gamepad(0, rng.u8(..) & !BUTTON_1);
+                host::set_mouse(rng.i16(-30..190), rng.i16(-30..190), rng.u8(..4));
+            }
+            run_frames(game, 1);
+        }
+        assert!(game.screens.top() == Some(ScreenName::GameOver));
+        let traces = host::take_traces();
+        let hex = traces
+            .iter()
+            .find_map(|t| t.split_once("frames): ").map(|(_, hex)| hex))
+            .expect("replay printed on game over");
+        let replay = Replay::from_hex(hex).expect("valid replay");
+        assert_eq!(Some(&replay), game.replay.as_ref());
+        assert!(!replay.is_full());
+        replay
+    }
+
+    /// Plays `replay` back, it ends like the run of `game`.
+    fn assert_replays(replay: &Replay, game: &Game) {
+        // Whatever the registers say, the replay is what counts
+        host::set_gamepad(0, BUTTON_UP);
+        let mut replayed = Game::from_replay(replay);
+        replayed.recording = true;
+        run_frames(&mut replayed, replay.frames() as usize);
+        assert!(replayed.screens.top() == Some(ScreenName::GameOver));
+        assert_eq!(replayed.replay, game.replay);
+        assert_eq!(replayed.timers.frame_count, game.timers.frame_count);
+        assert_eq!(replayed.scores.current, game.scores.current);
+        assert_eq!(replayed.scores.multiplier, game.scores.multiplier);
+        assert_eq!(replayed.entities.enemies.len(), game.entities.enemies.len());
+        assert_eq!(replayed.coop.is_some(), game.coop.is_some());
+        assert_eq!(replayed.revive.is_some(), game.revive.is_some());
+        assert_eq!(replayed.arena, game.arena);
+    }
+
+    fn boot() -> Game {
+        let mut game = Game::new();
+        game.start();
+        game
+    }
+
+    fn hold(game: &mut Game, gamepad: usize, buttons: u8, frames: usize) {
+        host::set_gamepad(gamepad, buttons);
+        run_frames(game, frames);
+        host::set_gamepad(gamepad, 0);
+    }
+
+    #[test]
+    fn snapshot_title() {
+        let _session = host::lock();
+        let mut game = boot();
+        run_frames(&mut game, 1);
+        assert_snapshot("title");
+    }
+
+    #[test]
+    fn snapshot_how_to_play_netplay() {
+        let _session = host::lock();
+        host::set_netplay(0b110);
+        let mut game = boot();
+        run_frames(&mut game, 10);
+        press(&mut game, 0, BUTTON_1);
+        run_frames(&mut game, 1);
+        assert_snapshot("how_to_play_netplay");
+    }
+
+    #[test]
+    fn snapshot_how_to_play() {
+        let _session = host::lock();
+        let mut game = boot();
+        run_frames(&mut game, 10);
+        press(&mut game, 0, BUTTON_1);
+        run_frames(&mut game, 1);
+        assert!(game.screens.top() == Some(ScreenName::HowToPlay));
+        assert_snapshot("how_to_play");
+    }
+
+    #[test]
+    fn snapshot_main_game() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        hold(&mut game, 0, BUTTON_RIGHT | BUTTON_UP, 30);
+        run_frames(&mut game, 120);
+        assert!(game.screens.top() == Some(ScreenName::MainGame));
+        assert_eq!(game.entities.enemies.len(), 1);
+        assert_eq!(game.entities.bombs.len(), 1);
+        assert_snapshot("main_game");
+    }
+
+    #[test]
+    fn snapshot_game_over() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        game.scores.current = 12345;
+        game.scores.multiplier = 42;
+        game.entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 should always exist")
+            .entity
+            .life = 0;
+        run_frames(&mut game, 30);
+        assert!(game.screens.top() == Some(ScreenName::GameOver));
+        assert_snapshot("game_over");
+    }
+
+    #[test]
+    fn snapshot_versus_lobby() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_versus(&mut game);
+        hold(&mut game, 1, BUTTON_DOWN | BUTTON_RIGHT, 20);
+        assert_snapshot("versus_lobby");
+    }
+
+    #[test]
+    fn snapshot_versus_results() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_versus(&mut game);
+        press(&mut game, 2, BUTTON_1);
+        press(&mut game, 0, BUTTON_1);
+        game.versus.as_mut().expect("versus match").wins = [1, 2, 0, 0];
+        lose_round(&mut game, PlayerN::P1);
+        assert!(game.screens.top() == Some(ScreenName::Versus));
+        game.entities.players[PlayerN::P3 as usize]
+            .as_mut()
+            .expect("P3 is in the round")
+            .entity
+            .life = 0;
+        run_frames(&mut game, 140);
+        assert!(game.screens.top() == Some(ScreenName::VersusResults));
+        assert_snapshot("versus_results");
+    }
+
+    #[test]
+    fn snapshot_coop_hud() {
+        let _session = host::lock();
+        let mut game = boot();
+        coop_with_p1_out(&mut game);
+        press(&mut game, 3, BUTTON_1);
+        hold(&mut game, 1, BUTTON_RIGHT, 30);
+        run_frames(&mut game, 90);
+        assert_snapshot("coop_hud");
+    }
+
+    #[test]
+    fn snapshot_revive() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_revive(&mut game);
+        hold(&mut game, 1, BUTTON_RIGHT, 30);
+        hit(&mut game, PlayerN::P1);
+        hold(&mut game, 1, BUTTON_LEFT, 26);
+        run_frames(&mut game, 40);
+        assert_snapshot("revive");
+    }
+
+    #[test]
+    fn snapshot_dash() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        press(&mut game, 1, BUTTON_1);
+        press(&mut game, 1, BUTTON_LEFT);
+        press(&mut game, 1, BUTTON_LEFT);
+        run_frames(&mut game, 30);
+        press(&mut game, 0, BUTTON_DOWN);
+        hold(&mut game, 0, BUTTON_DOWN, 3);
+        assert_snapshot("dash");
+    }
+
+    #[test]
+    fn snapshot_archetypes() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        game.entities.enemies.clear();
+        for (n, behavior) in Behavior::SPECIAL.into_iter().enumerate() {
+            let mut enemy = Enemy::new(0, Coord::new(20.0 + 28.0 * n as f64, 20.0), DRAW_COLOR_A)
+                .with_behavior(behavior);
+            enemy.entity.direction = Coord::new(0.0, 1.0);
+            game.entities.enemies.insert(enemy);
+        }
+        run_frames(&mut game, 35);
+        assert_snapshot("archetypes");
+    }
+
+    #[test]
+    fn obstacles_block_players() {
+        let _session = host::lock();
+        let mut game = boot();
+        game.save.settings.arena = 1;
+        start_game(&mut game);
+        assert_eq!(game.entities.obstacles, LAYOUTS[1].obstacles());
+        game.entities.enemies.clear();
+        hold(&mut game, 0, BUTTON_UP, 60);
+        let player = game.entities.players[PlayerN::P1 as usize]
+            .as_ref()
+            .expect("P1 should always exist");
+        // Against the arm of the cross, not at the top of the screen
+        let top = player.entity.position.y;
+        assert!(top > Scalar::from(59u32) && top < Scalar::from(61u32));
+
+        // Gone with the setting, next game
+        game.save.settings.arena = 0;
+        game.restart();
+        assert!(game.entities.obstacles.is_empty());
+    }
+
+    #[test]
+    fn arenas_rotate_with_difficulty() {
+        let _session = host::lock();
+        let mut game = boot();
+        game.save.settings.arena = ROTATING;
+        start_game(&mut game);
+        assert_eq!(game.arena, ARENA_ROTATION[0]);
+        let mut bomb = Bomb::new(&Coord::new(80.0, 30.0));
+        bomb.entity.size = scalar(6.0);
+        game.entities.bombs.insert(bomb);
+
+        game.calibrations.difficulty = 2;
+        run_frames(&mut game, 1);
+        assert_eq!(game.arena, ARENA_ROTATION[2]);
+        assert_eq!(game.entities.obstacles.len(), 8);
+        // Bombs aren't left inside the new walls
+        for bomb in game.entities.bombs.iter() {
+            for obstacle in game.entities.obstacles.iter() {
+                assert!(!obstacle.contains(bomb.entity.center(), scalar(0.0)));
+            }
+        }
+
+        // Enemies only come from the spawn points of the arena
+        let spawns: Vec<Coord> = LAYOUTS[game.arena].spawns().collect();
+        game.timers.respite = 0;
+        game.timers.frame_count = 0;
+        for _ in 0..50 {
+            game.entities.enemies.clear();
+            game.spawn_enemies();
+            let enemy = game.entities.enemies.iter().next().expect("an enemy");
+            assert!(spawns.contains(&enemy.entity.position));
+        }
+    }
+
+    #[test]
+    fn waves_spawn_around_p1() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        game.entities.enemies.clear();
+        game.calibrations.difficulty = 1;
+        // Not during the respite
+        game.timers.respite = 1;
+        game.spawn_waves();
+        game.timers.respite = 0;
+        let first = waves::SEQUENCES[1][0];
+        for _ in 1..first.after {
+            game.spawn_waves();
+        }
+        assert_eq!(game.entities.enemies.len(), 0);
+        game.spawn_waves();
+        assert_eq!(game.entities.enemies.len(), first.wave.count as usize);
+        let p1 = game.entities.players[PlayerN::P1 as usize]
+            .as_ref()
+            .expect("P1 should always exist")
+            .entity
+            .center();
+        for enemy in game.entities.enemies.iter() {
+            assert!(enemy.entity.center().distance_to(&p1) > scalar(60.0));
+        }
+    }
+
+    #[test]
+    fn bosses_at_milestones() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        assert_eq!(BOSS_HEALTH[2], 0);
+        game.scores.multiplier = DIFF_MUL_PROGRESSION[1];
+        game.update_difficulty();
+        assert!(game.entities.boss.is_none());
+        game.scores.multiplier = DIFF_MUL_PROGRESSION[2];
+        game.update_difficulty();
+        assert_eq!(game.calibrations.difficulty, 3);
+        let boss = game.entities.boss.as_mut().expect("a boss at difficulty 3");
+        assert_eq!(boss.health, BOSS_HEALTH[3]);
+        let p1 = game.entities.players[PlayerN::P1 as usize]
+            .as_ref()
+            .expect("P1 should always exist");
+        assert!(!boss.entity.collided_with(&p1.entity, scalar(10.0)));
+
+        // It attacks, and has a song of its own from the next loop
+        boss.age = BOSS_ATTACKS[0].after as u32;
+        game.entities.enemies.clear();
+        game.timers.respite = 0;
+        game.spawn_waves();
+        assert_eq!(
+            game.entities.enemies.len(),
+            BOSS_ATTACKS[0].wave.count as usize
+        );
+        game.timers.frame_count = VOICE_NOTES * MUSIC_SPEED_CTRL - 1;
+        game.sounds_and_music_tick(&Tally::default(), false, false, false);
+        assert_eq!(game.environment.song_nr, BOSS_SONG);
+
+        // Defeating it gives bonus points and a life
+        let (score, multiplier) = (game.scores.current, game.scores.multiplier);
+        let tally = Tally {
+            boss_defeated: true,
+            ..Default::default()
+        };
+        assert!(game.update_score(&tally));
+        assert_eq!(game.scores.current, score + multiplier * SCORE_BOSS);
+        assert_eq!(lives(&game, PlayerN::P1), Some(INIT_LIVES + 1));
+    }
+
+    #[test]
+    fn snapshot_boss() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        game.spawn_boss(BOSS_HEALTH[3]);
+        if let Some(boss) = game.entities.boss.as_mut() {
+            boss.hit(BOSS_HEALTH[3] / 2);
+        }
+        run_frames(&mut game, 155);
+        assert_snapshot("boss");
+    }
+
+    #[test]
+    fn snapshot_obstacles() {
+        let _session = host::lock();
+        let mut game = boot();
+        game.save.settings.arena = 3;
+        start_game(&mut game);
+        run_frames(&mut game, 240);
+        assert_snapshot("obstacles");
+    }
+
+    #[test]
+    fn snapshot_arena_select() {
+        let _session = host::lock();
+        let mut game = boot();
+        run_frames(&mut game, 1);
+        press(&mut game, 0, BUTTON_DOWN);
+        for _ in 0..6 {
+            press(&mut game, 0, BUTTON_DOWN);
+        }
+        press(&mut game, 0, BUTTON_1);
+        assert!(game.screens.top() == Some(ScreenName::ArenaSelect));
+        press(&mut game, 0, BUTTON_RIGHT);
+        press(&mut game, 0, BUTTON_RIGHT);
+        assert_snapshot("arena_select");
+    }
+
+    #[test]
+    fn snapshot_bomb_kinds() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        game.entities.bombs.clear();
+        for (n, kind) in BombKind::SPECIAL.into_iter().enumerate() {
+            let bomb = Bomb::new(&Coord::new(20.0 + 30.0 * n as f64, 20.0)).with_kind(kind);
+            game.entities.bombs.insert(bomb);
+            let mut wave = Bomb::new(&Coord::new(20.0 + 30.0 * n as f64, 110.0)).with_kind(kind);
+            wave.explode(PlayerN::P1, Coord::new(1.0, 0.0));
+            game.entities.bombs.insert(wave);
+        }
+        run_frames(&mut game, 3);
+        assert_snapshot("bomb_kinds");
+    }
+
+    #[test]
+    fn snapshot_power_ups() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        run_frames(&mut game, 30);
+        for (x, kind) in [(30.0, PowerKind::Magnet), (110.0, PowerKind::DoublePoints)] {
+            game.entities
+                .powerups
+                .insert(PowerUp::new(&Coord::new(x, 110.0), kind));
+        }
+        game.entities.players[PlayerN::P1 as usize]
+            .as_mut()
+            .expect("P1 always plays")
+            .powers
+            .grant(PowerKind::Shield);
+        run_frames(&mut game, 2);
+        assert_snapshot("power_ups");
+    }
+
+    #[test]
+    fn snapshot_coop_game_over() {
+        let _session = host::lock();
+        let mut game = boot();
+        coop_with_p1_out(&mut game);
+        let coop = game.coop.as_mut().expect("individual co-op");
+        for (n, score) in [(PlayerN::P1, 4321), (PlayerN::P2, 98765)] {
+            let scores = &mut coop.contenders[n as usize].as_mut().expect("joined").scores;
+            scores.current = score;
+            scores.multiplier = score / 100;
+        }
+        game.entities.players[PlayerN::P2 as usize]
+            .as_mut()
+            .expect("P2 joined")
+            .entity
+            .life = 0;
+        run_frames(&mut game, 30);
+        assert!(game.screens.top() == Some(ScreenName::GameOver));
+        assert_snapshot("coop_game_over");
+    }
+
+    #[test]
+    fn snapshot_pause() {
+        let _session = host::lock();
+        let mut game = boot();
+        start_game(&mut game);
+        run_frames(&mut game, 60);
+        host::set_mouse(80, 80, MOUSE_MIDDLE);
+        run_frames(&mut game, 1);
+        host::set_mouse(80, 80, 0);
+        run_frames(&mut game, 1);
+        host::set_mouse(80, 80, MOUSE_MIDDLE);
+        run_frames(&mut game, 1);
+        host::set_mouse(80, 80, 0);
+        press(&mut game, 0, BUTTON_DOWN);
+        assert!(game.screens.top() == Some(ScreenName::Pause));
+        assert_snapshot("pause");
+    }
+
+    #[test]
+    fn snapshot_settings() {
+        let _session = host::lock();
+        let mut game = boot();
+        run_frames(&mut game, 1);
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_DOWN);
+        press(&mut game, 0, BUTTON_LEFT);
+        assert!(game.screens.top() == Some(ScreenName::Settings));
+        assert_snapshot("settings");
+    }
+
+    #[test]
+    fn snapshot_enter_initials() {
+        let _session = host::lock();
+        host::set_disk(&Save::from_disk(&54321u32.to_le_bytes()).to_bytes());
+        let mut game = boot();
+        start_game(&mut game);
+        game_over(&mut game, 12345);
+        press(&mut game, 0, BUTTON_1);
+        press(&mut game, 0, BUTTON_UP);
+        press(&mut game, 0, BUTTON_1);
+        run_frames(&mut game, 3);
+        assert!(game.screens.top() == Some(ScreenName::EnterInitials));
+        assert_snapshot("enter_initials");
+    }
+}
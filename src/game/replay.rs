@@ -0,0 +1,288 @@
+// A replay is everything needed to play a run again, frame by frame: the seed
+// the rng was initialized with, the mode and the arena the run was played in
+// and the raw input registers of every frame since the run started. The
+// simulation doesn't depend on anything else, so feeding the same inputs to a
+// game restarted with the same seed, mode and arena reproduces the run exactly,
+// whatever is saved on the disk playing it back. Handy for bug reports and for
+// checking high scores.
+//
+// Inputs barely change from one frame to the next, so each of the 9 bytes of
+// `RawInput` is stored as its own lane of (length, value) runs. Binary layout,
+// little endian:
+//
+//     magic "D0RP" | version u8 | seed u64 | mode u8 | arena u8 | frames u32
+//     previous input [u8; 9]
+//     lane 0 runs | lane 1 runs | ... | lane 8 runs
+//
+// where each run is a length (1 to 255) and a value, and the runs of a lane add
+// up to the number of frames. The previous input is the one of the frame before
+// the run started, `Controls` needs it to tell what was just pressed. The arena
+// is the setting: an index in `LAYOUTS`, or `ROTATING`.
+
+use super::{controls::RawInput, save::GameMode};
+use crate::entities::layout::ROTATING;
+
+const MAGIC: &[u8; 4] = b"D0RP";
+const VERSION: u8 = 4;
+const HEADER_SIZE: usize = MAGIC.len() + 1 + 8 + 1 + 1 + 4 + RawInput::SIZE;
+
+// The recording stops once this many runs are stored (2 bytes each), so a
+// long mouse-driven game can't eat the whole heap. A game played with the
+// gamepad uses a few runs per second.
+const MAX_RUNS: usize = 4000;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub arena: u8,
+    pub previous: RawInput,
+    frames: u32,
+    lanes: [Vec<(u8, u8)>; RawInput::SIZE],
+    runs: usize,
+}
+
+impl Replay {
+    /// Starts an empty recording for a run using `seed`, played in `mode` and
+    /// `arena`. `previous` is the input of the frame before the run started.
+    pub fn new(seed: u64, mode: GameMode, arena: u8, previous: RawInput) -> Self {
+        Self {
+            seed,
+            mode,
+            arena,
+            previous,
+            frames: 0,
+            lanes: Default::default(),
+            runs: 0,
+        }
+    }
+
+    /// Number of frames recorded so far.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Whether the recording stopped because it ran out of space.
+    pub fn is_full(&self) -> bool {
+        self.runs >= MAX_RUNS
+    }
+
+    /// Appends the input of the next frame. Once full, inputs are ignored and
+    /// the replay ends there.
+    pub fn push(&mut self, input: RawInput) {
+        if self.is_full() {
+            return;
+        }
+        for (lane, value) in self.lanes.iter_mut().zip(input.to_bytes()) {
+            match lane.last_mut() {
+                Some((length, last)) if *last == value && *length < u8::MAX => *length += 1,
+                _ => {
+                    lane.push((1, value));
+                    self.runs += 1;
+                }
+            }
+        }
+        self.frames += 1;
+    }
+
+    /// Every recorded input, in order.
+    pub fn inputs(&self) -> Vec<RawInput> {
+        let mut bytes = vec![[0; RawInput::SIZE]; self.frames as usize];
+        for (i, lane) in self.lanes.iter().enumerate() {
+            let values = lane
+                .iter()
+                .flat_map(|&(length, value)| std::iter::repeat_n(value, length as usize));
+            for (frame, value) in bytes.iter_mut().zip(values) {
+                frame[i] = value;
+            }
+        }
+        bytes.into_iter().map(RawInput::from_bytes).collect()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + 2 * self.runs);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.mode as u8);
+        bytes.push(self.arena);
+        bytes.extend_from_slice(&self.frames.to_le_bytes());
+        bytes.extend_from_slice(&self.previous.to_bytes());
+        for &(length, value) in self.lanes.iter().flatten() {
+            bytes.extend_from_slice(&[length, value]);
+        }
+        bytes
+    }
+
+    /// Hex dump of `to_bytes`, e.g. to be copied from the debug console.
+    pub fn to_hex(&self) -> String {
+        self.to_bytes().iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+// The cart only writes replays, they are read back natively (see tests).
+#[allow(dead_code)]
+impl Replay {
+    /// Reads a replay written by `to_bytes`, None if it isn't a valid one.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC || bytes[4] != VERSION {
+            return None;
+        }
+        let seed = u64::from_le_bytes(bytes[5..13].try_into().ok()?);
+        let mode = GameMode::from_u8(bytes[13])?;
+        let arena = Some(bytes[14]).filter(|&arena| arena <= ROTATING)?;
+        let frames = u32::from_le_bytes(bytes[15..19].try_into().ok()?);
+        let previous = RawInput::from_bytes(bytes[19..HEADER_SIZE].try_into().ok()?);
+
+        let mut replay = Self::new(seed, mode, arena, previous);
+        replay.frames = frames;
+        let mut runs = bytes[HEADER_SIZE..].chunks(2);
+        for lane in replay.lanes.iter_mut() {
+            let mut covered = 0;
+            while covered < frames {
+                let &[length, value] = runs.next()? else {
+                    return None;
+                };
+                if length == 0 {
+                    return None;
+                }
+                covered += length as u32;
+                lane.push((length, value));
+            }
+            if covered != frames {
+                return None;
+            }
+            replay.runs += lane.len();
+        }
+        runs.next().is_none().then_some(replay)
+    }
+
+    /// Reads a replay written by `to_hex`. Whitespace is ignored.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let digits: Vec<u8> = hex
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()?;
+        if !digits.len().is_multiple_of(2) {
+            return None;
+        }
+        let bytes: Vec<u8> = digits.chunks(2).map(|d| d[0] << 4 | d[1]).collect();
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Feeds the inputs of a replay back, one frame at a time.
+pub struct Playback {
+    /// Taken by the restart that starts the run.
+    pub seed: Option<u64>,
+    /// Played in instead of the saved mode and arena
+    pub mode: GameMode,
+    pub arena: u8,
+    inputs: std::vec::IntoIter<RawInput>,
+}
+
+impl Playback {
+    pub fn new(replay: &Replay) -> Self {
+        Self {
+            seed: Some(replay.seed),
+            mode: replay.mode,
+            arena: replay.arena,
+            inputs: replay.inputs().into_iter(),
+        }
+    }
+
+    /// Input of the next frame, None once the replay is over.
+    pub fn next_input(&mut self) -> Option<RawInput> {
+        self.inputs.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastrand::Rng;
+
+    fn random_inputs(rng: &Rng, frames: usize) -> Vec<RawInput> {
+        let mut input = RawInput::default();
+        (0..frames)
+            .map(|_| {
+                // Hold inputs for a while, like a player would
+                if rng.u8(..) < 20 {
+                    input = RawInput {
+                        gamepads: [rng.u8(..), 0, rng.u8(..) & 0x0f, 0],
+                        mouse_x: rng.i16(-20..180),
+                        mouse_y: rng.i16(-20..180),
+                        mouse_buttons: rng.u8(..8),
+                    };
+                }
+                input
+            })
+            .collect()
+    }
+
+    fn record(seed: u64, inputs: &[RawInput]) -> Replay {
+        let mut replay = Replay::new(seed, GameMode::Individual, 2, RawInput::default());
+        for &input in inputs {
+            replay.push(input);
+        }
+        replay
+    }
+
+    #[test]
+    fn inputs_roundtrip() {
+        let inputs = random_inputs(&Rng::with_seed(3), 2000);
+        let replay = record(42, &inputs);
+        assert_eq!(replay.frames(), 2000);
+        assert_eq!(replay.inputs(), inputs);
+
+        let bytes = replay.to_bytes();
+        assert!(bytes.len() < inputs.len() * RawInput::SIZE / 4);
+        assert_eq!(Replay::from_bytes(&bytes), Some(replay.clone()));
+        assert_eq!(Replay::from_hex(&replay.to_hex()), Some(replay));
+    }
+
+    #[test]
+    fn long_runs() {
+        let inputs = vec![RawInput::default(); 1000];
+        let replay = record(0, &inputs);
+        // 1000 frames need 4 runs of at most 255 frames on each lane
+        assert_eq!(
+            replay.to_bytes().len(),
+            HEADER_SIZE + RawInput::SIZE * 4 * 2
+        );
+        assert_eq!(replay.inputs(), inputs);
+    }
+
+    #[test]
+    fn invalid_replays() {
+        let bytes = record(1, &random_inputs(&Rng::with_seed(5), 300)).to_bytes();
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(Replay::from_bytes(&[bytes.as_slice(), &[1, 0]].concat()).is_none());
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] += 1;
+        assert!(Replay::from_bytes(&wrong_version).is_none());
+        let mut unknown_mode = bytes.clone();
+        unknown_mode[13] = 9;
+        assert!(Replay::from_bytes(&unknown_mode).is_none());
+        let mut unknown_arena = bytes.clone();
+        unknown_arena[14] = ROTATING + 1;
+        assert!(Replay::from_bytes(&unknown_arena).is_none());
+        assert!(Replay::from_hex("D0RP").is_none());
+    }
+
+    #[test]
+    fn recording_stops_when_full() {
+        let mut replay = Replay::new(0, GameMode::Shared, 0, RawInput::default());
+        let mut frames = 0;
+        while !replay.is_full() {
+            replay.push(RawInput {
+                mouse_x: frames as i16,
+                ..Default::default()
+            });
+            frames += 1;
+        }
+        replay.push(RawInput::default());
+        assert_eq!(replay.frames(), frames);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    common::types::{scalar, ScalarExt},
+    entities::{
+        manager::EntityManager as Entities,
+        player::{Player, PlayerN},
+        traits::Visible,
+    },
+    graphics::draw_utils,
+};
+
+/// Frames a downed player waits for help before being out of the run
+pub const DOWNED_FRAMES: u32 = 10 * 60;
+/// Frames a teammate has to touch a downed player to revive them
+pub const REVIVE_FRAMES: u32 = 90;
+/// Downed players are drawn as an outline of their disk
+const GHOST_COLOR: u16 = 0x40;
+/// Ghosts blink in their last frames
+const BLINK_FRAMES: u32 = 3 * 60;
+
+/// A downed player, left where they were hit
+pub struct Ghost {
+    player: Player,
+    /// Color the player gets back when revived
+    color: u16,
+    /// Frames left before being out
+    pub timer: u32,
+    /// Frames a teammate has been touching them, without letting go
+    pub progress: u32,
+}
+
+/// Co-op with revives: a player who's hit isn't out right away, they're
+/// downed where they were hit while the game goes on. A teammate touching
+/// them for long enough brings them back, otherwise they're out of the run
+/// once their timer runs out.
+#[derive(Default)]
+pub struct Revive {
+    pub ghosts: [Option<Ghost>; 4],
+    /// Players nobody revived in time
+    pub out: [bool; 4],
+}
+
+impl Revive {
+    /// Adds `player_n`, unless they're downed or out of this run.
+    pub fn join(&mut self, entities: &mut Entities, player_n: PlayerN) -> bool {
+        let n = player_n as usize;
+        if self.ghosts[n].is_some() || self.out[n] {
+            return false;
+        }
+        entities.players[n] = Some(Player::new(player_n));
+        true
+    }
+
+    /// Takes `player_n` out of the field, leaving their ghost behind.
+    pub fn down(&mut self, entities: &mut Entities, player_n: PlayerN) {
+        let Some(mut player) = entities.players[player_n as usize].take() else {
+            return;
+        };
+        let color = player.entity.color;
+        player.entity.color = GHOST_COLOR;
+        player.stop();
+        self.ghosts[player_n as usize] = Some(Ghost {
+            player,
+            color,
+            timer: DOWNED_FRAMES,
+            progress: 0,
+        });
+    }
+
+    /// Takes `player_n` out of the run, standing or downed: leaving doesn't
+    /// spare anybody the wait for help. Returns whether they were in it.
+    pub fn leave(&mut self, entities: &mut Entities, player_n: PlayerN) -> bool {
+        let n = player_n as usize;
+        let downed = self.ghosts[n].take().is_some();
+        if entities.remove_player(player_n).is_none() && !downed {
+            return false;
+        }
+        self.out[n] = true;
+        true
+    }
+
+    /// Brings back the downed players touched long enough by a teammate, and
+    /// takes out the ones whose timer ran out. Returns whether someone was
+    /// revived.
+    pub fn update(&mut self, entities: &mut Entities) -> bool {
+        let mut revived = false;
+        for (n, slot) in self.ghosts.iter_mut().enumerate() {
+            let Some(ghost) = slot.as_mut() else {
+                continue;
+            };
+            let touched = entities.players.iter().flatten().any(|player| {
+                player
+                    .entity
+                    .collided_with(&ghost.player.entity, scalar(0.0))
+            });
+            ghost.progress = if touched { ghost.progress + 1 } else { 0 };
+            ghost.timer = ghost.timer.saturating_sub(1);
+            if ghost.progress >= REVIVE_FRAMES {
+                let Ghost {
+                    mut player, color, ..
+                } = slot.take().expect("ghost was just updated");
+                player.entity.color = color;
+                entities.players[n] = Some(player);
+                revived = true;
+            } else if ghost.timer == 0 {
+                *slot = None;
+                self.out[n] = true;
+            }
+        }
+        revived
+    }
+
+    /// Players who joined the run: standing, downed or out.
+    pub fn players(&self, entities: &Entities) -> usize {
+        (0..self.out.len())
+            .filter(|&n| entities.players[n].is_some() || self.ghosts[n].is_some() || self.out[n])
+            .count()
+    }
+
+    /// Downed players, with the seconds they have left and how far their
+    /// revive got.
+    pub fn draw(&self, frame_count: usize) {
+        for ghost in self.ghosts.iter().flatten() {
+            if ghost.timer > BLINK_FRAMES || (frame_count / 8).is_multiple_of(2) {
+                ghost.player.draw();
+            }
+            let entity = &ghost.player.entity;
+            draw_utils::draw_downed(
+                entity.center().x.to_i32(),
+                entity.position.y.to_i32(),
+                entity.size.to_i32(),
+                ghost.timer.div_ceil(60),
+                ghost.progress * 100 / REVIVE_FRAMES,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ghost(revive: &Revive, player_n: PlayerN) -> Option<&Ghost> {
+        revive.ghosts[player_n as usize].as_ref()
+    }
+
+    #[test]
+    fn teammates_revive_downed_players() {
+        let mut entities = Entities::new();
+        let mut revive = Revive::default();
+        assert!(revive.join(&mut entities, PlayerN::P2));
+        let color = entities.players[PlayerN::P1 as usize]
+            .as_ref()
+            .expect("P1 always starts")
+            .entity
+            .color;
+
+        revive.down(&mut entities, PlayerN::P1);
+        assert!(entities.players[PlayerN::P1 as usize].is_none());
+        assert!(!revive.join(&mut entities, PlayerN::P1));
+
+        // Both start in the middle: P2 is on P1's ghost, until it moves away
+        for _ in 0..REVIVE_FRAMES - 1 {
+            assert!(!revive.update(&mut entities));
+        }
+        entities.players[PlayerN::P2 as usize]
+            .as_mut()
+            .expect("P2 joined")
+            .entity
+            .position
+            .x += scalar(20.0);
+        assert!(!revive.update(&mut entities));
+        assert_eq!(ghost(&revive, PlayerN::P1).map(|g| g.progress), Some(0));
+        assert_eq!(
+            ghost(&revive, PlayerN::P1).map(|g| g.timer),
+            Some(DOWNED_FRAMES - REVIVE_FRAMES)
+        );
+
+        entities.players[PlayerN::P2 as usize]
+            .as_mut()
+            .expect("P2 joined")
+            .entity
+            .position
+            .x -= scalar(20.0);
+        for _ in 0..REVIVE_FRAMES - 1 {
+            assert!(!revive.update(&mut entities));
+        }
+        assert!(revive.update(&mut entities));
+        assert!(ghost(&revive, PlayerN::P1).is_none());
+        let player = entities.players[PlayerN::P1 as usize]
+            .as_ref()
+            .expect("P1 was revived");
+        assert_eq!(player.entity.color, color);
+    }
+
+    #[test]
+    fn nobody_comes_in_time() {
+        let mut entities = Entities::new();
+        let mut revive = Revive::default();
+        revive.down(&mut entities, PlayerN::P1);
+        for _ in 0..DOWNED_FRAMES {
+            assert!(!revive.update(&mut entities));
+        }
+        assert!(ghost(&revive, PlayerN::P1).is_none());
+        assert!(revive.out[PlayerN::P1 as usize]);
+        assert!(!revive.join(&mut entities, PlayerN::P1));
+        assert!(entities.players.iter().all(Option::is_none));
+    }
+}
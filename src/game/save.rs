@@ -0,0 +1,430 @@
+// Everything the game keeps on disk, within the 1024 bytes WASM-4 gives a cart.
+// Layout, little endian:
+//
+//     magic "D0SV" | version u8 | payload length u16 | payload crc32 u32 | payload
+//
+// The payload is a list of sections: tag u8 | length u8 | data. Unknown
+// sections are skipped and missing ones (or missing fields at the end of a
+// section) get their default value, so new data only needs a new section or
+// new fields at the end of one. The version changes when the meaning of
+// existing data changes, older payloads are then converted while loading.
+//
+// Before this format the disk only held the high score, as a bare u32.
+
+use super::leaderboard::{Entry, Leaderboard, LEADERBOARD_SIZE};
+use crate::{
+    common::crc::crc32,
+    entities::layout::ROTATING,
+    wasm4::{self, DISK_SIZE},
+};
+
+const MAGIC: &[u8; 4] = b"D0SV";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = MAGIC.len() + 1 + 2 + 4;
+const LEGACY_SIZE: usize = 4;
+
+// Only read, from saves made before the leaderboard
+const TAG_HIGH_SCORE: u8 = 1;
+const TAG_SETTINGS: u8 = 2;
+const TAG_STATS: u8 = 3;
+// Size of an entry, then the entries: leaves room for new fields in entries
+const TAG_LEADERBOARD: u8 = 4;
+const ENTRY_SIZE: usize = 3 + 4 + 4 + 1 + 1;
+
+/// Highest music and sound effects volume level
+pub const MAX_VOLUME: u8 = 10;
+
+/// How players play together
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GameMode {
+    /// P1 holds everybody's lives and they score together
+    #[default]
+    Shared,
+    /// Every player has their own lives and score
+    Individual,
+    /// Players send enemies at each other, for rounds
+    Versus,
+    /// Players hit are downed until a teammate revives them
+    Revive,
+}
+
+impl GameMode {
+    const ALL: [GameMode; 4] = [
+        GameMode::Shared,
+        GameMode::Individual,
+        GameMode::Versus,
+        GameMode::Revive,
+    ];
+
+    /// The mode `step` places after this one, wrapping around.
+    pub fn step(self, step: i32) -> Self {
+        let index = (self as i32 + step).rem_euclid(Self::ALL.len() as i32);
+        Self::ALL[index as usize]
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+}
+
+/// Player preferences
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Settings {
+    pub palette: u8,
+    /// From 0 (off) to MAX_VOLUME
+    pub music_volume: u8,
+    pub sfx_volume: u8,
+    pub mouse_control: bool,
+    /// The on-screen gamepad of touch screens
+    pub gamepad_overlay: bool,
+    pub mode: GameMode,
+    /// Index of the arena in `LAYOUTS`, or `ROTATING`
+    pub arena: u8,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            palette: 0,
+            music_volume: MAX_VOLUME,
+            sfx_volume: MAX_VOLUME,
+            mouse_control: true,
+            gamepad_overlay: true,
+            mode: GameMode::Shared,
+            arena: 0,
+        }
+    }
+}
+
+impl Settings {
+    /// These settings, with the ones every netplay client must agree on (mode,
+    /// arena and palette) taken from `shared`.
+    pub fn sharing(self, shared: Settings) -> Self {
+        Self {
+            palette: shared.palette,
+            mode: shared.mode,
+            arena: shared.arena,
+            ..self
+        }
+    }
+}
+
+/// Totals over every game played on this disk
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Stats {
+    pub games_played: u32,
+    pub enemies_absorbed: u32,
+    pub bombs_exploded: u32,
+    pub frames_played: u32,
+    pub best_multiplier: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Save {
+    pub leaderboard: Leaderboard,
+    pub settings: Settings,
+    pub stats: Stats,
+}
+
+impl Save {
+    /// Reads the disk. A blank or corrupted disk gives a fresh save.
+    pub fn load() -> Self {
+        let mut buffer = [0u8; DISK_SIZE];
+        let len = unsafe { wasm4::diskr(buffer.as_mut_ptr(), buffer.len() as u32) };
+        Self::from_disk(&buffer[..len as usize])
+    }
+
+    pub fn store(&self) {
+        let bytes = self.to_bytes();
+        unsafe {
+            wasm4::diskw(bytes.as_ptr(), bytes.len() as u32);
+        }
+    }
+
+    /// Whatever the disk contains, the best save that can be made of it.
+    pub fn from_disk(bytes: &[u8]) -> Self {
+        if bytes.is_empty() {
+            return Self::default();
+        }
+        if bytes.len() == LEGACY_SIZE && !bytes.starts_with(MAGIC) {
+            let mut save = Self::default();
+            save.migrate_high_score(read_u32(bytes, 0));
+            return save;
+        }
+        Self::from_bytes(bytes).unwrap_or_else(|| {
+            wasm4::trace("Save data is corrupted, starting from scratch");
+            Self::default()
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = vec![];
+        let mut leaderboard = vec![ENTRY_SIZE as u8];
+        for entry in self.leaderboard.entries() {
+            leaderboard.extend_from_slice(&entry.initials);
+            leaderboard.extend_from_slice(&entry.score.to_le_bytes());
+            leaderboard.extend_from_slice(&entry.multiplier.to_le_bytes());
+            leaderboard.push(entry.difficulty);
+            leaderboard.push(entry.players);
+        }
+        write_section(&mut payload, TAG_LEADERBOARD, &leaderboard);
+        let settings = &self.settings;
+        write_section(
+            &mut payload,
+            TAG_SETTINGS,
+            &[
+                settings.palette,
+                settings.music_volume,
+                settings.sfx_volume,
+                settings.mouse_control as u8,
+                settings.gamepad_overlay as u8,
+                settings.mode as u8,
+                settings.arena,
+            ],
+        );
+        let stats = &self.stats;
+        write_section(
+            &mut payload,
+            TAG_STATS,
+            &[
+                stats.games_played,
+                stats.enemies_absorbed,
+                stats.bombs_exploded,
+                stats.frames_played,
+                stats.best_multiplier,
+            ]
+            .map(u32::to_le_bytes)
+            .concat(),
+        );
+
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        debug_assert!(bytes.len() <= DISK_SIZE, "Save doesn't fit on disk");
+        bytes
+    }
+
+    /// Reads a save written by `to_bytes`, None if it isn't a valid one.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE || !bytes.starts_with(MAGIC) {
+            return None;
+        }
+        // Saves from a newer version of the game can't be understood
+        let version = bytes[4];
+        if version == 0 || version > VERSION {
+            return None;
+        }
+        let len = u16::from_le_bytes([bytes[5], bytes[6]]) as usize;
+        let payload = bytes.get(HEADER_SIZE..HEADER_SIZE + len)?;
+        if crc32(payload) != read_u32(bytes, 7) {
+            return None;
+        }
+
+        let mut save = Self::default();
+        let mut rest = payload;
+        while !rest.is_empty() {
+            let (&[tag, len], data) = rest.split_first_chunk()?;
+            let data = data.get(..len as usize)?;
+            match tag {
+                TAG_HIGH_SCORE => save.migrate_high_score(read_u32(data, 0)),
+                TAG_LEADERBOARD => save.leaderboard = read_leaderboard(data),
+                TAG_SETTINGS => save.settings = read_settings(data),
+                TAG_STATS => {
+                    save.stats = Stats {
+                        games_played: read_u32(data, 0),
+                        enemies_absorbed: read_u32(data, 4),
+                        bombs_exploded: read_u32(data, 8),
+                        frames_played: read_u32(data, 12),
+                        best_multiplier: read_u32(data, 16),
+                    }
+                }
+                _ => (),
+            }
+            rest = &rest[2 + data.len()..];
+        }
+        Some(save)
+    }
+
+    /// The high score of older versions becomes an anonymous leaderboard entry.
+    fn migrate_high_score(&mut self, score: u32) {
+        self.leaderboard.insert(Entry {
+            initials: *b"---",
+            score,
+            multiplier: 0,
+            difficulty: 0,
+            players: 1,
+        });
+    }
+}
+
+/// Settings missing from `data` keep their default value.
+fn read_settings(data: &[u8]) -> Settings {
+    let default = Settings::default();
+    let byte = |offset: usize, default: u8| data.get(offset).copied().unwrap_or(default);
+    Settings {
+        palette: byte(0, default.palette),
+        music_volume: byte(1, default.music_volume).min(MAX_VOLUME),
+        sfx_volume: byte(2, default.sfx_volume).min(MAX_VOLUME),
+        mouse_control: byte(3, default.mouse_control as u8) != 0,
+        gamepad_overlay: byte(4, default.gamepad_overlay as u8) != 0,
+        mode: GameMode::from_u8(byte(5, default.mode as u8)).unwrap_or_default(),
+        arena: byte(6, default.arena).min(ROTATING),
+    }
+}
+
+fn read_leaderboard(data: &[u8]) -> Leaderboard {
+    let mut leaderboard = Leaderboard::default();
+    let Some((&entry_size, entries)) = data.split_first() else {
+        return leaderboard;
+    };
+    let entry_size = (entry_size as usize).max(1);
+    for entry in entries.chunks_exact(entry_size).take(LEADERBOARD_SIZE) {
+        let byte = |offset: usize| entry.get(offset).copied().unwrap_or(0);
+        leaderboard.insert(Entry {
+            initials: [byte(0), byte(1), byte(2)],
+            score: read_u32(entry, 3),
+            multiplier: read_u32(entry, 7),
+            difficulty: byte(11),
+            players: byte(12),
+        });
+    }
+    leaderboard
+}
+
+fn write_section(payload: &mut Vec<u8>, tag: u8, data: &[u8]) {
+    payload.push(tag);
+    payload.push(data.len() as u8);
+    payload.extend_from_slice(data);
+}
+
+/// The u32 at `offset`, 0 if the data is too short to hold it.
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    data.get(offset..offset + 4)
+        .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Save {
+        let mut leaderboard = Leaderboard::default();
+        for (i, initials) in [b"MAX", b"ABC", b"ZZZ"].into_iter().enumerate() {
+            leaderboard.insert(Entry {
+                initials: *initials,
+                score: 123_456 * (i as u32 + 1),
+                multiplier: 321,
+                difficulty: 7,
+                players: i as u8 + 1,
+            });
+        }
+        Save {
+            leaderboard,
+            settings: Settings {
+                palette: 3,
+                music_volume: 4,
+                sfx_volume: 0,
+                mouse_control: false,
+                gamepad_overlay: false,
+                mode: GameMode::Versus,
+                arena: ROTATING,
+            },
+            stats: Stats {
+                games_played: 12,
+                enemies_absorbed: 3456,
+                bombs_exploded: 78,
+                frames_played: 90_000,
+                best_multiplier: 321,
+            },
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let save = example();
+        let bytes = save.to_bytes();
+        assert!(bytes.len() <= DISK_SIZE);
+        assert_eq!(save.leaderboard.entries()[0].initials, *b"ZZZ");
+        assert_eq!(Save::from_bytes(&bytes), Some(save.clone()));
+        assert_eq!(Save::from_disk(&bytes), save);
+    }
+
+    #[test]
+    fn blank_disk() {
+        assert_eq!(Save::from_disk(&[]), Save::default());
+    }
+
+    #[test]
+    fn legacy_high_score() {
+        let save = Save::from_disk(&98_765u32.to_le_bytes());
+        assert_eq!(save.leaderboard.high_score(), 98_765);
+        assert_eq!(save.leaderboard.entries().len(), 1);
+        assert_eq!(save.stats, Stats::default());
+    }
+
+    #[test]
+    fn corrupted_data() {
+        let bytes = example().to_bytes();
+        for i in 0..bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[i] ^= 0x10;
+            assert_eq!(Save::from_bytes(&corrupted), None, "byte {i} flipped");
+        }
+        assert_eq!(Save::from_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Save::from_disk(&bytes[..20]), Save::default());
+    }
+
+    #[test]
+    fn unknown_and_short_sections() {
+        let mut payload = vec![];
+        write_section(&mut payload, 200, &[1, 2, 3]);
+        write_section(&mut payload, TAG_HIGH_SCORE, &77u32.to_le_bytes());
+        // Written by versions with fewer fields
+        write_section(&mut payload, TAG_STATS, &5u32.to_le_bytes());
+        write_section(&mut payload, TAG_SETTINGS, &[2]);
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+
+        let save = Save::from_bytes(&bytes).expect("valid save");
+        assert_eq!(save.leaderboard.high_score(), 77);
+        assert_eq!(save.stats.games_played, 5);
+        assert_eq!(save.stats.best_multiplier, 0);
+        assert_eq!(
+            save.settings,
+            Settings {
+                palette: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn full_leaderboard_fits() {
+        let mut save = example();
+        for score in 1..=20 {
+            save.leaderboard.insert(Entry {
+                initials: *b"AAA",
+                score: u32::MAX - score,
+                multiplier: u32::MAX,
+                difficulty: u8::MAX,
+                players: 4,
+            });
+        }
+        let bytes = save.to_bytes();
+        assert!(bytes.len() <= DISK_SIZE);
+        assert_eq!(Save::from_bytes(&bytes), Some(save));
+    }
+
+    #[test]
+    fn newer_version() {
+        let mut bytes = example().to_bytes();
+        bytes[4] = VERSION + 1;
+        assert_eq!(Save::from_bytes(&bytes), None);
+    }
+}
@@ -1,87 +1,107 @@
-use crate::{
-    common::calibrations::{SCORE_BOMB, SCORE_ENEMY},
-    wasm4,
-};
+use crate::common::calibrations::SCORE_ENEMY;
 
 /// Score simply depends on enemies absorbed and bombs exploded. Each enemy/bomb
 /// gives an increasing amount of score, defined by the multiplier.
 pub struct Scores {
     pub current: u32,
     pub multiplier: u32,
-    pub high: u32,
+    /// Enemies absorbed and bombs exploded in this run
+    pub enemies: u32,
+    pub bombs: u32,
 }
 impl Scores {
     pub fn new() -> Self {
         Self {
             current: 0,
             multiplier: 1,
-            high: Scores::get_high_score(),
-        }
-    }
-    pub fn get_high_score() -> u32 {
-        unsafe {
-            let mut buffer = [0u8; core::mem::size_of::<u32>()];
-            wasm4::diskr(buffer.as_mut_ptr(), buffer.len() as u32);
-            u32::from_le_bytes(buffer)
-        }
-    }
-    pub fn save_high_score(&self, high_score: u32) {
-        unsafe {
-            let high_score_bytes = high_score.to_le_bytes();
-            wasm4::diskw(
-                high_score_bytes.as_ptr(),
-                core::mem::size_of::<u32>() as u32,
-            );
+            enemies: 0,
+            bombs: 0,
         }
     }
     /// Updates the player's score depening on how many enemies were killed and
-    /// bombs exploded in the current frame.
-    pub fn update(&mut self, enemies_killed: u32, bombs_exploded: u32) {
-        for _ in 0..bombs_exploded {
+    /// bombs exploded in the current frame. The bombs score `bomb_points` all
+    /// together, which depend on their kind. Points are multiplied by
+    /// `factor`, DOUBLE_POINTS with the double-points power-up.
+    pub fn update(
+        &mut self,
+        enemies_killed: u32,
+        bombs_exploded: u32,
+        bomb_points: u32,
+        factor: u32,
+    ) {
+        self.enemies = self.enemies.saturating_add(enemies_killed);
+        self.bombs = self.bombs.saturating_add(bombs_exploded);
+        self.current = self
+            .current
+            .wrapping_add(self.multiplier.wrapping_mul(bomb_points * factor));
+        self.multiplier = self.multiplier.wrapping_add(bomb_points);
+        for _ in 0..enemies_killed {
             self.current = self
                 .current
-                .wrapping_add(self.multiplier.wrapping_mul(SCORE_BOMB));
-            self.multiplier = self.multiplier.wrapping_add(SCORE_BOMB);
-        }
-        for _ in 0..enemies_killed {
-            self.current = self.current.wrapping_add(self.multiplier);
+                .wrapping_add(self.multiplier.wrapping_mul(factor));
             self.multiplier = self.multiplier.wrapping_add(SCORE_ENEMY);
         }
         self.current = self.current.clamp(0, 999_999_999);
     }
+
+    /// Scores `points` times the multiplier and `factor`, without raising the
+    /// multiplier: the bonus for defeating a boss.
+    pub fn bonus(&mut self, points: u32, factor: u32) {
+        self.current = self
+            .current
+            .wrapping_add(self.multiplier.wrapping_mul(points * factor))
+            .clamp(0, 999_999_999);
+    }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::calibrations::{DOUBLE_POINTS, SCORE_BOMB};
     #[test]
     fn test_update() {
-        let mut scores = Scores {
-            current: 0,
-            multiplier: 1,
-            high: 0,
-        };
+        let mut scores = Scores::new();
         let init_score = scores.current;
         let init_multiplier = scores.multiplier;
-        scores.update(0, 0);
+        scores.update(0, 0, 0, 1);
         assert_eq!(scores.current, 0);
         assert_eq!(scores.multiplier, init_multiplier);
 
-        scores.update(1, 0);
+        scores.update(1, 0, 0, 1);
         assert_eq!(scores.current, init_score + init_multiplier);
         assert_eq!(scores.multiplier, init_multiplier + SCORE_ENEMY);
 
-        scores.update(0, 1);
+        scores.update(0, 1, SCORE_BOMB, 1);
         let mut exp_score =
             init_score + init_multiplier + (init_multiplier + SCORE_ENEMY) * SCORE_BOMB;
         assert_eq!(scores.current, exp_score);
 
         let mut exp_mult = init_multiplier + SCORE_ENEMY + SCORE_BOMB;
         assert_eq!(scores.multiplier, exp_mult);
-        scores.update(1, 1);
+        scores.update(1, 1, SCORE_BOMB, 1);
         exp_score += (exp_mult * SCORE_BOMB) + (exp_mult + SCORE_BOMB);
 
         assert_eq!(scores.current, exp_score);
         exp_mult += SCORE_BOMB + SCORE_ENEMY;
         assert_eq!(scores.multiplier, exp_mult);
+        assert_eq!((scores.enemies, scores.bombs), (2, 2));
+    }
+
+    #[test]
+    fn double_points() {
+        let mut scores = Scores::new();
+        scores.update(1, 1, SCORE_BOMB, DOUBLE_POINTS);
+        let multiplier = 1 + SCORE_BOMB;
+        assert_eq!(scores.current, 2 * SCORE_BOMB + 2 * multiplier);
+        assert_eq!(scores.multiplier, multiplier + SCORE_ENEMY);
+    }
+
+    #[test]
+    fn bonus() {
+        let mut scores = Scores::new();
+        scores.update(0, 1, SCORE_BOMB, 1);
+        let (current, multiplier) = (scores.current, scores.multiplier);
+        scores.bonus(100, DOUBLE_POINTS);
+        assert_eq!(scores.current, current + multiplier * 100 * DOUBLE_POINTS);
+        assert_eq!(scores.multiplier, multiplier);
     }
 }
@@ -0,0 +1,67 @@
+use super::{
+    confirm_pressed,
+    settings::{arena_name, arena_step},
+    Screen, ScreenName, Transition,
+};
+use crate::{
+    entities::layout::LAYOUTS,
+    game::{
+        controls::{ControlEvent, Repeater},
+        Game,
+    },
+    graphics::screen,
+};
+
+/// Preview of every arena, over the settings menu. Left/right browse them, X
+/// picks the one shown and goes back to the settings.
+pub struct ArenaSelect {
+    arena: u8,
+    repeat: Repeater,
+    // Frames since the screen was opened, for blinking
+    tick: usize,
+}
+
+impl ArenaSelect {
+    /// Starts from `arena`, the one in the settings.
+    pub fn new(arena: u8) -> Self {
+        Self {
+            arena,
+            repeat: Repeater::default(),
+            tick: 0,
+        }
+    }
+}
+
+impl Screen for ArenaSelect {
+    fn name(&self) -> ScreenName {
+        ScreenName::ArenaSelect
+    }
+
+    fn update(&mut self, game: &mut Game, events: &[ControlEvent]) -> Transition {
+        self.tick += 1;
+        let mut steer = 0;
+        for event in events {
+            match event {
+                ControlEvent::Left(_) => steer = -1,
+                ControlEvent::Right(_) => steer = 1,
+                _ => (),
+            }
+        }
+        if self.repeat.fire(steer != 0) {
+            self.arena = arena_step(self.arena, steer);
+        }
+        if !confirm_pressed(events) {
+            return Transition::Stay;
+        }
+        game.save.settings.arena = self.arena;
+        Transition::Pop
+    }
+
+    fn draw(&self, _game: &Game) {
+        screen::arena_select(
+            self.tick,
+            arena_name(self.arena),
+            LAYOUTS.get(self.arena as usize),
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use super::{confirm_pressed, MainGame, Screen, ScreenName, Transition};
+use crate::{
+    game::{controls::ControlEvent, leaderboard::InitialsEntry, Game},
+    graphics::screen,
+};
+
+/// The leaderboard, where the initials of the run that just ended are entered.
+pub struct EnterInitials {
+    entry: InitialsEntry,
+}
+
+impl EnterInitials {
+    pub fn new(rank: usize) -> Self {
+        Self {
+            entry: InitialsEntry::new(rank),
+        }
+    }
+}
+
+impl Screen for EnterInitials {
+    fn name(&self) -> ScreenName {
+        ScreenName::EnterInitials
+    }
+
+    fn update(&mut self, game: &mut Game, events: &[ControlEvent]) -> Transition {
+        // Directions held, by any player
+        let mut steer = (0, 0);
+        for event in events {
+            match event {
+                ControlEvent::Left(_) => steer.0 = -1,
+                ControlEvent::Right(_) => steer.0 = 1,
+                ControlEvent::Up(_) => steer.1 = -1,
+                ControlEvent::Down(_) => steer.1 = 1,
+                _ => (),
+            }
+        }
+        if let Some(initials) = game.save.leaderboard.initials_mut(self.entry.rank) {
+            self.entry.steer(initials, steer.0, steer.1);
+        }
+
+        // X: next letter, then restart once they're all in
+        if !confirm_pressed(events) {
+            return Transition::Stay;
+        }
+        if self.entry.is_done() {
+            return Transition::Replace(Box::new(MainGame));
+        }
+        self.entry.confirm();
+        if self.entry.is_done() {
+            game.store_save();
+        }
+        Transition::Stay
+    }
+
+    fn draw(&self, game: &Game) {
+        let rows: Vec<([u8; 3], u32, u8)> = game
+            .save
+            .leaderboard
+            .entries()
+            .iter()
+            .map(|entry| (entry.initials, entry.score, entry.players))
+            .collect();
+        screen::enter_initials(
+            game.timers.frame_count,
+            &rows,
+            self.entry.rank,
+            self.entry.cursor,
+        );
+    }
+}
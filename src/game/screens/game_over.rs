@@ -0,0 +1,59 @@
+use super::{confirm_pressed, EnterInitials, MainGame, Screen, ScreenName, Transition};
+use crate::{
+    game::{
+        controls::{self, ControlEvent},
+        Game,
+    },
+    graphics::screen,
+    sound::music::GAME_OVER_SONG,
+};
+
+pub struct GameOver {
+    /// Leaderboard position of the run that just ended, if it made it there
+    rank: Option<usize>,
+    new_high_score: bool,
+}
+
+impl GameOver {
+    pub fn new(rank: Option<usize>, new_high_score: bool) -> Self {
+        Self {
+            rank,
+            new_high_score,
+        }
+    }
+}
+
+impl Screen for GameOver {
+    fn name(&self) -> ScreenName {
+        ScreenName::GameOver
+    }
+
+    fn enter(&mut self, game: &mut Game) {
+        game.environment.song_nr = GAME_OVER_SONG;
+        game.timers.song_tick = 0;
+    }
+
+    fn update(&mut self, _game: &mut Game, events: &[ControlEvent]) -> Transition {
+        if !confirm_pressed(events) {
+            return Transition::Stay;
+        }
+        // Every client of a netplay game has its own leaderboard: they all
+        // skip the initials, to go on with the same screens
+        match self.rank {
+            Some(rank) if !controls::netplay() => {
+                Transition::Replace(Box::new(EnterInitials::new(rank)))
+            }
+            _ => Transition::Replace(Box::new(MainGame)),
+        }
+    }
+
+    fn draw(&self, game: &Game) {
+        if game.coop.is_some() {
+            game.draw_coop_results();
+        } else {
+            // A new high score blinks
+            game.draw_hud(!self.new_high_score || (game.timers.frame_count / 2) % 10 < 5);
+        }
+        screen::game_over(game.timers.frame_count, self.rank);
+    }
+}
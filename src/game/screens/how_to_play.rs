@@ -0,0 +1,31 @@
+use super::{confirm_pressed, MainGame, Screen, ScreenName, Transition, VersusGame};
+use crate::{
+    game::{
+        controls::{self, ControlEvent},
+        save::GameMode,
+        Game,
+    },
+    graphics::screen,
+};
+
+pub struct HowToPlay;
+
+impl Screen for HowToPlay {
+    fn name(&self) -> ScreenName {
+        ScreenName::HowToPlay
+    }
+
+    fn update(&mut self, game: &mut Game, events: &[ControlEvent]) -> Transition {
+        if !confirm_pressed(events) {
+            Transition::Stay
+        } else if game.mode() == GameMode::Versus {
+            Transition::Replace(Box::<VersusGame>::default())
+        } else {
+            Transition::Replace(Box::new(MainGame))
+        }
+    }
+
+    fn draw(&self, game: &Game) {
+        screen::how_to_play(game.timers.frame_count, controls::local_player());
+    }
+}
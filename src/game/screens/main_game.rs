@@ -0,0 +1,115 @@
+use super::{GameOver, Pause, Screen, ScreenName, Transition};
+use crate::{
+    common::types::{scalar, Coord, Scalar},
+    entities::player::PlayerN,
+    game::{controls::ControlEvent, Game},
+    sound::effects,
+};
+
+/// A run, from the first frame to the one the players run out of lives.
+pub struct MainGame;
+
+impl MainGame {
+    /// Players steer, dash, change color and drop the bomb they carry, unless
+    /// the world is stopped by a death.
+    pub fn process_inputs(game: &mut Game, events: &[ControlEvent]) {
+        if game.entities.killer.is_some() {
+            return;
+        }
+        for event in events {
+            let player_n = match event {
+                ControlEvent::Left(player_n)
+                | ControlEvent::Down(player_n)
+                | ControlEvent::Up(player_n)
+                | ControlEvent::Right(player_n)
+                | ControlEvent::Btn1(player_n)
+                | ControlEvent::Btn2(player_n)
+                | ControlEvent::Dash(player_n) => *player_n,
+                ControlEvent::MouseRightClick | ControlEvent::MouseLeftHold(_) => PlayerN::P1,
+                _ => continue,
+            };
+            if let ControlEvent::Btn2(_) = event {
+                if game.entities.drop_bomb(player_n) {
+                    effects::drop_bomb(game.environment.sfx_volume);
+                }
+                continue;
+            }
+            let Some(player) = game.entities.players[player_n as usize].as_mut() else {
+                continue;
+            };
+            match event {
+                ControlEvent::Left(_) => player.left(),
+                ControlEvent::Down(_) => player.down(),
+                ControlEvent::Up(_) => player.up(),
+                ControlEvent::Right(_) => player.right(),
+                ControlEvent::Btn1(_) | ControlEvent::MouseRightClick => player.toggle_color(),
+                ControlEvent::Dash(_) if player.dash() => {
+                    effects::dash(game.environment.sfx_volume)
+                }
+                ControlEvent::MouseLeftHold((mouse_x, mouse_y)) => {
+                    let new_d_x = Scalar::from(*mouse_x)
+                        - player.entity.position.x
+                        - player.entity.size / scalar(2.0);
+                    let new_d_y = Scalar::from(*mouse_y)
+                        - player.entity.position.y
+                        - player.entity.size / scalar(2.0);
+                    if new_d_x.abs() > scalar(1.0) || new_d_y.abs() > scalar(1.0) {
+                        player.entity.direction = Coord {
+                            x: new_d_x,
+                            y: new_d_y,
+                        };
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn play_frame(game: &mut Game, events: &[ControlEvent]) -> Transition {
+        Self::process_inputs(game, events);
+
+        // Stop-the-world death event
+        if game.entities.killer.is_some() {
+            game.death_tick();
+            return Transition::Stay;
+        }
+
+        // End-game. Players ran out of lives, save the run and show game-over
+        if game.run_over() {
+            let new_high_score = game.run_score().0 > game.save.leaderboard.high_score();
+            let rank = game.save_run();
+            return Transition::Replace(Box::new(GameOver::new(rank, new_high_score)));
+        }
+
+        game.step_world();
+        Transition::Stay
+    }
+}
+
+impl Screen for MainGame {
+    fn name(&self) -> ScreenName {
+        ScreenName::MainGame
+    }
+
+    fn enter(&mut self, game: &mut Game) {
+        game.restart();
+    }
+
+    /// The frame is played before pausing, so no frame is skipped or played
+    /// twice around a pause.
+    fn update(&mut self, game: &mut Game, events: &[ControlEvent]) -> Transition {
+        let transition = Self::play_frame(game, events);
+        let pause = events.iter().any(|e| matches!(e, ControlEvent::Pause));
+        match transition {
+            Transition::Stay if pause => {
+                Transition::Push(Box::new(Pause::new(|| Box::new(MainGame))))
+            }
+            transition => transition,
+        }
+    }
+
+    fn draw(&self, game: &Game) {
+        game.draw_hud(true);
+        game.draw_world();
+    }
+}
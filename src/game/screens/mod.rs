@@ -0,0 +1,262 @@
+// Every screen of the game is a state on a stack. Only the state on top gets
+// the input and is updated, but all of them are drawn, bottom first, so a
+// state can be pushed over another as an overlay (a pause menu over the game).
+// States move the game along by returning a `Transition` from `update`.
+mod arena_select;
+mod enter_initials;
+mod game_over;
+mod how_to_play;
+mod main_game;
+mod pause;
+mod settings;
+mod title;
+mod versus_game;
+mod versus_results;
+
+pub use arena_select::ArenaSelect;
+pub use enter_initials::EnterInitials;
+pub use game_over::GameOver;
+pub use how_to_play::HowToPlay;
+pub use main_game::MainGame;
+pub use pause::Pause;
+pub use settings::Settings;
+pub use title::Title;
+pub use versus_game::VersusGame;
+pub use versus_results::VersusResults;
+
+use super::{
+    controls::{self, ControlEvent},
+    Game,
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ScreenName {
+    Title,
+    HowToPlay,
+    MainGame,
+    GameOver,
+    EnterInitials,
+    Pause,
+    Settings,
+    ArenaSelect,
+    Versus,
+    VersusResults,
+}
+
+/// What the stack should do after a state was updated.
+pub enum Transition {
+    Stay,
+    /// Put a state over the current one, which is kept as it is
+    Push(Box<dyn Screen>),
+    /// Remove the current state, going back to the one below
+    Pop,
+    /// Swap the current state for another
+    Replace(Box<dyn Screen>),
+    /// Remove every state, top first, and start over from another
+    Clear(Box<dyn Screen>),
+}
+
+pub trait Screen: Send {
+    fn name(&self) -> ScreenName;
+
+    /// Called when the state is added to the stack.
+    fn enter(&mut self, _game: &mut Game) {}
+
+    /// Called when the state is removed from the stack.
+    fn exit(&mut self, _game: &mut Game) {}
+
+    /// Called every frame the state is on top of the stack, with the input
+    /// events of the frame.
+    fn update(&mut self, game: &mut Game, events: &[ControlEvent]) -> Transition;
+
+    /// Called every frame the state is on the stack, after the update.
+    fn draw(&self, game: &Game);
+}
+
+/// X button (of any player) or left click: the "Press X to..." of most screens.
+pub fn confirm_pressed(events: &[ControlEvent]) -> bool {
+    events
+        .iter()
+        .any(|event| matches!(event, ControlEvent::Btn1(_) | ControlEvent::MouseLeftClick))
+}
+
+/// Same as `confirm_pressed`, but only for the players in charge (see
+/// `controls::in_charge`).
+pub fn confirm_pressed_in_charge(events: &[ControlEvent]) -> bool {
+    events.iter().any(|event| match event {
+        ControlEvent::Btn1(player_n) => controls::in_charge(*player_n),
+        ControlEvent::MouseLeftClick => true,
+        _ => false,
+    })
+}
+
+#[derive(Default)]
+pub struct ScreenStack {
+    screens: Vec<Box<dyn Screen>>,
+}
+
+impl ScreenStack {
+    /// A stack with `first` on it. `first` isn't entered, there's no game to
+    /// enter it with yet.
+    pub fn new(first: Box<dyn Screen>) -> Self {
+        Self {
+            screens: vec![first],
+        }
+    }
+
+    /// Name of the state on top.
+    pub fn top(&self) -> Option<ScreenName> {
+        self.screens.last().map(|screen| screen.name())
+    }
+
+    pub fn contains(&self, name: ScreenName) -> bool {
+        self.screens.iter().any(|screen| screen.name() == name)
+    }
+
+    /// Updates the state on top and applies its transition. A state that's
+    /// entered is updated right away, in the same frame, but without the input
+    /// events that were already used by the previous one. A state uncovered by
+    /// a pop is only updated from the next frame, like any other.
+    pub fn update(&mut self, game: &mut Game, events: &[ControlEvent]) {
+        let Some(screen) = self.screens.last_mut() else {
+            return;
+        };
+        let transition = screen.update(game, events);
+        if self.apply(game, transition) {
+            if let Some(screen) = self.screens.last_mut() {
+                let transition = screen.update(game, &[]);
+                self.apply(game, transition);
+            }
+        }
+    }
+
+    pub fn draw(&self, game: &Game) {
+        for screen in &self.screens {
+            screen.draw(game);
+        }
+    }
+
+    /// Returns whether a new state was entered.
+    fn apply(&mut self, game: &mut Game, transition: Transition) -> bool {
+        match transition {
+            Transition::Stay => return false,
+            Transition::Push(mut screen) => {
+                screen.enter(game);
+                self.screens.push(screen);
+            }
+            Transition::Pop => {
+                if let Some(mut screen) = self.screens.pop() {
+                    screen.exit(game);
+                }
+                return false;
+            }
+            Transition::Replace(mut screen) => {
+                if let Some(mut old) = self.screens.pop() {
+                    old.exit(game);
+                }
+                screen.enter(game);
+                self.screens.push(screen);
+            }
+            Transition::Clear(mut screen) => {
+                while let Some(mut old) = self.screens.pop() {
+                    old.exit(game);
+                }
+                screen.enter(game);
+                self.screens.push(screen);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm4::host;
+    use std::sync::Mutex;
+
+    // Everything that happened to the test screens, in order
+    static LOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn log(entry: String) {
+        LOG.lock().unwrap().push(entry);
+    }
+
+    /// Pushes an overlay on X and pops itself on Z when it is one.
+    struct Test(ScreenName, bool);
+
+    impl Screen for Test {
+        fn name(&self) -> ScreenName {
+            self.0
+        }
+
+        fn enter(&mut self, _game: &mut Game) {
+            log(format!("enter {:?}", self.0));
+        }
+
+        fn exit(&mut self, _game: &mut Game) {
+            log(format!("exit {:?}", self.0));
+        }
+
+        fn update(&mut self, _game: &mut Game, events: &[ControlEvent]) -> Transition {
+            log(format!("update {:?} {}", self.0, events.len()));
+            match events.first() {
+                Some(ControlEvent::Btn1(_)) => {
+                    Transition::Push(Box::new(Test(ScreenName::GameOver, true)))
+                }
+                Some(ControlEvent::Btn2(_)) if self.1 => Transition::Pop,
+                Some(ControlEvent::MouseLeftClick) => {
+                    Transition::Replace(Box::new(Test(ScreenName::HowToPlay, false)))
+                }
+                _ => Transition::Stay,
+            }
+        }
+
+        fn draw(&self, _game: &Game) {
+            log(format!("draw {:?}", self.0));
+        }
+    }
+
+    #[test]
+    fn push_pop_and_replace() {
+        use crate::entities::player::PlayerN;
+        let _session = host::lock();
+        let mut game = Game::new();
+        let mut stack = ScreenStack::new(Box::new(Test(ScreenName::Title, false)));
+        let mut frame = |stack: &mut ScreenStack, events: &[ControlEvent]| {
+            stack.update(&mut game, events);
+            stack.draw(&game);
+            LOG.lock().unwrap().drain(..).collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            frame(&mut stack, &[ControlEvent::Btn1(PlayerN::P1)]),
+            [
+                "update Title 1",
+                "enter GameOver",
+                "update GameOver 0",
+                "draw Title",
+                "draw GameOver"
+            ]
+        );
+        assert_eq!(stack.top(), Some(ScreenName::GameOver));
+        assert!(stack.contains(ScreenName::Title));
+
+        assert_eq!(
+            frame(&mut stack, &[ControlEvent::Btn2(PlayerN::P1)]),
+            ["update GameOver 1", "exit GameOver", "draw Title"]
+        );
+        assert_eq!(
+            frame(&mut stack, &[ControlEvent::MouseLeftClick]),
+            [
+                "update Title 1",
+                "exit Title",
+                "enter HowToPlay",
+                "update HowToPlay 0",
+                "draw HowToPlay"
+            ]
+        );
+        assert_eq!(stack.top(), Some(ScreenName::HowToPlay));
+        assert!(!stack.contains(ScreenName::Title));
+    }
+}
@@ -0,0 +1,90 @@
+use super::{
+    confirm_pressed, confirm_pressed_in_charge, Screen, ScreenName, Settings, Title, Transition,
+};
+use crate::{
+    game::{
+        controls::{ControlEvent, Repeater},
+        Game,
+    },
+    graphics::screen,
+};
+
+const OPTIONS: [&str; 5] = ["Resume", "Restart", "Palette", "Settings", "Quit to title"];
+const RESUME: usize = 0;
+const RESTART: usize = 1;
+const PALETTE: usize = 2;
+const SETTINGS: usize = 3;
+const QUIT: usize = 4;
+
+/// Menu over the game, which is frozen (music aside) until it's resumed.
+pub struct Pause {
+    selected: usize,
+    repeat: Repeater,
+    // Frames since the game was paused, for blinking
+    tick: usize,
+    /// The screen "Restart" starts over from
+    restart: fn() -> Box<dyn Screen>,
+}
+
+impl Pause {
+    pub fn new(restart: fn() -> Box<dyn Screen>) -> Self {
+        Self {
+            selected: 0,
+            repeat: Repeater::default(),
+            tick: 0,
+            restart,
+        }
+    }
+}
+
+impl Screen for Pause {
+    fn name(&self) -> ScreenName {
+        ScreenName::Pause
+    }
+
+    fn enter(&mut self, game: &mut Game) {
+        game.timers.paused = true;
+        game.environment.quiet = true;
+    }
+
+    fn exit(&mut self, game: &mut Game) {
+        game.timers.paused = false;
+        game.environment.quiet = false;
+    }
+
+    fn update(&mut self, game: &mut Game, events: &[ControlEvent]) -> Transition {
+        self.tick += 1;
+        let mut steer = 0;
+        for event in events {
+            match event {
+                ControlEvent::Pause => return Transition::Pop,
+                ControlEvent::Up(_) => steer = -1,
+                ControlEvent::Down(_) => steer = 1,
+                _ => (),
+            }
+        }
+        if self.repeat.fire(steer != 0) {
+            self.selected =
+                (self.selected as i32 + steer).rem_euclid(OPTIONS.len() as i32) as usize;
+        }
+
+        if !confirm_pressed(events) {
+            return Transition::Stay;
+        }
+        match self.selected {
+            RESUME => Transition::Pop,
+            RESTART => Transition::Clear((self.restart)()),
+            PALETTE if confirm_pressed_in_charge(events) => {
+                game.next_palette();
+                Transition::Stay
+            }
+            SETTINGS => Transition::Push(Box::<Settings>::default()),
+            QUIT => Transition::Clear(Box::new(Title)),
+            _ => Transition::Stay,
+        }
+    }
+
+    fn draw(&self, _game: &Game) {
+        screen::pause_menu(self.tick, &OPTIONS, self.selected);
+    }
+}
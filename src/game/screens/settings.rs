@@ -0,0 +1,162 @@
+use super::{
+    confirm_pressed, confirm_pressed_in_charge, ArenaSelect, Screen, ScreenName, Transition,
+};
+use crate::{
+    entities::layout::{LAYOUTS, ROTATING},
+    game::{
+        controls::{self, ControlEvent, Repeater},
+        save::{GameMode, MAX_VOLUME},
+        Game,
+    },
+    graphics::{palette::PALETTES, screen},
+    sound::effects,
+};
+
+const MUSIC: usize = 0;
+const SFX: usize = 1;
+const PALETTE: usize = 2;
+const MOUSE: usize = 3;
+const OVERLAY: usize = 4;
+const MODE: usize = 5;
+const ARENA: usize = 6;
+const BACK: usize = 7;
+const LABELS: [&str; 8] = [
+    "Music",
+    "Sound FX",
+    "Palette",
+    "Mouse",
+    "Pad overlay",
+    "Mode",
+    "Arena",
+    "Back",
+];
+
+/// The arena setting after `arena`, `step` values forward (or back), wrapping
+/// around.
+pub fn arena_step(arena: u8, step: i32) -> u8 {
+    (arena as i32 + step).rem_euclid(ROTATING as i32 + 1) as u8
+}
+
+pub fn arena_name(arena: u8) -> &'static str {
+    match LAYOUTS.get(arena as usize) {
+        Some(layout) => layout.name,
+        None => "Rotating",
+    }
+}
+
+/// Settings menu, over the title or the pause menu. Up/down select a setting,
+/// left/right (or X) change it, X on the arena opens the arena select screen.
+/// Changes apply right away and are saved when leaving the menu.
+#[derive(Default)]
+pub struct Settings {
+    selected: usize,
+    select_repeat: Repeater,
+    change_repeat: Repeater,
+    // Frames since the menu was opened, for blinking
+    tick: usize,
+}
+
+impl Settings {
+    /// Moves the selected setting `step` values forward (or back). Volumes
+    /// stop at their ends when steered, X cycles through every value.
+    fn change(&self, game: &mut Game, step: i32, cycle: bool) {
+        let settings = &mut game.save.settings;
+        let volume = |volume: u8| {
+            let levels = MAX_VOLUME as i32 + 1;
+            let volume = volume as i32 + step;
+            match cycle {
+                true => volume.rem_euclid(levels) as u8,
+                false => volume.clamp(0, levels - 1) as u8,
+            }
+        };
+        match self.selected {
+            MUSIC => settings.music_volume = volume(settings.music_volume),
+            SFX => settings.sfx_volume = volume(settings.sfx_volume),
+            PALETTE => {
+                settings.palette =
+                    (settings.palette as i32 + step).rem_euclid(PALETTES.len() as i32) as u8
+            }
+            MOUSE => settings.mouse_control = !settings.mouse_control,
+            OVERLAY => settings.gamepad_overlay = !settings.gamepad_overlay,
+            MODE => settings.mode = settings.mode.step(step),
+            ARENA => settings.arena = arena_step(settings.arena, step),
+            _ => return,
+        }
+        game.apply_settings();
+        if self.selected == SFX {
+            // A sample of the new volume
+            effects::color1_switch(game.environment.sfx_volume);
+        }
+    }
+}
+
+impl Screen for Settings {
+    fn name(&self) -> ScreenName {
+        ScreenName::Settings
+    }
+
+    fn exit(&mut self, game: &mut Game) {
+        game.store_save();
+    }
+
+    fn update(&mut self, game: &mut Game, events: &[ControlEvent]) -> Transition {
+        self.tick += 1;
+        let (mut dx, mut dy) = (0, 0);
+        // Only the host changes the palette in netplay
+        let changes = |player_n| self.selected != PALETTE || controls::in_charge(player_n);
+        for event in events {
+            match event {
+                ControlEvent::Left(player_n) if changes(*player_n) => dx = -1,
+                ControlEvent::Right(player_n) if changes(*player_n) => dx = 1,
+                ControlEvent::Up(_) => dy = -1,
+                ControlEvent::Down(_) => dy = 1,
+                _ => (),
+            }
+        }
+        if self.select_repeat.fire(dy != 0) {
+            self.selected = (self.selected as i32 + dy).rem_euclid(LABELS.len() as i32) as usize;
+        }
+        if self.change_repeat.fire(dx != 0) {
+            self.change(game, dx, false);
+        }
+
+        if !confirm_pressed(events) {
+            return Transition::Stay;
+        }
+        match self.selected {
+            BACK => return Transition::Pop,
+            PALETTE if !confirm_pressed_in_charge(events) => return Transition::Stay,
+            ARENA => return Transition::Push(Box::new(ArenaSelect::new(game.save.settings.arena))),
+            _ => (),
+        }
+        self.change(game, 1, true);
+        Transition::Stay
+    }
+
+    fn draw(&self, game: &Game) {
+        let settings = &game.save.settings;
+        let on_off = |on: bool| if on { "On" } else { "Off" }.to_string();
+        let values = [
+            settings.music_volume.to_string(),
+            settings.sfx_volume.to_string(),
+            (settings.palette + 1).to_string(),
+            on_off(settings.mouse_control),
+            on_off(settings.gamepad_overlay),
+            match settings.mode {
+                GameMode::Shared => "Team lives",
+                GameMode::Individual => "Own lives",
+                GameMode::Versus => "Versus",
+                GameMode::Revive => "Revive",
+            }
+            .to_string(),
+            arena_name(settings.arena).to_string(),
+            String::new(),
+        ];
+        let rows: Vec<(&str, &str)> = LABELS
+            .iter()
+            .zip(values.iter())
+            .map(|(label, value)| (*label, value.as_str()))
+            .collect();
+        screen::settings_menu(self.tick, &rows, self.selected);
+    }
+}
@@ -0,0 +1,33 @@
+use super::{confirm_pressed, HowToPlay, Screen, ScreenName, Settings, Transition};
+use crate::{
+    game::{controls::ControlEvent, Game},
+    graphics::screen,
+    sound::music::INTRO_SONG,
+};
+
+pub struct Title;
+
+impl Screen for Title {
+    fn name(&self) -> ScreenName {
+        ScreenName::Title
+    }
+
+    fn enter(&mut self, game: &mut Game) {
+        game.environment.song_nr = INTRO_SONG;
+        game.timers.song_tick = 0;
+    }
+
+    fn update(&mut self, _game: &mut Game, events: &[ControlEvent]) -> Transition {
+        if confirm_pressed(events) {
+            Transition::Replace(Box::new(HowToPlay))
+        } else if events.iter().any(|e| matches!(e, ControlEvent::Down(_))) {
+            Transition::Push(Box::<Settings>::default())
+        } else {
+            Transition::Stay
+        }
+    }
+
+    fn draw(&self, game: &Game) {
+        screen::title(game.timers.frame_count);
+    }
+}
@@ -0,0 +1,131 @@
+use super::{MainGame, Pause, Screen, ScreenName, Transition, VersusResults};
+use crate::{
+    entities::player::PlayerN,
+    game::{controls::ControlEvent, Game},
+    graphics::screen,
+};
+
+/// Frames of the countdown before a round
+const COUNTDOWN: usize = 3 * 60;
+/// Frames the outcome of a round is shown for
+const ROUND_OVER: usize = 2 * 60;
+
+#[derive(Clone, Copy)]
+enum Phase {
+    /// Players join, P1 starts the match once there are at least two
+    Lobby,
+    /// Frames left before the round starts
+    Countdown(usize),
+    Playing,
+    /// Winner of the round (None for a draw), and frames left showing it
+    RoundOver(Option<PlayerN>, usize),
+}
+
+/// A versus match: enemies absorbed are sent at the other players, the last
+/// disk standing wins the round.
+pub struct VersusGame {
+    phase: Phase,
+    // Frames since the match started, for blinking
+    tick: usize,
+}
+
+impl Default for VersusGame {
+    fn default() -> Self {
+        Self {
+            phase: Phase::Lobby,
+            tick: 0,
+        }
+    }
+}
+
+impl VersusGame {
+    fn next_phase(&mut self, game: &mut Game, events: &[ControlEvent]) -> Transition {
+        const MATCH: &str = "a match is on while VersusGame is shown";
+        self.phase = match self.phase {
+            Phase::Lobby => {
+                // Players roam the empty field while waiting
+                MainGame::process_inputs(game, events);
+                game.entities.update();
+                let start = events.iter().any(|event| {
+                    matches!(
+                        event,
+                        ControlEvent::Btn1(PlayerN::P1) | ControlEvent::MouseLeftClick
+                    )
+                });
+                let versus = game.versus.as_mut().expect(MATCH);
+                if !start || versus.entrants() < 2 {
+                    return Transition::Stay;
+                }
+                versus.start_round(&mut game.entities);
+                Phase::Countdown(COUNTDOWN)
+            }
+            Phase::Countdown(0) => Phase::Playing,
+            Phase::Countdown(frames) => Phase::Countdown(frames - 1),
+            Phase::Playing => {
+                MainGame::process_inputs(game, events);
+                // Stop-the-world death event
+                if game.entities.killer.is_some() {
+                    game.death_tick();
+                    return Transition::Stay;
+                }
+                let versus = game.versus.as_mut().expect(MATCH);
+                match versus.end_round(&mut game.entities) {
+                    Some(winner) => Phase::RoundOver(winner, ROUND_OVER),
+                    None => {
+                        game.step_world();
+                        Phase::Playing
+                    }
+                }
+            }
+            Phase::RoundOver(winner, frames) if frames > 0 => Phase::RoundOver(winner, frames - 1),
+            Phase::RoundOver(..) => {
+                let versus = game.versus.as_mut().expect(MATCH);
+                if versus.champion().is_some() {
+                    return Transition::Replace(Box::new(VersusResults));
+                }
+                versus.start_round(&mut game.entities);
+                Phase::Countdown(COUNTDOWN)
+            }
+        };
+        Transition::Stay
+    }
+}
+
+impl Screen for VersusGame {
+    fn name(&self) -> ScreenName {
+        ScreenName::Versus
+    }
+
+    fn enter(&mut self, game: &mut Game) {
+        game.restart();
+        game.start_versus();
+    }
+
+    fn update(&mut self, game: &mut Game, events: &[ControlEvent]) -> Transition {
+        self.tick += 1;
+        if events.iter().any(|e| matches!(e, ControlEvent::Pause)) {
+            return Transition::Push(Box::new(Pause::new(|| Box::<VersusGame>::default())));
+        }
+        self.next_phase(game, events)
+    }
+
+    fn draw(&self, game: &Game) {
+        game.draw_world();
+        game.draw_versus_hud();
+        let Some(versus) = &game.versus else {
+            return;
+        };
+        match self.phase {
+            Phase::Lobby => screen::versus_lobby(self.tick, &versus.entrants),
+            Phase::Countdown(frames) => screen::banner(
+                &format!("ROUND {}", versus.round),
+                &(frames / 60 + 1).to_string(),
+            ),
+            Phase::Playing => (),
+            Phase::RoundOver(Some(winner), _) => {
+                screen::banner(&format!("P{} WINS", winner as u8 + 1), "the round")
+            }
+            Phase::RoundOver(None, _) => screen::banner("DRAW", "nobody is left"),
+        }
+    }
+}
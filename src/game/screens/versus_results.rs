@@ -0,0 +1,31 @@
+use super::{confirm_pressed, Screen, ScreenName, Transition, VersusGame};
+use crate::{
+    game::{controls::ControlEvent, Game},
+    sound::music::GAME_OVER_SONG,
+};
+
+/// End of a versus match: round wins of every player.
+pub struct VersusResults;
+
+impl Screen for VersusResults {
+    fn name(&self) -> ScreenName {
+        ScreenName::VersusResults
+    }
+
+    fn enter(&mut self, game: &mut Game) {
+        game.environment.song_nr = GAME_OVER_SONG;
+        game.timers.song_tick = 0;
+    }
+
+    fn update(&mut self, _game: &mut Game, events: &[ControlEvent]) -> Transition {
+        if confirm_pressed(events) {
+            Transition::Replace(Box::<VersusGame>::default())
+        } else {
+            Transition::Stay
+        }
+    }
+
+    fn draw(&self, game: &Game) {
+        game.draw_versus_results();
+    }
+}
@@ -15,6 +15,8 @@ pub struct Timers {
     pub death_countdown: usize,
     pub respite: usize, // frames without enemies
     pub song_tick: usize,
+    /// While the game is paused only the song keeps going
+    pub paused: bool,
 }
 impl Timers {
     pub fn new() -> Self {
@@ -23,13 +25,17 @@ impl Timers {
             death_countdown: DEATH_COUNTDOWN_DURATION,
             respite: RESPITE_DURATION,
             song_tick: 0,
+            paused: false,
         }
     }
 
     pub fn tick(&mut self) {
+        self.song_tick = self.song_tick.wrapping_add(1);
+        if self.paused {
+            return;
+        }
         self.frame_count = self.frame_count.wrapping_add(1);
         self.respite = self.respite.saturating_sub(1);
-        self.song_tick = self.song_tick.wrapping_add(1);
     }
 }
 
@@ -49,5 +55,11 @@ mod tests {
         assert_eq!(timers.frame_count, 2);
         assert_eq!(timers.respite, RESPITE_DURATION - 2);
         assert_eq!(timers.song_tick, 2);
+
+        timers.paused = true;
+        timers.tick();
+        assert_eq!(timers.frame_count, 2);
+        assert_eq!(timers.respite, RESPITE_DURATION - 2);
+        assert_eq!(timers.song_tick, 3);
     }
 }
@@ -0,0 +1,135 @@
+use crate::{
+    common::types::Coord,
+    entities::{
+        manager::EntityManager as Entities,
+        player::{Player, PlayerN},
+    },
+};
+
+/// Round wins needed to win the match
+pub const WINS_NEEDED: u8 = 3;
+
+const PLAYERS: [PlayerN; 4] = [PlayerN::P1, PlayerN::P2, PlayerN::P3, PlayerN::P4];
+
+/// Where players start a round, one per corner
+const START_POSITIONS: [(f64, f64); 4] =
+    [(32.0, 32.0), (121.0, 32.0), (32.0, 121.0), (121.0, 121.0)];
+
+/// A versus match: rounds with a life each, the last disk standing wins the
+/// round. Players join before the first round, and play every round after.
+#[derive(Default)]
+pub struct Versus {
+    pub entrants: [bool; 4],
+    pub wins: [u8; 4],
+    /// Rounds started, 0 while players are still joining
+    pub round: u8,
+}
+
+impl Versus {
+    /// Adds `player_n` to the match, if it hasn't started yet.
+    pub fn join(&mut self, entities: &mut Entities, player_n: PlayerN) -> bool {
+        if self.round > 0 {
+            return false;
+        }
+        self.entrants[player_n as usize] = true;
+        entities.players[player_n as usize] = Some(Player::new(player_n));
+        true
+    }
+
+    /// Takes `player_n` out of the match: they forfeit it, and the round
+    /// goes on without them.
+    pub fn leave(&mut self, entities: &mut Entities, player_n: PlayerN) {
+        self.entrants[player_n as usize] = false;
+        entities.remove_player(player_n);
+    }
+
+    pub fn entrants(&self) -> usize {
+        self.entrants.iter().filter(|&&entrant| entrant).count()
+    }
+
+    /// Clears the field and puts every entrant back in their corner, with one
+    /// life.
+    pub fn start_round(&mut self, entities: &mut Entities) {
+        self.round = self.round.saturating_add(1);
+        entities.enemies.clear();
+        entities.bombs.clear();
+        entities.killer = None;
+        entities.victim = None;
+        for (n, player_n) in PLAYERS.into_iter().enumerate() {
+            entities.players[n] = self.entrants[n].then(|| {
+                let mut player = Player::new(player_n);
+                let (x, y) = START_POSITIONS[n];
+                player.entity.position = Coord::new(x, y);
+                player
+            });
+        }
+    }
+
+    /// Takes the players without lives out of the round. Once one player or
+    /// none is left the round is over: returns its winner, None for a draw,
+    /// and counts the win.
+    pub fn end_round(&mut self, entities: &mut Entities) -> Option<Option<PlayerN>> {
+        for player in entities.players.iter_mut() {
+            if player.as_ref().is_some_and(|p| p.entity.life == 0) {
+                *player = None;
+            }
+        }
+        let mut standing = entities.players.iter().flatten();
+        let winner = standing.next().map(|player| player.player_number);
+        if standing.next().is_some() {
+            return None;
+        }
+        if let Some(winner) = winner {
+            self.wins[winner as usize] = self.wins[winner as usize].saturating_add(1);
+        }
+        Some(winner)
+    }
+
+    /// The player who won the match, if any did yet.
+    pub fn champion(&self) -> Option<PlayerN> {
+        let n = self.wins.iter().position(|&wins| wins >= WINS_NEEDED)?;
+        Some(PLAYERS[n])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_disk_standing() {
+        let mut entities = Entities::new();
+        let mut versus = Versus::default();
+        versus.join(&mut entities, PlayerN::P1);
+        versus.join(&mut entities, PlayerN::P3);
+        versus.join(&mut entities, PlayerN::P4);
+        versus.leave(&mut entities, PlayerN::P4);
+        assert!(entities.players[PlayerN::P4 as usize].is_none());
+        assert_eq!(versus.entrants(), 2);
+
+        for round in 1..=WINS_NEEDED {
+            assert_eq!(versus.champion(), None);
+            versus.start_round(&mut entities);
+            assert_eq!(versus.round, round);
+            assert!(!versus.join(&mut entities, PlayerN::P2));
+            assert_eq!(versus.end_round(&mut entities), None);
+            entities.players[PlayerN::P1 as usize]
+                .as_mut()
+                .expect("P1 is in the round")
+                .entity
+                .life = 0;
+            assert_eq!(versus.end_round(&mut entities), Some(Some(PlayerN::P3)));
+        }
+        assert_eq!(versus.wins, [0, 0, WINS_NEEDED, 0]);
+        assert_eq!(versus.champion(), Some(PlayerN::P3));
+
+        // Everybody is back for the next round, a round without survivors is a draw
+        versus.start_round(&mut entities);
+        assert!(entities.players[PlayerN::P1 as usize].is_some());
+        assert!(entities.players[PlayerN::P2 as usize].is_none());
+        for player in entities.players.iter_mut().flatten() {
+            player.entity.life = 0;
+        }
+        assert_eq!(versus.end_round(&mut entities), Some(None));
+    }
+}
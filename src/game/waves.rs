@@ -0,0 +1,476 @@
+use crate::{
+    common::{
+        calibrations::DIFFICULTY_LEVELS,
+        types::{scalar, Coord, Scalar},
+    },
+    entities::{behavior::Behavior, boss::Boss, enemy::Enemy},
+    graphics::palette::{DRAW_COLOR_A, DRAW_COLOR_B},
+};
+
+/// Directions of the formations, in sixty-fourths of a turn
+const TURN: usize = 64;
+/// Sine over a quarter turn, times 1024
+const QUARTER_SINE: [u16; TURN / 4 + 1] = [
+    0, 100, 200, 297, 392, 483, 569, 650, 724, 792, 851, 903, 946, 980, 1004, 1019, 1024,
+];
+
+/// Bursts of enemies on top of the steady spawns, by difficulty: each wave
+/// starts `after` frames after the previous one, the last one is followed by
+/// the first again. None at the start of the game.
+pub const SEQUENCES: [&[Cue]; DIFFICULTY_LEVELS] = [
+    &[],
+    &[Cue::after(900, Wave::ring(8, 70))],
+    &[
+        Cue::after(720, Wave::line((0, 0), (159, 0), 8).every(3)),
+        Cue::after(720, Wave::ring(12, 70)),
+    ],
+    &[
+        Cue::after(600, Wave::ring(16, 70)),
+        Cue::after(
+            600,
+            Wave::spiral(12, 80, 1).every(6).colored(WaveColor::Other),
+        ),
+    ],
+    &[
+        Cue::after(600, Wave::ring(12, 60).of(Behavior::Orbiter)),
+        Cue::after(
+            600,
+            Wave::line((0, 0), (0, 159), 10)
+                .every(2)
+                .colored(WaveColor::Alternating),
+        ),
+        Cue::after(480, Wave::ring(16, 70)),
+    ],
+    &[
+        Cue::after(
+            540,
+            Wave::spiral(16, 80, 2)
+                .every(4)
+                .colored(WaveColor::Alternating),
+        ),
+        Cue::after(540, Wave::ring(8, 60).of(Behavior::Dasher(0))),
+        Cue::after(540, Wave::line((0, 159), (159, 159), 12).every(2)),
+    ],
+    &[
+        Cue::after(480, Wave::ring(16, 70).colored(WaveColor::Alternating)),
+        Cue::after(
+            480,
+            Wave::line((0, 80), (159, 80), 8).of(Behavior::Wanderer),
+        ),
+        Cue::after(
+            480,
+            Wave::ring(4, 50).of(Behavior::Tank(Behavior::TANK_ABSORBS)),
+        ),
+    ],
+    &[
+        Cue::after(420, Wave::spiral(20, 80, 2).every(3)),
+        Cue::after(420, Wave::ring(12, 60).of(Behavior::Splitter)),
+        Cue::after(420, Wave::ring(16, 70).colored(WaveColor::Other)),
+    ],
+    &[
+        Cue::after(360, Wave::ring(16, 70).colored(WaveColor::Alternating)),
+        Cue::after(
+            360,
+            Wave::line((159, 0), (159, 159), 12).of(Behavior::Dasher(0)),
+        ),
+        Cue::after(
+            360,
+            Wave::spiral(24, 80, 3)
+                .every(2)
+                .colored(WaveColor::Alternating),
+        ),
+    ],
+    &[
+        Cue::after(300, Wave::ring(20, 70)),
+        Cue::after(
+            300,
+            Wave::ring(12, 50)
+                .of(Behavior::Orbiter)
+                .colored(WaveColor::Other),
+        ),
+        Cue::after(
+            300,
+            Wave::spiral(24, 80, 3).every(2).of(Behavior::Dasher(0)),
+        ),
+        Cue::after(
+            300,
+            Wave::ring(6, 60).of(Behavior::Tank(Behavior::TANK_ABSORBS)),
+        ),
+    ],
+];
+
+/// Attacks of the bosses, by phase: a wave bursting out of them every `after`
+/// frames, half of it in the color that hurts them.
+pub const BOSS_ATTACKS: [Cue; Boss::PHASES] = [
+    Cue::after(180, Wave::ring(6, 16).colored(WaveColor::Alternating)),
+    Cue::after(
+        150,
+        Wave::ring(12, 16).every(3).colored(WaveColor::Alternating),
+    ),
+    Cue::after(
+        120,
+        Wave::ring(8, 16)
+            .of(Behavior::Dasher(0))
+            .colored(WaveColor::Alternating),
+    ),
+];
+
+/// Where the enemies of a wave show up.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Formation {
+    /// Evenly around the player, `radius` pixels away
+    Ring { radius: u8 },
+    /// Evenly from a point of the screen to another
+    Line { from: (u8, u8), to: (u8, u8) },
+    /// Around the player, winding `turns` times from `radius` pixels away
+    /// towards them
+    Spiral { radius: u8, turns: u8 },
+}
+
+/// Color of the enemies of a wave, from the one enemies currently spawn in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WaveColor {
+    Current,
+    Other,
+    /// One of each, in turn
+    Alternating,
+}
+
+/// A burst of `count` enemies of archetype `behavior`, in formation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Wave {
+    pub formation: Formation,
+    pub count: u8,
+    pub color: WaveColor,
+    pub behavior: Behavior,
+    /// Frames between an enemy of the wave and the next, all at once with 0
+    pub delay: u8,
+}
+
+impl Wave {
+    const fn new(formation: Formation, count: u8) -> Self {
+        Self {
+            formation,
+            count,
+            color: WaveColor::Current,
+            behavior: Behavior::Chaser,
+            delay: 0,
+        }
+    }
+
+    pub const fn ring(count: u8, radius: u8) -> Self {
+        Self::new(Formation::Ring { radius }, count)
+    }
+
+    pub const fn line(from: (u8, u8), to: (u8, u8), count: u8) -> Self {
+        Self::new(Formation::Line { from, to }, count)
+    }
+
+    pub const fn spiral(count: u8, radius: u8, turns: u8) -> Self {
+        Self::new(Formation::Spiral { radius, turns }, count)
+    }
+
+    pub const fn of(self, behavior: Behavior) -> Self {
+        Self { behavior, ..self }
+    }
+
+    pub const fn colored(self, color: WaveColor) -> Self {
+        Self { color, ..self }
+    }
+
+    pub const fn every(self, delay: u8) -> Self {
+        Self { delay, ..self }
+    }
+
+    /// The enemies of the wave around `target`, `color` being the current
+    /// one, with the frames each waits for before showing up.
+    pub fn enemies(&self, frame: usize, target: Coord, color: u16) -> Vec<(u32, Enemy)> {
+        let other = match color {
+            DRAW_COLOR_A => DRAW_COLOR_B,
+            _ => DRAW_COLOR_A,
+        };
+        let count = self.count as usize;
+        (0..count)
+            .map(|i| {
+                let color = match self.color {
+                    WaveColor::Current => color,
+                    WaveColor::Other => other,
+                    WaveColor::Alternating if i % 2 == 0 => color,
+                    WaveColor::Alternating => other,
+                };
+                let center = self.formation.point(i, count, target);
+                let mut enemy = Enemy::new(frame, center, color).with_behavior(self.behavior);
+                let radius = enemy.entity.radius();
+                enemy.entity.position = center
+                    - Coord {
+                        x: radius,
+                        y: radius,
+                    };
+                enemy.entity.direction = target - center;
+                (i as u32 * self.delay as u32, enemy)
+            })
+            .collect()
+    }
+}
+
+impl Formation {
+    /// Where the `i`th of `count` enemies is centered, around `target`.
+    fn point(&self, i: usize, count: usize, target: Coord) -> Coord {
+        let fraction = |n: usize| Scalar::from(n as u32) / Scalar::from(count as u32);
+        match *self {
+            Formation::Ring { radius } => {
+                target + heading(i * TURN / count).scale(Scalar::from(radius as u32))
+            }
+            Formation::Line { from, to } => {
+                let (from, to) = (pixels(from), pixels(to));
+                // Multiplied first, to land exactly on the ends
+                let (n, steps) = (Scalar::from(i as u32), count.saturating_sub(1).max(1));
+                let steps = Scalar::from(steps as u32);
+                let along = to - from;
+                from + Coord {
+                    x: along.x * n / steps,
+                    y: along.y * n / steps,
+                }
+            }
+            Formation::Spiral { radius, turns } => {
+                let distance = Scalar::from(radius as u32) * fraction(count - i);
+                target + heading(i * TURN * turns as usize / count).scale(distance)
+            }
+        }
+    }
+}
+
+fn pixels((x, y): (u8, u8)) -> Coord {
+    Coord {
+        x: Scalar::from(x as u32),
+        y: Scalar::from(y as u32),
+    }
+}
+
+/// Unit vector `step` sixty-fourths of a turn clockwise from the right.
+fn heading(step: usize) -> Coord {
+    let sine = |step: usize| {
+        let step = step % TURN;
+        let quarter = match step % (TURN / 2) {
+            s if s <= TURN / 4 => s,
+            s => TURN / 2 - s,
+        };
+        let value = Scalar::from(QUARTER_SINE[quarter] as u32) / scalar(1024.0);
+        match step < TURN / 2 {
+            true => value,
+            false => scalar(0.0) - value,
+        }
+    };
+    Coord {
+        x: sine(step + TURN / 4),
+        y: sine(step),
+    }
+}
+
+/// A wave of a sequence, see `SEQUENCES`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cue {
+    /// Frames since the previous wave started
+    pub after: u16,
+    pub wave: Wave,
+}
+
+impl Cue {
+    pub const fn after(after: u16, wave: Wave) -> Self {
+        Self { after, wave }
+    }
+}
+
+/// Plays the sequence of waves of the difficulty, from its first wave
+/// whenever the difficulty changes.
+#[derive(Default)]
+pub struct Waves {
+    difficulty: u32,
+    /// Index of the next wave in the sequence, and frames until it starts
+    next: usize,
+    wait: u32,
+    /// Enemies of the waves started, and the frames they still wait for
+    pending: Vec<(u32, Enemy)>,
+}
+
+impl Waves {
+    /// One frame of the waves of `difficulty`: the enemies that show up, in
+    /// `color` and around `target` for the waves that start.
+    pub fn update(
+        &mut self,
+        difficulty: u32,
+        frame: usize,
+        target: Coord,
+        color: u16,
+    ) -> Vec<Enemy> {
+        let sequence = SEQUENCES[difficulty as usize];
+        if difficulty != self.difficulty {
+            self.difficulty = difficulty;
+            self.next = 0;
+            self.wait = sequence.first().map_or(0, |cue| cue.after as u32);
+        }
+        self.play(sequence, frame, target, color)
+    }
+
+    /// Starts `wave` around `from` right away, its enemies heading out: the
+    /// attack of a boss. They show up with those of the sequence.
+    pub fn burst(&mut self, wave: Wave, frame: usize, from: Coord, color: u16) {
+        let enemies = wave.enemies(frame, from, color).into_iter();
+        self.pending.extend(enemies.map(|(wait, mut enemy)| {
+            enemy.entity.direction = enemy.entity.center() - from;
+            (wait, enemy)
+        }));
+    }
+
+    fn play(&mut self, sequence: &[Cue], frame: usize, target: Coord, color: u16) -> Vec<Enemy> {
+        if !sequence.is_empty() {
+            self.wait = self.wait.saturating_sub(1);
+            if self.wait == 0 {
+                let wave = sequence[self.next % sequence.len()].wave;
+                self.pending.extend(wave.enemies(frame, target, color));
+                self.next = (self.next + 1) % sequence.len();
+                self.wait = sequence[self.next].after as u32;
+            }
+        }
+        let mut due = vec![];
+        for (wait, enemy) in std::mem::take(&mut self.pending) {
+            match wait {
+                0 => due.push(enemy),
+                _ => self.pending.push((wait - 1, enemy)),
+            }
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::calibrations::MAX_ENEMIES;
+
+    const TARGET: Coord = Coord::new(80.0, 80.0);
+
+    #[test]
+    fn headings_go_around() {
+        let close = |a: Coord, b: Coord| a.distance_to(&b) < scalar(0.01);
+        assert!(close(heading(0), Coord::new(1.0, 0.0)));
+        assert!(close(heading(TURN / 4), Coord::new(0.0, 1.0)));
+        assert!(close(heading(TURN / 2), Coord::new(-1.0, 0.0)));
+        assert!(close(heading(3 * TURN / 4), Coord::new(0.0, -1.0)));
+        assert!(close(heading(TURN), heading(0)));
+        for step in 0..TURN {
+            assert!((heading(step).norm() - scalar(1.0)).abs() < scalar(0.01));
+        }
+    }
+
+    #[test]
+    fn rings_close_in() {
+        let wave = Wave::ring(16, 60).colored(WaveColor::Alternating);
+        let enemies = wave.enemies(0, TARGET, DRAW_COLOR_A);
+        assert_eq!(enemies.len(), 16);
+        for (i, (wait, enemy)) in enemies.iter().enumerate() {
+            assert_eq!(*wait, 0);
+            let distance = enemy.entity.center().distance_to(&TARGET);
+            assert!((distance - scalar(60.0)).abs() < scalar(0.5));
+            // Heading for the middle
+            let ahead = enemy.entity.center() + enemy.entity.direction;
+            assert!(ahead.distance_to(&TARGET) < scalar(0.01));
+            let color = [DRAW_COLOR_A, DRAW_COLOR_B][i % 2];
+            assert_eq!(enemy.entity.color, color);
+        }
+        // Evenly spread: no two in the same place
+        for (i, (_, a)) in enemies.iter().enumerate() {
+            for (_, b) in &enemies[i + 1..] {
+                assert!(a.entity.position.distance_to(&b.entity.position) > scalar(20.0));
+            }
+        }
+    }
+
+    #[test]
+    fn lines_and_spirals() {
+        let wave = Wave::line((0, 10), (150, 10), 4)
+            .of(Behavior::Orbiter)
+            .every(5);
+        let enemies = wave.enemies(0, TARGET, DRAW_COLOR_B);
+        let xs: Vec<Scalar> = enemies.iter().map(|(_, e)| e.entity.center().x).collect();
+        let expected = [0.0, 50.0, 100.0, 150.0].map(scalar);
+        assert_eq!(xs, expected);
+        let waits: Vec<u32> = enemies.iter().map(|(wait, _)| *wait).collect();
+        assert_eq!(waits, [0, 5, 10, 15]);
+        assert!(enemies.iter().all(|(_, e)| e.behavior == Behavior::Orbiter));
+
+        // Each closer than the one before
+        let wave = Wave::spiral(8, 80, 2).colored(WaveColor::Other);
+        let enemies = wave.enemies(0, TARGET, DRAW_COLOR_B);
+        let distances: Vec<Scalar> = enemies
+            .iter()
+            .map(|(_, e)| e.entity.center().distance_to(&TARGET))
+            .collect();
+        assert!(distances.windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(enemies.iter().all(|(_, e)| e.entity.color == DRAW_COLOR_A));
+    }
+
+    #[test]
+    fn sequences_play_in_order() {
+        let sequence = [
+            Cue::after(10, Wave::ring(3, 40)),
+            Cue::after(5, Wave::line((0, 0), (100, 0), 2).every(2)),
+        ];
+        let mut waves = Waves {
+            wait: 10,
+            ..Default::default()
+        };
+        let mut spawned = vec![];
+        for frame in 1..=30 {
+            let enemies = waves.play(&sequence, frame, TARGET, DRAW_COLOR_A);
+            spawned.push(enemies.len());
+        }
+        // Ring at 10, line at 15 and 17, ring again at 25, line at 30
+        let expected: Vec<usize> = (1..=30)
+            .map(|frame| match frame {
+                10 | 25 => 3,
+                15 | 17 | 30 => 1,
+                _ => 0,
+            })
+            .collect();
+        assert_eq!(spawned, expected);
+
+        // Nothing at the start, the sequence starts over with the difficulty
+        let mut waves = Waves::default();
+        assert!(waves.update(0, 0, TARGET, DRAW_COLOR_A).is_empty());
+        waves.update(1, 0, TARGET, DRAW_COLOR_A);
+        assert_eq!(waves.wait as u16, SEQUENCES[1][0].after - 1);
+    }
+
+    #[test]
+    fn sequences_fit() {
+        assert!(SEQUENCES[0].is_empty());
+        for sequence in SEQUENCES {
+            for cue in sequence {
+                assert!(cue.after > 0);
+                assert!(cue.wave.count > 0 && (cue.wave.count as usize) < MAX_ENEMIES / 4);
+            }
+        }
+    }
+
+    #[test]
+    fn bosses_burst_out() {
+        let attack = BOSS_ATTACKS[1].wave;
+        let mut waves = Waves::default();
+        waves.burst(attack, 0, TARGET, DRAW_COLOR_B);
+        let mut enemies = vec![];
+        for frame in 0..attack.count as usize * attack.delay as usize {
+            enemies.extend(waves.update(0, frame, TARGET, DRAW_COLOR_A));
+        }
+        assert_eq!(enemies.len(), attack.count as usize);
+        for (i, enemy) in enemies.iter().enumerate() {
+            // Heading away from the boss
+            let behind = enemy.entity.center() - enemy.entity.direction;
+            assert!(behind.distance_to(&TARGET) < scalar(0.01));
+            assert_eq!(enemy.entity.color, [DRAW_COLOR_B, DRAW_COLOR_A][i % 2]);
+        }
+        for cue in BOSS_ATTACKS {
+            assert!(cue.after > 0 && cue.wave.color == WaveColor::Alternating);
+        }
+    }
+}
@@ -63,3 +63,188 @@ pub fn draw_hud(lives: u32, score: u32, high_score: u32, multiplier: u32, show_h
         );
     }
 }
+
+/// Compact HUD of individual co-op, every player in a corner (P1 top left, P2
+/// top right, P3 bottom left, P4 bottom right) with their score, then lives
+/// and multiplier. `corners` holds (lives, score, multiplier) of the players
+/// who joined, the ones out of lives show "out".
+pub fn draw_coop_hud(corners: &[Option<(u32, u32, u32)>; 4]) {
+    const LINE_HEIGHT: i32 = 8;
+    for (n, corner) in corners.iter().enumerate() {
+        let Some((lives, score, multiplier)) = *corner else {
+            continue;
+        };
+        let score = score.to_string();
+        let status = match lives {
+            0 => "out".to_string(),
+            // Room for the heart
+            _ => format!(" {lives} x{multiplier}"),
+        };
+        let top = match n < 2 {
+            true => 1,
+            false => SCREEN_SIZE as i32 - 2 * LINE_HEIGHT,
+        };
+        let x = |text: &str| match n % 2 {
+            0 => 1,
+            _ => SCREEN_SIZE as i32 - 1 - text.len() as i32 * 8,
+        };
+        palette::set_draw_color(0x12);
+        wasm4::text(&score, x(&score), top);
+        wasm4::text(&status, x(&status), top + LINE_HEIGHT);
+        if lives > 0 {
+            palette::set_draw_color(0x20);
+            wasm4::blit(&HEART, x(&status), top + LINE_HEIGHT, 8, 8, BLIT_1BPP);
+        }
+    }
+}
+
+/// HUD of a versus match: the round on top, and the round wins of every player
+/// in the match (None for the others) in their corner, like `draw_coop_hud`.
+pub fn draw_versus_hud(round: u8, wins: &[Option<u8>; 4]) {
+    palette::set_draw_color(0x12);
+    if round > 0 {
+        let round = format!("Round {round}");
+        wasm4::text(&round, (SCREEN_SIZE as i32 - round.len() as i32 * 8) / 2, 1);
+    }
+    for (n, wins) in wins.iter().enumerate() {
+        let Some(wins) = wins else {
+            continue;
+        };
+        let text = format!("P{} {}", n + 1, wins);
+        let x = match n % 2 {
+            0 => 1,
+            _ => SCREEN_SIZE as i32 - 1 - text.len() as i32 * 8,
+        };
+        let y = match n < 2 {
+            true => 1,
+            false => SCREEN_SIZE as i32 - 8,
+        };
+        wasm4::text(&text, x, y);
+    }
+}
+
+/// Health bar of the boss, across the top of the screen under the scores,
+/// filled in the color that hurts it.
+pub fn draw_boss_bar(health: u32, max_health: u32, color: u16) {
+    const WIDTH: u32 = 80;
+    const TOP: i32 = 18;
+    let left = (SCREEN_SIZE - WIDTH) as i32 / 2;
+    palette::set_draw_color(0x40);
+    wasm4::rect(left - 1, TOP, WIDTH + 2, 5);
+    let filled = WIDTH * health / max_health.max(1);
+    if filled > 0 {
+        let fill = color & 0xf;
+        palette::set_draw_color(fill << 4 | fill);
+        wasm4::rect(left, TOP + 1, filled, 3);
+    }
+}
+
+/// Marks a downed player's ghost, `size` wide with its top at `top`: the
+/// seconds they have left above it and, while a teammate touches it, a bar
+/// below it filling up to `percent` of the revive.
+pub fn draw_downed(center_x: i32, top: i32, size: i32, seconds: u32, percent: u32) {
+    let seconds = seconds.to_string();
+    palette::set_draw_color(0x4);
+    wasm4::text(
+        &seconds,
+        center_x - seconds.len() as i32 * 4,
+        (top - 9).max(0),
+    );
+    if percent > 0 {
+        let width = (size + 2) * percent.min(100) as i32 / 100;
+        palette::set_draw_color(0x44);
+        wasm4::rect(
+            center_x - size / 2 - 1,
+            top + size + 1,
+            width.max(1) as u32,
+            2,
+        );
+    }
+}
+
+/// Points down at what's right under (`center_x`, `top`): in netplay, the
+/// player this client controls.
+pub fn draw_pointer(center_x: i32, top: i32) {
+    palette::set_draw_color(0x4);
+    let y = (top - 5).max(0);
+    wasm4::hline(center_x - 2, y, 5);
+    wasm4::hline(center_x - 1, y + 1, 3);
+    wasm4::hline(center_x, y + 2, 1);
+}
+
+/// Dots of `draw_meter`, clockwise from the top
+const METER_OFFSETS: [(i32, i32); 8] = [
+    (0, -6),
+    (4, -4),
+    (6, 0),
+    (4, 4),
+    (0, 6),
+    (-4, 4),
+    (-6, 0),
+    (-4, -4),
+];
+pub const METER_DOTS: u32 = METER_OFFSETS.len() as u32;
+
+/// A ring of dots around (`center_x`, `center_y`), the first `filled` of
+/// `METER_DOTS` lit: how much a player's dash has recharged.
+pub fn draw_meter(center_x: i32, center_y: i32, filled: u32) {
+    let size = SCREEN_SIZE as i32;
+    for (n, (dx, dy)) in METER_OFFSETS.into_iter().enumerate() {
+        let (x, y) = (center_x + dx, center_y + dy);
+        if !(0..size).contains(&x) || !(0..size).contains(&y) {
+            continue;
+        }
+        let color = if (n as u32) < filled { 0x4 } else { 0x2 };
+        palette::set_draw_color(color);
+        pixel(x, y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm4::host;
+
+    #[test]
+    fn pixel_layout() {
+        let _session = host::lock();
+        palette::set_draw_color(0x4);
+        pixel(5, 1);
+        assert_eq!(host::framebuffer()[(160 + 5) / 4], 0b11 << 2);
+        assert_eq!(host::pixel(5, 1), 3);
+    }
+
+    #[test]
+    fn hud_hearts() {
+        let _session = host::lock();
+        draw_hud(3, 1234, 5678, 9, true);
+        let heart_pixel = |l: i32| host::pixel(SCREEN_SIZE as i32 - 7 - l * 8, 152);
+        assert_eq!([0, 1, 2, 3].map(heart_pixel), [1, 1, 1, 0]);
+        // Score text sits on a background of palette color 0
+        assert!((1..9).any(|x| host::pixel(x, 3) == 1));
+    }
+
+    #[test]
+    fn boss_bar_fills() {
+        let _session = host::lock();
+        draw_boss_bar(3, 4, 0x34);
+        // A quarter of the bar is gone
+        assert_eq!(host::pixel(40, 20), 3);
+        assert_eq!(host::pixel(99, 20), 3);
+        assert_eq!(host::pixel(101, 20), 0);
+        assert_eq!(host::pixel(39, 18), 3);
+    }
+
+    #[test]
+    fn coop_hud_corners() {
+        let _session = host::lock();
+        draw_coop_hud(&[Some((2, 10, 3)), None, None, Some((0, 55, 9))]);
+        let text_at = |x: i32, y: i32| (x..x + 8).any(|x| host::pixel(x, y + 3) == 1);
+        // P1 score and heart on the top left, P4 "out" on the bottom right
+        assert!(text_at(1, 1));
+        assert_eq!(host::pixel(3, 11), 1);
+        assert!(!text_at(SCREEN_SIZE as i32 - 9, 1));
+        assert!(text_at(SCREEN_SIZE as i32 - 9, SCREEN_SIZE as i32 - 8));
+        assert!(!text_at(SCREEN_SIZE as i32 - 33, SCREEN_SIZE as i32 - 8));
+    }
+}
@@ -2,4 +2,6 @@ pub mod draw_utils;
 pub mod intro_screen;
 pub mod palette;
 pub mod screen;
+#[cfg(test)]
+pub mod snapshot;
 pub mod title_image;
@@ -1,8 +1,9 @@
 use crate::{
-    common::types::Coord,
+    common::types::{Coord, Scalar, ScalarExt},
     entities::{
         bomb::Bomb,
         enemy::Enemy,
+        layout::Layout,
         player::{Player, PlayerN},
         traits::Visible,
     },
@@ -46,14 +47,6 @@ const CMOUSE_ICON: [u8; 16] = [
     0xea, 0xaf, 0x94, 0x5b, 0x94, 0x5b, 0xaa, 0xab, 0x95, 0x5b, 0xe5, 0x6f, 0xfa, 0xbf, 0xff, 0xff,
 ];
 
-#[derive(PartialEq, Eq)]
-pub enum ScreenName {
-    Title,
-    HowToPlay,
-    MainGame,
-    GameOver,
-}
-
 pub fn title(tick: usize) {
     palette::set_draw_color(0x1234);
     wasm4::blit(
@@ -73,10 +66,14 @@ pub fn title(tick: usize) {
         TITLE1_HEIGHT,
         TITLE1_FLAGS,
     );
+    palette::set_draw_color(0x41);
+    wasm4::text([DOWN_ICON, b" Settings"].concat(), 78, 150);
     palette::set_draw_color(0x02);
 }
 
-pub fn how_to_play(tick: usize) {
+/// `local_player` is the player this client controls in netplay, where the
+/// mouse is disabled and its hints are left out.
+pub fn how_to_play(tick: usize, local_player: Option<PlayerN>) {
     const HTP_TEXT_COLOR: u16 = 0x12;
     const HTP_TEXT_COLOR_ALT: u16 = 0x13;
     let voff = 5;
@@ -89,6 +86,9 @@ pub fn how_to_play(tick: usize) {
     palette::set_draw_color(0x02);
     wasm4::text("--- HOW TO PLAY ---", hoff - 16, voff + 7);
     let mut player = Player::new(PlayerN::P1);
+    let mouse = local_player.is_none();
+    // Mouse hints go after a slash, blank without them
+    let or: &[u8] = if mouse { b"/" } else { b" " };
 
     palette::set_draw_color(HTP_TEXT_COLOR);
     wasm4::text("   You:", hoff, voff + 25);
@@ -96,16 +96,16 @@ pub fn how_to_play(tick: usize) {
     wasm4::text("Absorb:", hoff, voff + 45);
     wasm4::text("  Bomb:", hoff, voff + 55);
     player.entity.position = Coord {
-        x: hoff as f64 + 59.0,
-        y: voff as f64 + 25.0,
+        x: Scalar::from(hoff + 59),
+        y: Scalar::from(voff + 25),
     };
     player.draw();
 
     let enemy = Enemy::new(
         0,
         Coord {
-            x: hoff as f64 + 60.0,
-            y: voff as f64 + 36.0,
+            x: Scalar::from(hoff + 60),
+            y: Scalar::from(voff + 36),
         },
         DRAW_COLOR_A,
     );
@@ -113,63 +113,74 @@ pub fn how_to_play(tick: usize) {
     let enemy = Enemy::new(
         0,
         Coord {
-            x: hoff as f64 + 60.0,
-            y: voff as f64 + 46.0,
+            x: Scalar::from(hoff + 60),
+            y: Scalar::from(voff + 46),
         },
         DRAW_COLOR_B,
     );
     enemy.draw();
     let bomb = Bomb::new(&Coord {
-        x: hoff as f64 + 58.0,
-        y: voff as f64 + 54.0,
+        x: Scalar::from(hoff + 58),
+        y: Scalar::from(voff + 54),
     });
     bomb.draw();
     palette::set_draw_color(HTP_TEXT_COLOR);
     wasm4::text(
-        [b" /", LEFT_ICON, DOWN_ICON, UP_ICON, RIGHT_ICON, b":Move"].concat(),
+        [
+            b" ", or, LEFT_ICON, DOWN_ICON, UP_ICON, RIGHT_ICON, b":Move",
+        ]
+        .concat(),
         hoff,
         voff + 70,
     );
-    palette::set_draw_color(0x0234);
-    wasm4::blit(
-        &LMOUSE_ICON,
-        hoff - 1,
-        voff + 69,
-        LMOUSE_ICON_WIDTH,
-        LMOUSE_ICON_HEIGHT,
-        LMOUSE_ICON_FLAGS,
-    );
     palette::set_draw_color(HTP_TEXT_COLOR);
-    wasm4::text([b"    /", X_ICON, b": -> ->"].concat(), hoff, voff + 80);
-    palette::set_draw_color(0x0234);
-    wasm4::blit(
-        &RMOUSE_ICON,
-        hoff + 24,
-        voff + 80,
-        RMOUSE_ICON_WIDTH,
-        RMOUSE_ICON_HEIGHT,
-        RMOUSE_ICON_FLAGS,
-    );
+    wasm4::text([b"    ", or, X_ICON, b": -> ->"].concat(), hoff, voff + 80);
+    if mouse {
+        palette::set_draw_color(0x0234);
+        wasm4::blit(
+            &LMOUSE_ICON,
+            hoff - 1,
+            voff + 69,
+            LMOUSE_ICON_WIDTH,
+            LMOUSE_ICON_HEIGHT,
+            LMOUSE_ICON_FLAGS,
+        );
+        wasm4::blit(
+            &RMOUSE_ICON,
+            hoff + 24,
+            voff + 80,
+            RMOUSE_ICON_WIDTH,
+            RMOUSE_ICON_HEIGHT,
+            RMOUSE_ICON_FLAGS,
+        );
+    }
     player.entity.position = Coord {
-        x: hoff as f64 + 55.0,
-        y: voff as f64 + 80.0,
+        x: Scalar::from(hoff + 55),
+        y: Scalar::from(voff + 80),
     };
     player.draw();
     player.entity.position = Coord {
-        x: hoff as f64 + 80.0,
-        y: voff as f64 + 80.0,
+        x: Scalar::from(hoff + 80),
+        y: Scalar::from(voff + 80),
     };
     player.toggle_color();
     player.draw();
     player.toggle_color();
     player.entity.position = Coord {
-        x: hoff as f64 + 104.0,
-        y: voff as f64 + 80.0,
+        x: Scalar::from(hoff + 104),
+        y: Scalar::from(voff + 80),
     };
     player.draw();
 
     palette::set_draw_color(HTP_TEXT_COLOR_ALT);
-    wasm4::text("--Multiplayer--\nUp to 4 Players", hoff, voff + 96);
+    match local_player {
+        Some(player_n) => wasm4::text(
+            format!("  --Netplay--\n  You are P{}", player_n as u8 + 1),
+            hoff,
+            voff + 96,
+        ),
+        None => wasm4::text("--Multiplayer--\nUp to 4 Players", hoff, voff + 96),
+    }
 
     palette::set_draw_color(0x23);
     wasm4::rect(hoff - 10, voff + 122, SCREEN_SIZE - 20, 13);
@@ -187,31 +198,336 @@ pub fn how_to_play(tick: usize) {
     );
 
     palette::set_draw_color(HTP_TEXT_COLOR_ALT);
-    wasm4::text([b"/", Z_ICON, b":palette"].concat(), hoff + 54, voff + 145);
-    palette::set_draw_color(0x0234);
-    wasm4::blit(
-        &CMOUSE_ICON,
-        hoff + 46,
-        voff + 145,
-        CMOUSE_ICON_WIDTH,
-        CMOUSE_ICON_HEIGHT,
-        CMOUSE_ICON_FLAGS,
-    );
+    wasm4::text([or, Z_ICON, b":palette"].concat(), hoff + 54, voff + 145);
+    if mouse {
+        palette::set_draw_color(0x0234);
+        wasm4::blit(
+            &CMOUSE_ICON,
+            hoff + 46,
+            voff + 145,
+            CMOUSE_ICON_WIDTH,
+            CMOUSE_ICON_HEIGHT,
+            CMOUSE_ICON_FLAGS,
+        );
+    }
 }
 
-pub fn game_over(tick: usize) {
+/// `rank` is the position (0 based) the run got on the leaderboard, if any.
+pub fn game_over(tick: usize, rank: Option<usize>) {
     palette::set_draw_color(0x14);
     wasm4::text(
         "GAME OVER",
         SCREEN_SIZE as i32 / 2 - 35,
         SCREEN_SIZE as i32 / 2 - 10,
     );
+    palette::set_draw_color(0x12);
+    let rank_text = match rank {
+        Some(rank) => format!("Rank #{}", rank + 1),
+        None => "Not in top 10".to_owned(),
+    };
+    wasm4::text(
+        &rank_text,
+        SCREEN_SIZE as i32 / 2 - rank_text.len() as i32 * 4,
+        SCREEN_SIZE as i32 / 2,
+    );
+    if (tick / 2) % 10 < 5 {
+        palette::set_draw_color(0x10);
+    }
+    if rank.is_some() {
+        wasm4::text(
+            [b"Press ", X_ICON, b" to continue"].concat(),
+            4,
+            SCREEN_SIZE as i32 / 2 + 13,
+        );
+    } else {
+        wasm4::text(
+            [b"Press ", X_ICON, b" to restart"].concat(),
+            8,
+            SCREEN_SIZE as i32 / 2 + 13,
+        );
+    }
+}
+
+/// Results of an individual co-op run: score and multiplier of every player
+/// who joined, (player number, score, multiplier) in `rows`, with the `best`
+/// row highlighted.
+pub fn coop_results(rows: &[(u8, u32, u32)], best: usize) {
+    const ROWS_TOP: i32 = 28;
+    const ROW_HEIGHT: i32 = 10;
+    palette::set_draw_color(0x23);
+    wasm4::rect(2, 8, SCREEN_SIZE - 4, 15);
+    palette::set_draw_color(0x02);
+    wasm4::text("---- RESULTS ----", 12, 12);
+
+    for (i, (player, score, multiplier)) in rows.iter().enumerate() {
+        palette::set_draw_color(if i == best { 0x14 } else { 0x12 });
+        wasm4::text(
+            format!("P{} {:>9} x{:<4}", player, score, multiplier),
+            8,
+            ROWS_TOP + i as i32 * ROW_HEIGHT,
+        );
+    }
+}
+
+/// Players who joined the versus match (`entrants`), and how to start it.
+pub fn versus_lobby(tick: usize, entrants: &[bool; 4]) {
+    const LEFT: i32 = 20;
+    const LINE_HEIGHT: i32 = 10;
+    let top = 40;
+    palette::set_draw_color(0x21);
+    wasm4::rect(LEFT, top, SCREEN_SIZE - 2 * LEFT as u32, 80);
+    palette::set_draw_color(0x04);
+    wasm4::text("VERSUS", SCREEN_SIZE as i32 / 2 - 24, top + 6);
+    for (n, entrant) in entrants.iter().enumerate() {
+        let y = top + 22 + n as i32 * LINE_HEIGHT;
+        if *entrant {
+            palette::set_draw_color(0x04);
+            wasm4::text(format!("P{}  ready", n + 1), LEFT + 16, y);
+        } else {
+            palette::set_draw_color(0x03);
+            wasm4::text(
+                [format!("P{}  ", n + 1).as_bytes(), X_ICON, b" to join"].concat(),
+                LEFT + 16,
+                y,
+            );
+        }
+    }
+    let ready = entrants.iter().filter(|&&entrant| entrant).count() >= 2;
+    palette::set_draw_color(0x03);
+    if ready {
+        if (tick / 2) % 10 < 5 {
+            palette::set_draw_color(0x04);
+        }
+        wasm4::text([b"P1 ", X_ICON, b" to start"].concat(), LEFT + 8, top + 66);
+    } else {
+        wasm4::text("Need 2 players", LEFT + 4, top + 66);
+    }
+}
+
+/// Two centered lines on a panel, e.g. the countdown before a round.
+pub fn banner(title: &str, subtitle: &str) {
+    let top = SCREEN_SIZE as i32 / 2 - 16;
+    palette::set_draw_color(0x21);
+    wasm4::rect(20, top, SCREEN_SIZE - 40, 32);
+    palette::set_draw_color(0x04);
+    wasm4::text(
+        title,
+        SCREEN_SIZE as i32 / 2 - title.len() as i32 * 4,
+        top + 6,
+    );
+    palette::set_draw_color(0x03);
+    wasm4::text(
+        subtitle,
+        SCREEN_SIZE as i32 / 2 - subtitle.len() as i32 * 4,
+        top + 18,
+    );
+}
+
+/// End of a versus match: (player number, round wins) in `rows`, with the
+/// row of the `best` player highlighted.
+pub fn versus_results(tick: usize, rows: &[(u8, u8)], best: Option<usize>) {
+    const ROWS_TOP: i32 = 28;
+    const ROW_HEIGHT: i32 = 10;
+    palette::set_draw_color(0x23);
+    wasm4::rect(2, 8, SCREEN_SIZE - 4, 15);
+    palette::set_draw_color(0x02);
+    wasm4::text("---- VERSUS ----", 16, 12);
+
+    for (i, (player, wins)) in rows.iter().enumerate() {
+        palette::set_draw_color(if Some(i) == best { 0x14 } else { 0x12 });
+        let plural = if *wins == 1 { "" } else { "s" };
+        wasm4::text(
+            format!("P{}  {} round{}", player, wins, plural),
+            24,
+            ROWS_TOP + i as i32 * ROW_HEIGHT,
+        );
+    }
+
+    if let Some(best) = best.and_then(|best| rows.get(best)) {
+        let winner = format!("P{} WINS!", best.0);
+        palette::set_draw_color(0x14);
+        wasm4::text(
+            &winner,
+            SCREEN_SIZE as i32 / 2 - winner.len() as i32 * 4,
+            SCREEN_SIZE as i32 / 2 + 10,
+        );
+    }
+    palette::set_draw_color(0x12);
     if (tick / 2) % 10 < 5 {
         palette::set_draw_color(0x10);
     }
     wasm4::text(
-        [b"Press ", X_ICON, b" to restart"].concat(),
+        [b"Press ", X_ICON, b" to rematch"].concat(),
         8,
-        SCREEN_SIZE as i32 / 2 + 13,
+        SCREEN_SIZE as i32 / 2 + 23,
     );
 }
+
+/// Preview of the arena called `name`: its obstacles where they start and the
+/// enemy spawn points. None for rotating arenas.
+pub fn arena_select(tick: usize, name: &str, layout: Option<&Layout>) {
+    const STRIP: i32 = 12;
+    palette::set_draw_color(0x11);
+    wasm4::rect(0, 0, SCREEN_SIZE, SCREEN_SIZE);
+    match layout {
+        Some(layout) => {
+            for obstacle in layout.obstacles() {
+                obstacle.draw();
+            }
+            palette::set_draw_color(0x22);
+            for spawn in layout.spawns() {
+                let max = SCREEN_SIZE as i32 - 4;
+                wasm4::rect(spawn.x.to_i32().min(max), spawn.y.to_i32().min(max), 4, 4);
+            }
+        }
+        None => {
+            palette::set_draw_color(0x03);
+            for (i, line) in ["A new arena as", "the game gets", "harder"]
+                .iter()
+                .enumerate()
+            {
+                wasm4::text(
+                    line,
+                    SCREEN_SIZE as i32 / 2 - line.len() as i32 * 4,
+                    60 + i as i32 * 12,
+                );
+            }
+        }
+    }
+
+    palette::set_draw_color(0x21);
+    wasm4::rect(0, 0, SCREEN_SIZE, STRIP as u32);
+    wasm4::rect(0, SCREEN_SIZE as i32 - STRIP, SCREEN_SIZE, STRIP as u32);
+    palette::set_draw_color(0x04);
+    wasm4::text(name, SCREEN_SIZE as i32 / 2 - name.len() as i32 * 4, 2);
+    palette::set_draw_color(0x03);
+    if (tick / 2) % 10 < 5 {
+        palette::set_draw_color(0x04);
+    }
+    wasm4::text(
+        [LEFT_ICON, RIGHT_ICON, b" browse ", X_ICON, b" pick"].concat(),
+        16,
+        SCREEN_SIZE as i32 - STRIP + 2,
+    );
+}
+
+/// Pause menu, drawn over the frozen game, with the `selected` option pointed at.
+pub fn pause_menu(tick: usize, options: &[&str], selected: usize) {
+    let rows: Vec<(&str, &str)> = options.iter().map(|option| (*option, "")).collect();
+    menu(tick, "PAUSED", 14, &rows, selected);
+}
+
+/// Settings menu, rows of names and values, drawn over whatever screen it was
+/// opened from.
+pub fn settings_menu(tick: usize, rows: &[(&str, &str)], selected: usize) {
+    menu(tick, "SETTINGS", 8, rows, selected);
+}
+
+/// Panel with a `title` and rows of options (and their value, on the right),
+/// `left` pixels from the edges of the screen.
+fn menu(tick: usize, title: &str, left: i32, rows: &[(&str, &str)], selected: usize) {
+    const LINE_HEIGHT: i32 = 12;
+    let height = 26 + rows.len() as i32 * LINE_HEIGHT;
+    let top = (SCREEN_SIZE as i32 - height) / 2;
+    palette::set_draw_color(0x21);
+    wasm4::rect(left, top, SCREEN_SIZE - 2 * left as u32, height as u32);
+    palette::set_draw_color(0x04);
+    wasm4::text(
+        title,
+        SCREEN_SIZE as i32 / 2 - title.len() as i32 * 4,
+        top + 6,
+    );
+
+    for (i, (option, value)) in rows.iter().enumerate() {
+        let y = top + 22 + i as i32 * LINE_HEIGHT;
+        if i == selected {
+            palette::set_draw_color(0x04);
+            if (tick / 4) % 10 < 6 {
+                wasm4::text(RIGHT_ICON, left + 6, y);
+            }
+        } else {
+            palette::set_draw_color(0x03);
+        }
+        wasm4::text(option, left + 18, y);
+        let right = SCREEN_SIZE as i32 - left - 6;
+        wasm4::text(value, right - value.len() as i32 * 8, y);
+    }
+}
+
+/// Leaderboard rows (initials, score and player count), with the initials of
+/// the row at `rank` being entered: the letter under `cursor` is underlined
+/// until all three are confirmed.
+pub fn enter_initials(tick: usize, rows: &[([u8; 3], u32, u8)], rank: usize, cursor: usize) {
+    const ROWS_TOP: i32 = 30;
+    const ROW_HEIGHT: i32 = 10;
+    palette::set_draw_color(0x23);
+    wasm4::rect(2, 8, SCREEN_SIZE - 4, 15);
+    palette::set_draw_color(0x02);
+    wasm4::text("--- HIGH SCORES ---", 4, 12);
+
+    for (i, (initials, score, players)) in rows.iter().enumerate() {
+        let y = ROWS_TOP + i as i32 * ROW_HEIGHT;
+        palette::set_draw_color(if i == rank { 0x14 } else { 0x12 });
+        wasm4::text(
+            [
+                format!("{:>2} ", i + 1).as_bytes(),
+                initials,
+                format!(" {:>9} {}P", score, players).as_bytes(),
+            ]
+            .concat(),
+            4,
+            y,
+        );
+        if i == rank && cursor < 3 && (tick / 4) % 10 < 6 {
+            wasm4::hline(28 + cursor as i32 * 8, y + 8, 8);
+        }
+    }
+
+    let y = ROWS_TOP + 10 * ROW_HEIGHT + 4;
+    palette::set_draw_color(0x12);
+    if cursor < 3 {
+        wasm4::text(
+            [UP_ICON, DOWN_ICON, b":Letter ", X_ICON, b":Next"].concat(),
+            16,
+            y,
+        );
+    } else {
+        if (tick / 2) % 10 < 5 {
+            palette::set_draw_color(0x10);
+        }
+        wasm4::text([b"Press ", X_ICON, b" to restart"].concat(), 8, y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm4::host;
+
+    #[test]
+    fn title_covers_screen() {
+        let _session = host::lock();
+        title(0);
+        let mut colors = [0; 4];
+        for y in 0..SCREEN_SIZE as i32 {
+            for x in 0..SCREEN_SIZE as i32 {
+                colors[host::pixel(x, y) as usize] += 1;
+            }
+        }
+        assert!(colors.iter().all(|&c| c > 0));
+    }
+
+    #[test]
+    fn how_to_play_layout() {
+        let _session = host::lock();
+        how_to_play(0, None);
+        // Header bar, outline and fill
+        assert_eq!(host::pixel(2, 8), 1);
+        assert_eq!(host::pixel(3, 9), 2);
+        // "You:" disk, with player number dot in the center
+        assert_eq!(host::pixel(81, 33), 3);
+        assert_eq!(host::pixel(82, 33), 2);
+        // Panel background is left plain around it
+        assert_eq!(host::pixel(77, 33), 0);
+    }
+}
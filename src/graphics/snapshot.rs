@@ -0,0 +1,146 @@
+// Golden image testing: the current framebuffer of the host backend is saved as
+// a 2 bits indexed PNG using the active palette, and compared with the image
+// checked in under tests/snapshots/.
+//
+// After an intended visual change, re-bless the images with:
+//
+//     BLESS=1 cargo run_tests snapshot
+//
+// and review them before committing. When a comparison fails the rendered image
+// is left in target/snapshots/ for inspection.
+
+use std::{fs, path::PathBuf};
+
+use crate::wasm4::{host, SCREEN_SIZE};
+
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+const FAILED_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/snapshots");
+
+/// What's on screen: palette index of every pixel, row by row, and the palette.
+#[derive(PartialEq)]
+struct Image {
+    pixels: Vec<u8>,
+    palette: [u32; 4],
+}
+
+impl Image {
+    fn capture() -> Self {
+        let size = SCREEN_SIZE as i32;
+        Self {
+            pixels: (0..size)
+                .flat_map(|y| (0..size).map(move |x| host::pixel(x, y)))
+                .collect(),
+            palette: host::palette(),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, SCREEN_SIZE, SCREEN_SIZE);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Two);
+        encoder.set_palette(
+            self.palette
+                .iter()
+                .flat_map(|c| [(c >> 16) as u8, (c >> 8) as u8, *c as u8])
+                .collect::<Vec<u8>>(),
+        );
+        // PNG packs the leftmost pixel in the high bits
+        let data: Vec<u8> = self
+            .pixels
+            .chunks(4)
+            .map(|p| p[0] << 6 | p[1] << 4 | p[2] << 2 | p[3])
+            .collect();
+        let mut writer = encoder.write_header().expect("PNG header");
+        writer.write_image_data(&data).expect("PNG data");
+        writer.finish().expect("PNG end");
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let mut reader = decoder.read_info().expect("PNG header");
+        let mut data = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut data).expect("PNG data");
+
+        let info = reader.info();
+        assert_eq!(
+            (info.color_type, info.bit_depth),
+            (png::ColorType::Indexed, png::BitDepth::Two),
+            "Snapshots are 2 bits indexed PNGs"
+        );
+        let plte = info.palette.as_ref().expect("PNG palette");
+        let mut palette = [0; 4];
+        for (color, rgb) in palette.iter_mut().zip(plte.chunks(3)) {
+            *color = (rgb[0] as u32) << 16 | (rgb[1] as u32) << 8 | rgb[2] as u32;
+        }
+        Self {
+            pixels: data
+                .iter()
+                .flat_map(|b| [b >> 6, (b >> 4) & 0b11, (b >> 2) & 0b11, b & 0b11])
+                .collect(),
+            palette,
+        }
+    }
+}
+
+/// Compares what's currently on screen with the golden image `name`.
+pub fn assert_snapshot(name: &str) {
+    let actual = Image::capture();
+    let golden_path = PathBuf::from(GOLDEN_DIR).join(format!("{name}.png"));
+
+    if std::env::var_os("BLESS").is_some() {
+        fs::create_dir_all(GOLDEN_DIR).expect("snapshot directory");
+        fs::write(&golden_path, actual.encode()).expect("golden image");
+        return;
+    }
+
+    let golden = match fs::read(&golden_path) {
+        Ok(bytes) => Image::decode(&bytes),
+        Err(_) => panic!(
+            "Missing golden image {}, create it with `BLESS=1 cargo run_tests snapshot`",
+            golden_path.display()
+        ),
+    };
+    if golden == actual {
+        return;
+    }
+
+    fs::create_dir_all(FAILED_DIR).expect("snapshot directory");
+    let failed_path = PathBuf::from(FAILED_DIR).join(format!("{name}.png"));
+    fs::write(&failed_path, actual.encode()).expect("rendered image");
+    let different = golden
+        .pixels
+        .iter()
+        .zip(&actual.pixels)
+        .filter(|(a, b)| a != b)
+        .count();
+    panic!(
+        "Snapshot '{name}' differs from its golden image: {different} pixels changed{}.\n\
+         Rendered image saved to {}. If the change is intended, re-bless with \
+         `BLESS=1 cargo run_tests snapshot`",
+        if golden.palette != actual.palette {
+            " and the palette changed"
+        } else {
+            ""
+        },
+        failed_path.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_roundtrip() {
+        let image = Image {
+            pixels: (0..SCREEN_SIZE * SCREEN_SIZE)
+                .map(|i| (i % 7 % 4) as u8)
+                .collect(),
+            palette: [0x120136, 0x035aa6, 0x40bad5, 0xfcbf1e],
+        };
+        assert!(Image::decode(&image.encode()) == image);
+    }
+}
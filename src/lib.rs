@@ -1,4 +1,6 @@
-#[cfg(feature = "buddy-alloc")]
+// The buddy allocator is meant for the cart only, native builds (tests) keep
+// using the system one.
+#[cfg(all(feature = "buddy-alloc", target_arch = "wasm32"))]
 mod alloc;
 mod entities;
 mod game;
@@ -14,7 +16,7 @@ lazy_static! {
 
 #[no_mangle]
 fn start() {
-    graphics::palette::set_palette_n(0);
+    PLAYER_GAME.lock().expect("game_state").start();
 }
 
 #[no_mangle]
@@ -1,52 +1,103 @@
 #![allow(unused)]
-use super::notes::*;
+// Sound effects, played at `percent` of their volume.
+use super::{notes::*, tone};
 use crate::wasm4::{
     self, TONE_MODE1, TONE_MODE2, TONE_MODE3, TONE_MODE4, TONE_NOISE, TONE_PAN_LEFT,
     TONE_PAN_RIGHT, TONE_PULSE1, TONE_PULSE2, TONE_TRIANGLE,
 };
 
-pub fn bomb_explode() {
-    wasm4::tone(
+pub fn bomb_explode(percent: u32) {
+    tone(
         380 | (10 << 16),
         10 | (10 << 16),
         10,
         TONE_PULSE1 | TONE_MODE3,
+        percent,
     );
 }
-pub fn death() {
-    wasm4::tone(
+pub fn death(percent: u32) {
+    tone(
         140 | (110 << 16),
         3 | (6 << 16),
         60,
         TONE_NOISE | TONE_MODE3,
+        percent,
     );
 }
 
-pub fn extra_life() {
-    wasm4::tone(
+pub fn extra_life(percent: u32) {
+    tone(
         6000 << 16,
         1 | (3 << 8) | (8 << 16) | (3 << 24),
         100 | (100 << 8),
         TONE_PULSE1 | TONE_MODE1,
+        percent,
     );
 }
 
-pub fn new_player() {
-    wasm4::tone(400 | 1000 << 16, 10, 100, TONE_PULSE2 | TONE_MODE1);
+pub fn new_player(percent: u32) {
+    tone(400 | 1000 << 16, 10, 100, TONE_PULSE2 | TONE_MODE1, percent);
 }
-pub fn color1_switch() {
-    wasm4::tone(
+pub fn power_up(percent: u32) {
+    tone(
+        500 | (1200 << 16),
+        2 | (8 << 8) | (6 << 16),
+        70 | (100 << 8),
+        TONE_TRIANGLE | TONE_MODE1,
+        percent,
+    );
+}
+pub fn drop_bomb(percent: u32) {
+    tone(
+        200 | (120 << 16),
+        4 | (6 << 8),
+        60 | (100 << 8),
+        TONE_PULSE1 | TONE_MODE2,
+        percent,
+    );
+}
+pub fn dash(percent: u32) {
+    tone(
+        900 | (200 << 16),
+        6,
+        40 | (100 << 8),
+        TONE_NOISE | TONE_MODE1,
+        percent,
+    );
+}
+pub fn leave(percent: u32) {
+    tone(
+        1000 | 400 << 16,
+        10,
+        100 | (100 << 8),
+        TONE_PULSE2 | TONE_MODE1,
+        percent,
+    );
+}
+pub fn revive(percent: u32) {
+    tone(
+        300 | (900 << 16),
+        4 | (16 << 8) | (10 << 16),
+        80 | (100 << 8),
+        TONE_PULSE2 | TONE_MODE2,
+        percent,
+    );
+}
+pub fn color1_switch(percent: u32) {
+    tone(
         340,
         1 | (3 << 8) | (8 << 16),
         24 << 8,
         TONE_TRIANGLE | TONE_MODE1,
+        percent,
     );
 }
-pub fn color2_switch() {
-    wasm4::tone(
+pub fn color2_switch(percent: u32) {
+    tone(
         360,
         1 | (3 << 8) | (8 << 16) | (3 << 24),
         24 << 8,
         TONE_TRIANGLE | TONE_MODE1,
+        percent,
     );
 }
@@ -1,3 +1,52 @@
 pub mod effects;
 pub mod music;
 pub mod notes;
+
+use crate::wasm4;
+
+/// Plays a tone at `percent` of its `volume` (sustain, and attack peak in the
+/// second byte). Nothing is played at 0%: a volume word can't be silent, the
+/// console plays a peak of 0 at 100.
+pub fn tone(frequency: u32, duration: u32, volume: u32, flags: u32, percent: u32) {
+    if percent > 0 {
+        wasm4::tone(frequency, duration, scaled_volume(volume, percent), flags);
+    }
+}
+
+/// Tone volume (sustain, and attack peak in the second byte) at `percent`.
+/// A peak of 0 stands for 100, and a scaled peak never rounds down to 0.
+fn scaled_volume(volume: u32, percent: u32) -> u32 {
+    let peak = match volume >> 8 & 0xff {
+        0 => 100,
+        peak => peak,
+    };
+    let sustain = (volume & 0xff) * percent / 100;
+    let peak = (peak * percent / 100).max(1);
+    sustain | peak << 8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm4::host;
+
+    fn volumes(play: fn(u32), percent: u32) -> Vec<u32> {
+        play(percent);
+        host::take_tones().iter().map(|tone| tone.volume).collect()
+    }
+
+    #[test]
+    fn volumes_scale_with_the_setting() {
+        let _session = host::lock();
+        // Explicit peak, no sustain
+        assert_eq!(volumes(effects::color1_switch, 100), [24 << 8]);
+        assert_eq!(volumes(effects::color1_switch, 50), [12 << 8]);
+        assert!(volumes(effects::color1_switch, 0).is_empty());
+        // Implicit peak, played at 100 by the console
+        assert_eq!(volumes(effects::bomb_explode, 100), [10 | 100 << 8]);
+        assert_eq!(volumes(effects::bomb_explode, 50), [5 | 50 << 8]);
+        assert!(volumes(effects::bomb_explode, 0).is_empty());
+        // Quiet, but never back to a peak of 0
+        assert_eq!(scaled_volume(24 << 8, 2), 1 << 8);
+    }
+}
@@ -1,5 +1,5 @@
 #![allow(unused)]
-use super::notes::*;
+use super::{notes::*, tone};
 use crate::wasm4::{
     self, TONE_MODE1, TONE_MODE2, TONE_MODE3, TONE_MODE4, TONE_NOISE, TONE_PAN_LEFT,
     TONE_PAN_RIGHT, TONE_PULSE1, TONE_PULSE2, TONE_TRIANGLE,
@@ -7,18 +7,26 @@ use crate::wasm4::{
 
 pub const VOICE_NOTES: usize = 64;
 
-fn voice_player(counter: usize, voice: Voice, duration: Duration, volume: Volume, flags: Flags) {
+fn voice_player(
+    counter: usize,
+    voice: Voice,
+    duration: Duration,
+    volume: Volume,
+    flags: Flags,
+    percent: u32,
+) {
     let idx = counter % VOICE_NOTES;
     let note = voice[idx];
     if note != XX {
-        wasm4::tone(note as u32, duration, volume, flags);
+        tone(note as u32, duration, volume, flags, percent);
     }
 }
 
-pub fn play(counter: usize, song_n: u8) {
+/// Plays the notes of `song_n` at `counter`, at `percent` of their volume.
+pub fn play(counter: usize, song_n: u8, percent: u32) {
     let song = SONGS[song_n as usize];
     for voice in song.iter().flatten() {
-        voice_player(counter, voice.0, voice.1, voice.2, voice.3);
+        voice_player(counter, voice.0, voice.1, voice.2, voice.3, percent);
     }
 }
 
@@ -103,6 +111,13 @@ const SONG2: Song = [
     None, //Some((T_2_0, 10, 60 | (10 << 8), TONE_TRIANGLE | TONE_MODE1)),
     Some((T_3_0B, 1 | (10 << 8), 40, TONE_NOISE | TONE_MODE3)),
 ];
+// Boss fights: a pounding bass under a siren-like lead
+const SONG_BOSS: Song = [
+    Some((T_0_2, 8, 40 | (10 << 8), TONE_PULSE1 | TONE_MODE2)),
+    None,
+    Some((T_2_3, 6, 70 | (10 << 8), TONE_TRIANGLE | TONE_MODE1)),
+    Some((T_3_1, 1 | (12 << 8), 50, TONE_NOISE | TONE_MODE3)),
+];
 // const SONG1_5: Song = [
 //     Some((T_4_0, 1 | (1 << 10), 50, TONE_TRIANGLE | TONE_MODE4)),
 //     Some((T_4_1, 20, 50, TONE_TRIANGLE | TONE_MODE4)),
@@ -110,7 +125,7 @@ const SONG2: Song = [
 //     None, //Some((T_3_0, 1 | (16 << 8), 40, TONE_NOISE | TONE_MODE3)),
 // ];
 #[rustfmt::skip]
-const SONGS: [Song; 9] = [
+const SONGS: [Song; 10] = [
     SONG2,
     SONG1_0,
     SONG1_1,
@@ -120,12 +135,14 @@ const SONGS: [Song; 9] = [
     SONG1_4B,
     SONG1_5,
     SONG1_6,
+    SONG_BOSS,
     SONG0,
 ];
 
 pub const INTRO_SONG: u8 = 0;
 pub const GAME_SONG_START: u8 = 1;
 pub const GAME_OVER_SONG: u8 = SONGS.len() as u8 - 1;
+pub const BOSS_SONG: u8 = GAME_OVER_SONG - 1;
 
 #[rustfmt::skip]
 const GAME_THEME: Voice = [
@@ -680,3 +697,114 @@ XX, XX, XX, XX,
 // Bar 16
 G3, XX, XX, XX,
 ];
+
+#[rustfmt::skip]
+const T_0_2: Voice = [
+
+// Bar 1
+E4, XX, XX, XX,
+// Bar 2
+G4, XX, E4, XX,
+// Bar 3
+Fd4_Gb4, XX, Dd4_Eb4, XX,
+// Bar 4
+E4, XX, XX, XX,
+// Bar 5
+E4, XX, XX, XX,
+// Bar 6
+G4, XX, B4, XX,
+// Bar 7
+A4, XX, G4, XX,
+// Bar 8
+Fd4_Gb4, XX, XX, XX,
+// Bar 9
+C5, XX, XX, XX,
+// Bar 10
+B4, XX, A4, XX,
+// Bar 11
+G4, XX, Fd4_Gb4, XX,
+// Bar 12
+E4, XX, XX, XX,
+// Bar 13
+Dd4_Eb4, XX, E4, XX,
+// Bar 14
+Fd4_Gb4, XX, G4, XX,
+// Bar 15
+Fd4_Gb4, XX, Dd4_Eb4, XX,
+// Bar 16
+B3, XX, XX, XX,
+];
+
+#[rustfmt::skip]
+const T_2_3: Voice = [
+
+// Bar 1
+E2, XX, E2, E3,
+// Bar 2
+E2, XX, E2, E3,
+// Bar 3
+E2, XX, E2, E3,
+// Bar 4
+E2, XX, E2, E3,
+// Bar 5
+C2, XX, C2, C3,
+// Bar 6
+C2, XX, C2, C3,
+// Bar 7
+D2, XX, D2, D3,
+// Bar 8
+D2, XX, D2, D3,
+// Bar 9
+A1, XX, A1, A2,
+// Bar 10
+A1, XX, A1, A2,
+// Bar 11
+C2, XX, C2, C3,
+// Bar 12
+C2, XX, C2, C3,
+// Bar 13
+B1, XX, B1, B2,
+// Bar 14
+B1, XX, B1, B2,
+// Bar 15
+B1, XX, B1, B2,
+// Bar 16
+B1, XX, B1, B2,
+];
+
+#[rustfmt::skip]
+const T_3_1: Voice = [
+
+// Bar 1
+A4, XX, G3, XX,
+// Bar 2
+A4, A4, G3, XX,
+// Bar 3
+A4, XX, G3, XX,
+// Bar 4
+A4, A4, G3, XX,
+// Bar 5
+A4, XX, G3, XX,
+// Bar 6
+A4, A4, G3, XX,
+// Bar 7
+A4, XX, G3, XX,
+// Bar 8
+A4, A4, G3, XX,
+// Bar 9
+A4, XX, G3, XX,
+// Bar 10
+A4, A4, G3, XX,
+// Bar 11
+A4, XX, G3, XX,
+// Bar 12
+A4, A4, G3, XX,
+// Bar 13
+A4, XX, G3, XX,
+// Bar 14
+A4, A4, G3, XX,
+// Bar 15
+A4, XX, G3, XX,
+// Bar 16
+A4, A4, G3, XX,
+];
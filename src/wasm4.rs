@@ -3,6 +3,18 @@
 
 #![allow(unused)]
 
+// Everything below is imported from the WASM-4 runtime. Native builds (tests,
+// tools) get an in-memory stand-in instead, see `host.rs`.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod host;
+#[cfg(not(target_arch = "wasm32"))]
+pub use host::{diskr, diskw};
+#[cfg(not(target_arch = "wasm32"))]
+use host::{
+    extern_blit, extern_blit_sub, extern_hline, extern_line, extern_oval, extern_rect, extern_text,
+    extern_tone, extern_trace, extern_vline, mem,
+};
+
 // ┌───────────────────────────────────────────────────────────────────────────┐
 // │                                                                           │
 // │ Platform Constants                                                        │
@@ -10,24 +22,31 @@
 // └───────────────────────────────────────────────────────────────────────────┘
 
 pub const SCREEN_SIZE: u32 = 160;
+pub const DISK_SIZE: usize = 1024;
 // ┌───────────────────────────────────────────────────────────────────────────┐
 // │                                                                           │
 // │ Memory Addresses                                                          │
 // │                                                                           │
 // └───────────────────────────────────────────────────────────────────────────┘
 
-pub const PALETTE: *mut [u32; 4] = 0x04 as *mut [u32; 4];
-pub const DRAW_COLORS: *mut u16 = 0x14 as *mut u16;
-pub const GAMEPAD1: *const u8 = 0x16 as *const u8;
-pub const GAMEPAD2: *const u8 = 0x17 as *const u8;
-pub const GAMEPAD3: *const u8 = 0x18 as *const u8;
-pub const GAMEPAD4: *const u8 = 0x19 as *const u8;
-pub const MOUSE_X: *const i16 = 0x1a as *const i16;
-pub const MOUSE_Y: *const i16 = 0x1c as *const i16;
-pub const MOUSE_BUTTONS: *const u8 = 0x1e as *const u8;
-pub const SYSTEM_FLAGS: *mut u8 = 0x1f as *mut u8;
-pub const NETPLAY: *const u8 = 0x20 as *const u8;
-pub const FRAMEBUFFER: *mut [u8; 6400] = 0xa0 as *mut [u8; 6400];
+pub const PALETTE: *mut [u32; 4] = mem(0x04);
+pub const DRAW_COLORS: *mut u16 = mem(0x14);
+pub const GAMEPAD1: *const u8 = mem(0x16);
+pub const GAMEPAD2: *const u8 = mem(0x17);
+pub const GAMEPAD3: *const u8 = mem(0x18);
+pub const GAMEPAD4: *const u8 = mem(0x19);
+pub const MOUSE_X: *const i16 = mem(0x1a);
+pub const MOUSE_Y: *const i16 = mem(0x1c);
+pub const MOUSE_BUTTONS: *const u8 = mem(0x1e);
+pub const SYSTEM_FLAGS: *mut u8 = mem(0x1f);
+pub const NETPLAY: *const u8 = mem(0x20);
+pub const FRAMEBUFFER: *mut [u8; 6400] = mem(0xa0);
+
+/// On the console the registers live at fixed addresses of the linear memory.
+#[cfg(target_arch = "wasm32")]
+const fn mem<T>(addr: usize) -> *mut T {
+    addr as *mut T
+}
 
 pub const BUTTON_1: u8 = 1;
 pub const BUTTON_2: u8 = 2;
@@ -53,6 +72,7 @@ pub const SYSTEM_HIDE_GAMEPAD_OVERLAY: u8 = 2;
 pub fn blit(sprite: &[u8], x: i32, y: i32, width: u32, height: u32, flags: u32) {
     unsafe { extern_blit(sprite.as_ptr(), x, y, width, height, flags) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "blit"]
     fn extern_blit(sprite: *const u8, x: i32, y: i32, width: u32, height: u32, flags: u32);
@@ -85,6 +105,7 @@ pub fn blit_sub(
         )
     }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "blitSub"]
     fn extern_blit_sub(
@@ -110,6 +131,7 @@ pub const BLIT_ROTATE: u32 = 8;
 pub fn line(x1: i32, y1: i32, x2: i32, y2: i32) {
     unsafe { extern_line(x1, y1, x2, y2) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "line"]
     fn extern_line(x1: i32, y1: i32, x2: i32, y2: i32);
@@ -119,6 +141,7 @@ extern "C" {
 pub fn oval(x: i32, y: i32, width: u32, height: u32) {
     unsafe { extern_oval(x, y, width, height) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "oval"]
     fn extern_oval(x: i32, y: i32, width: u32, height: u32);
@@ -128,6 +151,7 @@ extern "C" {
 pub fn rect(x: i32, y: i32, width: u32, height: u32) {
     unsafe { extern_rect(x, y, width, height) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "rect"]
     fn extern_rect(x: i32, y: i32, width: u32, height: u32);
@@ -138,6 +162,7 @@ pub fn text<T: AsRef<[u8]>>(text: T, x: i32, y: i32) {
     let text_ref = text.as_ref();
     unsafe { extern_text(text_ref.as_ptr(), text_ref.len(), x, y) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "textUtf8"]
     fn extern_text(text: *const u8, length: usize, x: i32, y: i32);
@@ -150,6 +175,7 @@ pub fn vline(x: i32, y: i32, len: u32) {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "vline"]
     fn extern_vline(x: i32, y: i32, len: u32);
@@ -162,6 +188,7 @@ pub fn hline(x: i32, y: i32, len: u32) {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "hline"]
     fn extern_hline(x: i32, y: i32, len: u32);
@@ -177,6 +204,7 @@ extern "C" {
 pub fn tone(frequency: u32, duration: u32, volume: u32, flags: u32) {
     unsafe { extern_tone(frequency, duration, volume, flags) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "tone"]
     fn extern_tone(frequency: u32, duration: u32, volume: u32, flags: u32);
@@ -199,6 +227,7 @@ pub const TONE_PAN_RIGHT: u32 = 32;
 // │                                                                           │
 // └───────────────────────────────────────────────────────────────────────────┘
 
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     /// Reads up to `size` bytes from persistent storage into the pointer `dest`.
     pub fn diskr(dest: *mut u8, size: u32) -> u32;
@@ -218,6 +247,7 @@ pub fn trace<T: AsRef<str>>(text: T) {
     let text_ref = text.as_ref();
     unsafe { extern_trace(text_ref.as_ptr(), text_ref.len()) }
 }
+#[cfg(target_arch = "wasm32")]
 extern "C" {
     #[link_name = "traceUtf8"]
     fn extern_trace(trace: *const u8, length: usize);
@@ -0,0 +1,320 @@
+// Native stand-in for the WASM-4 runtime.
+//
+// The cart only talks to the console through the registers and imports
+// declared in `wasm4.rs`. When building for anything other than wasm32 those
+// are backed by the state in this file: a block of memory laid out like the
+// first bytes of the WASM-4 linear memory (palette, draw colors, gamepads,
+// mouse, system flags, netplay and framebuffer), a 1024 bytes disk and a log
+// of every tone and trace emitted. Drawing functions are rasterized in
+// software (`raster.rs`). This lets tests drive `Game::update` frame by frame
+// on the host and look at what ends up on screen.
+//
+// The state is global, like on the console, so tests touching it must hold a
+// `Session` (see `lock`) to avoid stepping on each other.
+
+mod font;
+pub mod raster;
+
+use std::sync::{Mutex, MutexGuard};
+
+use super::{
+    BLIT_2BPP, DISK_SIZE, DRAW_COLORS, FRAMEBUFFER, GAMEPAD1, MOUSE_BUTTONS, MOUSE_X, MOUSE_Y,
+    NETPLAY, SYSTEM_FLAGS,
+};
+
+// Registers and framebuffer end at 0xa0 + 6400.
+const MEMORY_SIZE: usize = 0x19a0;
+
+#[repr(C, align(8))]
+struct Memory([u8; MEMORY_SIZE]);
+
+static mut MEMORY: Memory = Memory([0; MEMORY_SIZE]);
+
+/// Registers point into `MEMORY` at the same offsets used by the console.
+pub(super) const fn mem<T>(addr: usize) -> *mut T {
+    unsafe { (&raw mut MEMORY.0 as *mut u8).add(addr) as *mut T }
+}
+
+/// A call to `tone`, as it was received.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Tone {
+    pub frequency: u32,
+    pub duration: u32,
+    pub volume: u32,
+    pub flags: u32,
+}
+
+struct State {
+    disk: Vec<u8>,
+    tones: Vec<Tone>,
+    traces: Vec<String>,
+}
+
+impl State {
+    const fn new() -> Self {
+        Self {
+            disk: Vec::new(),
+            tones: Vec::new(),
+            traces: Vec::new(),
+        }
+    }
+}
+
+static STATE: Mutex<State> = Mutex::new(State::new());
+static SESSION: Mutex<()> = Mutex::new(());
+
+fn state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Exclusive access to the host console. Dropping it releases the console for
+/// the next user.
+pub struct Session {
+    _guard: MutexGuard<'static, ()>,
+}
+
+/// Waits for exclusive access to the host console and resets it to power-on
+/// state: default palette and draw colors, no input, empty disk and logs.
+pub fn lock() -> Session {
+    let guard = SESSION.lock().unwrap_or_else(|e| e.into_inner());
+    unsafe {
+        std::ptr::write_bytes(&raw mut MEMORY.0, 0, 1);
+        *super::PALETTE = [0xe0f8cf, 0x86c06c, 0x306850, 0x071821];
+        *super::DRAW_COLORS = 0x1203;
+    }
+    *state() = State::new();
+    Session { _guard: guard }
+}
+
+/// Mimics what the console does before calling `update`: unless the cart asked
+/// to preserve it, the framebuffer is cleared to the first palette color.
+pub fn begin_frame() {
+    unsafe {
+        if *SYSTEM_FLAGS & super::SYSTEM_PRESERVE_FRAMEBUFFER == 0 {
+            (*FRAMEBUFFER).fill(0);
+        }
+    }
+}
+
+/// Sets the raw state of gamepad `n` (0 to 3).
+pub fn set_gamepad(n: usize, buttons: u8) {
+    assert!(n < 4, "WASM-4 only has 4 gamepads");
+    unsafe { *(GAMEPAD1 as *mut u8).add(n) = buttons }
+}
+
+/// Sets the raw state of the mouse.
+pub fn set_mouse(x: i16, y: i16, buttons: u8) {
+    unsafe {
+        *(MOUSE_X as *mut i16) = x;
+        *(MOUSE_Y as *mut i16) = y;
+        *(MOUSE_BUTTONS as *mut u8) = buttons;
+    }
+}
+
+/// Sets the raw value of the netplay register.
+pub fn set_netplay(value: u8) {
+    unsafe { *(NETPLAY as *mut u8) = value }
+}
+
+/// Copy of the current framebuffer.
+pub fn framebuffer() -> [u8; 6400] {
+    unsafe { *FRAMEBUFFER }
+}
+
+/// Palette index (0 to 3) of the pixel at (x, y) of the current framebuffer.
+pub fn pixel(x: i32, y: i32) -> u8 {
+    unsafe { raster::get_pixel(&*FRAMEBUFFER, x, y) }
+}
+
+/// Current palette, as set by the cart.
+pub fn palette() -> [u32; 4] {
+    unsafe { *super::PALETTE }
+}
+
+/// Copy of the disk content, as last written by the cart.
+pub fn disk() -> Vec<u8> {
+    state().disk.clone()
+}
+
+/// Replaces the disk content, e.g. to start from a given save.
+pub fn set_disk(data: &[u8]) {
+    let len = data.len().min(DISK_SIZE);
+    state().disk = data[..len].to_vec();
+}
+
+/// Returns (and forgets) all the tones played since the last call.
+pub fn take_tones() -> Vec<Tone> {
+    std::mem::take(&mut state().tones)
+}
+
+/// Returns (and forgets) all the traces printed since the last call.
+pub fn take_traces() -> Vec<String> {
+    std::mem::take(&mut state().traces)
+}
+
+// ┌───────────────────────────────────────────────────────────────────────────┐
+// │                                                                           │
+// │ Runtime imports                                                           │
+// │                                                                           │
+// └───────────────────────────────────────────────────────────────────────────┘
+
+unsafe fn framebuffer_mut() -> &'static mut raster::Framebuffer {
+    &mut *FRAMEBUFFER
+}
+
+pub(super) unsafe fn extern_blit(
+    sprite: *const u8,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    flags: u32,
+) {
+    extern_blit_sub(sprite, x, y, width, height, 0, 0, width, flags);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) unsafe fn extern_blit_sub(
+    sprite: *const u8,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    src_x: u32,
+    src_y: u32,
+    stride: u32,
+    flags: u32,
+) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    // The sprite length isn't passed along, work it out from the last row used
+    let bpp = if flags & BLIT_2BPP != 0 { 2 } else { 1 };
+    let bits = ((src_y + height - 1) * stride + src_x + width) * bpp;
+    let sprite = std::slice::from_raw_parts(sprite, bits.div_ceil(8) as usize);
+    raster::blit_sub(
+        framebuffer_mut(),
+        *DRAW_COLORS,
+        sprite,
+        x,
+        y,
+        width,
+        height,
+        src_x,
+        src_y,
+        stride,
+        flags,
+    );
+}
+
+pub(super) unsafe fn extern_line(x1: i32, y1: i32, x2: i32, y2: i32) {
+    raster::line(framebuffer_mut(), *DRAW_COLORS, x1, y1, x2, y2);
+}
+
+pub(super) unsafe fn extern_oval(x: i32, y: i32, width: u32, height: u32) {
+    raster::oval(framebuffer_mut(), *DRAW_COLORS, x, y, width, height);
+}
+
+pub(super) unsafe fn extern_rect(x: i32, y: i32, width: u32, height: u32) {
+    raster::rect(framebuffer_mut(), *DRAW_COLORS, x, y, width, height);
+}
+
+pub(super) unsafe fn extern_text(text: *const u8, length: usize, x: i32, y: i32) {
+    let text = std::slice::from_raw_parts(text, length);
+    raster::text(framebuffer_mut(), *DRAW_COLORS, text, x, y);
+}
+
+pub(super) unsafe fn extern_vline(x: i32, y: i32, len: u32) {
+    raster::vline(framebuffer_mut(), *DRAW_COLORS, x, y, len);
+}
+
+pub(super) unsafe fn extern_hline(x: i32, y: i32, len: u32) {
+    raster::hline(framebuffer_mut(), *DRAW_COLORS, x, y, len);
+}
+
+pub(super) unsafe fn extern_tone(frequency: u32, duration: u32, volume: u32, flags: u32) {
+    state().tones.push(Tone {
+        frequency,
+        duration,
+        volume,
+        flags,
+    });
+}
+
+/// Reads up to `size` bytes from persistent storage into the pointer `dest`.
+///
+/// # Safety
+/// `dest` must be valid for `size` bytes of writes.
+pub unsafe fn diskr(dest: *mut u8, size: u32) -> u32 {
+    let state = state();
+    let len = state.disk.len().min(size as usize);
+    std::ptr::copy_nonoverlapping(state.disk.as_ptr(), dest, len);
+    len as u32
+}
+
+/// Writes up to `size` bytes from the pointer `src` into persistent storage.
+///
+/// # Safety
+/// `src` must be valid for `size` bytes of reads.
+pub unsafe fn diskw(src: *const u8, size: u32) -> u32 {
+    let len = (size as usize).min(DISK_SIZE);
+    state().disk = std::slice::from_raw_parts(src, len).to_vec();
+    len as u32
+}
+
+pub(super) unsafe fn extern_trace(trace: *const u8, length: usize) {
+    let bytes = std::slice::from_raw_parts(trace, length);
+    state()
+        .traces
+        .push(String::from_utf8_lossy(bytes).into_owned());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm4;
+
+    #[test]
+    fn disk_roundtrip() {
+        let _session = lock();
+        let data = [1u8, 2, 3, 4];
+        let mut read = [0u8; 8];
+        unsafe {
+            assert_eq!(wasm4::diskw(data.as_ptr(), data.len() as u32), 4);
+            assert_eq!(wasm4::diskr(read.as_mut_ptr(), read.len() as u32), 4);
+        }
+        assert_eq!(read[..4], data);
+        assert_eq!(disk(), data);
+    }
+
+    #[test]
+    fn registers() {
+        let _session = lock();
+        set_gamepad(2, wasm4::BUTTON_1);
+        set_mouse(-3, 170, wasm4::MOUSE_LEFT);
+        unsafe {
+            assert_eq!(*wasm4::GAMEPAD1, 0);
+            assert_eq!(*wasm4::GAMEPAD3, wasm4::BUTTON_1);
+            assert_eq!((*wasm4::MOUSE_X, *wasm4::MOUSE_Y), (-3, 170));
+            assert_eq!(*wasm4::MOUSE_BUTTONS, wasm4::MOUSE_LEFT);
+        }
+    }
+
+    #[test]
+    fn tone_and_trace_log() {
+        let _session = lock();
+        wasm4::tone(440, 10, 50, wasm4::TONE_PULSE1);
+        wasm4::trace("hello");
+        assert_eq!(
+            take_tones(),
+            [Tone {
+                frequency: 440,
+                duration: 10,
+                volume: 50,
+                flags: wasm4::TONE_PULSE1
+            }]
+        );
+        assert!(take_tones().is_empty());
+        assert_eq!(take_traces(), ["hello"]);
+    }
+}
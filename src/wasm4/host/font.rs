@@ -0,0 +1,119 @@
+// 8x8 font used by `text`, one byte per row, most significant bit on the
+// left. Like the console font, bits are inverted: a 0 is ink (draw color 1)
+// and a 1 is background (draw color 2).
+//
+// This is not a copy of the WASM-4 system font, glyphs are a plain 5x7 design
+// with the same metrics, so text lands on the same cells but doesn't look
+// exactly like on the console. It covers ASCII and the console's button and
+// arrow glyphs (0x80 to 0x87), other characters are blank.
+
+pub const FIRST_CHAR: u8 = 32;
+pub const LAST_CHAR: u8 = 0x87;
+
+#[rustfmt::skip]
+pub const FONT: [u8; ((LAST_CHAR - FIRST_CHAR) as usize + 1) * 8] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // ' '
+    0xef, 0xef, 0xef, 0xef, 0xef, 0xff, 0xef, 0xff, // '!'
+    0xd7, 0xd7, 0xd7, 0xff, 0xff, 0xff, 0xff, 0xff, // '"'
+    0xd7, 0xd7, 0x83, 0xd7, 0x83, 0xd7, 0xd7, 0xff, // '#'
+    0xef, 0xc3, 0xaf, 0xc7, 0xeb, 0x87, 0xef, 0xff, // '$'
+    0x9f, 0x9b, 0xf7, 0xef, 0xdf, 0xb3, 0xf3, 0xff, // '%'
+    0xcf, 0xb7, 0xaf, 0xdf, 0xab, 0xb7, 0xcb, 0xff, // '&'
+    0xef, 0xef, 0xdf, 0xff, 0xff, 0xff, 0xff, 0xff, // "'"
+    0xf7, 0xef, 0xdf, 0xdf, 0xdf, 0xef, 0xf7, 0xff, // '('
+    0xdf, 0xef, 0xf7, 0xf7, 0xf7, 0xef, 0xdf, 0xff, // ')'
+    0xff, 0xef, 0xab, 0xc7, 0xab, 0xef, 0xff, 0xff, // '*'
+    0xff, 0xef, 0xef, 0x83, 0xef, 0xef, 0xff, 0xff, // '+'
+    0xff, 0xff, 0xff, 0xff, 0xcf, 0xef, 0xdf, 0xff, // ','
+    0xff, 0xff, 0xff, 0x83, 0xff, 0xff, 0xff, 0xff, // '-'
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xcf, 0xcf, 0xff, // '.'
+    0xff, 0xfb, 0xf7, 0xef, 0xdf, 0xbf, 0xff, 0xff, // '/'
+    0xc7, 0xbb, 0xb3, 0xab, 0x9b, 0xbb, 0xc7, 0xff, // '0'
+    0xef, 0xcf, 0xef, 0xef, 0xef, 0xef, 0xc7, 0xff, // '1'
+    0xc7, 0xbb, 0xfb, 0xf7, 0xef, 0xdf, 0x83, 0xff, // '2'
+    0x83, 0xf7, 0xef, 0xf7, 0xfb, 0xbb, 0xc7, 0xff, // '3'
+    0xf7, 0xe7, 0xd7, 0xb7, 0x83, 0xf7, 0xf7, 0xff, // '4'
+    0x83, 0xbf, 0x87, 0xfb, 0xfb, 0xbb, 0xc7, 0xff, // '5'
+    0xe7, 0xdf, 0xbf, 0x87, 0xbb, 0xbb, 0xc7, 0xff, // '6'
+    0x83, 0xfb, 0xf7, 0xef, 0xdf, 0xdf, 0xdf, 0xff, // '7'
+    0xc7, 0xbb, 0xbb, 0xc7, 0xbb, 0xbb, 0xc7, 0xff, // '8'
+    0xc7, 0xbb, 0xbb, 0xc3, 0xfb, 0xf7, 0xcf, 0xff, // '9'
+    0xff, 0xcf, 0xcf, 0xff, 0xcf, 0xcf, 0xff, 0xff, // ':'
+    0xff, 0xcf, 0xcf, 0xff, 0xcf, 0xef, 0xdf, 0xff, // ';'
+    0xf7, 0xef, 0xdf, 0xbf, 0xdf, 0xef, 0xf7, 0xff, // '<'
+    0xff, 0xff, 0x83, 0xff, 0x83, 0xff, 0xff, 0xff, // '='
+    0xdf, 0xef, 0xf7, 0xfb, 0xf7, 0xef, 0xdf, 0xff, // '>'
+    0xc7, 0xbb, 0xfb, 0xf7, 0xef, 0xff, 0xef, 0xff, // '?'
+    0xc7, 0xbb, 0xfb, 0xcb, 0xab, 0xab, 0xc7, 0xff, // '@'
+    0xc7, 0xbb, 0xbb, 0x83, 0xbb, 0xbb, 0xbb, 0xff, // 'A'
+    0x87, 0xbb, 0xbb, 0x87, 0xbb, 0xbb, 0x87, 0xff, // 'B'
+    0xc7, 0xbb, 0xbf, 0xbf, 0xbf, 0xbb, 0xc7, 0xff, // 'C'
+    0x8f, 0xb7, 0xbb, 0xbb, 0xbb, 0xb7, 0x8f, 0xff, // 'D'
+    0x83, 0xbf, 0xbf, 0x87, 0xbf, 0xbf, 0x83, 0xff, // 'E'
+    0x83, 0xbf, 0xbf, 0x87, 0xbf, 0xbf, 0xbf, 0xff, // 'F'
+    0xc7, 0xbb, 0xbf, 0xa3, 0xbb, 0xbb, 0xc3, 0xff, // 'G'
+    0xbb, 0xbb, 0xbb, 0x83, 0xbb, 0xbb, 0xbb, 0xff, // 'H'
+    0xc7, 0xef, 0xef, 0xef, 0xef, 0xef, 0xc7, 0xff, // 'I'
+    0xe3, 0xf7, 0xf7, 0xf7, 0xf7, 0xb7, 0xcf, 0xff, // 'J'
+    0xbb, 0xb7, 0xaf, 0x9f, 0xaf, 0xb7, 0xbb, 0xff, // 'K'
+    0xbf, 0xbf, 0xbf, 0xbf, 0xbf, 0xbf, 0x83, 0xff, // 'L'
+    0xbb, 0x93, 0xab, 0xab, 0xbb, 0xbb, 0xbb, 0xff, // 'M'
+    0xbb, 0xbb, 0x9b, 0xab, 0xb3, 0xbb, 0xbb, 0xff, // 'N'
+    0xc7, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xc7, 0xff, // 'O'
+    0x87, 0xbb, 0xbb, 0x87, 0xbf, 0xbf, 0xbf, 0xff, // 'P'
+    0xc7, 0xbb, 0xbb, 0xbb, 0xab, 0xb7, 0xcb, 0xff, // 'Q'
+    0x87, 0xbb, 0xbb, 0x87, 0xaf, 0xb7, 0xbb, 0xff, // 'R'
+    0xc3, 0xbf, 0xbf, 0xc7, 0xfb, 0xfb, 0x87, 0xff, // 'S'
+    0x83, 0xef, 0xef, 0xef, 0xef, 0xef, 0xef, 0xff, // 'T'
+    0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xc7, 0xff, // 'U'
+    0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xd7, 0xef, 0xff, // 'V'
+    0xbb, 0xbb, 0xbb, 0xab, 0xab, 0xab, 0xd7, 0xff, // 'W'
+    0xbb, 0xbb, 0xd7, 0xef, 0xd7, 0xbb, 0xbb, 0xff, // 'X'
+    0xbb, 0xbb, 0xbb, 0xd7, 0xef, 0xef, 0xef, 0xff, // 'Y'
+    0x83, 0xfb, 0xf7, 0xef, 0xdf, 0xbf, 0x83, 0xff, // 'Z'
+    0xc7, 0xdf, 0xdf, 0xdf, 0xdf, 0xdf, 0xc7, 0xff, // '['
+    0xff, 0xbf, 0xdf, 0xef, 0xf7, 0xfb, 0xff, 0xff, // '\\'
+    0xc7, 0xf7, 0xf7, 0xf7, 0xf7, 0xf7, 0xc7, 0xff, // ']'
+    0xef, 0xd7, 0xbb, 0xff, 0xff, 0xff, 0xff, 0xff, // '^'
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x83, 0xff, // '_'
+    0xdf, 0xef, 0xf7, 0xff, 0xff, 0xff, 0xff, 0xff, // '`'
+    0xff, 0xff, 0xc7, 0xfb, 0xc3, 0xbb, 0xc3, 0xff, // 'a'
+    0xbf, 0xbf, 0xa7, 0x9b, 0xbb, 0xbb, 0x87, 0xff, // 'b'
+    0xff, 0xff, 0xc7, 0xbf, 0xbf, 0xbb, 0xc7, 0xff, // 'c'
+    0xfb, 0xfb, 0xcb, 0xb3, 0xbb, 0xbb, 0xc3, 0xff, // 'd'
+    0xff, 0xff, 0xc7, 0xbb, 0x83, 0xbf, 0xc7, 0xff, // 'e'
+    0xe7, 0xdb, 0xdf, 0x8f, 0xdf, 0xdf, 0xdf, 0xff, // 'f'
+    0xff, 0xc3, 0xbb, 0xbb, 0xc3, 0xfb, 0xc7, 0xff, // 'g'
+    0xbf, 0xbf, 0xa7, 0x9b, 0xbb, 0xbb, 0xbb, 0xff, // 'h'
+    0xef, 0xff, 0xcf, 0xef, 0xef, 0xef, 0xc7, 0xff, // 'i'
+    0xf7, 0xff, 0xe7, 0xf7, 0xf7, 0xb7, 0xcf, 0xff, // 'j'
+    0xbf, 0xbf, 0xb7, 0xaf, 0x9f, 0xaf, 0xb7, 0xff, // 'k'
+    0xcf, 0xef, 0xef, 0xef, 0xef, 0xef, 0xc7, 0xff, // 'l'
+    0xff, 0xff, 0x97, 0xab, 0xab, 0xbb, 0xbb, 0xff, // 'm'
+    0xff, 0xff, 0xa7, 0x9b, 0xbb, 0xbb, 0xbb, 0xff, // 'n'
+    0xff, 0xff, 0xc7, 0xbb, 0xbb, 0xbb, 0xc7, 0xff, // 'o'
+    0xff, 0xff, 0x87, 0xbb, 0x87, 0xbf, 0xbf, 0xff, // 'p'
+    0xff, 0xff, 0xcb, 0xb3, 0xc3, 0xfb, 0xfb, 0xff, // 'q'
+    0xff, 0xff, 0xa7, 0x9b, 0xbf, 0xbf, 0xbf, 0xff, // 'r'
+    0xff, 0xff, 0xc7, 0xbf, 0xc7, 0xfb, 0x87, 0xff, // 's'
+    0xdf, 0xdf, 0x8f, 0xdf, 0xdf, 0xdb, 0xe7, 0xff, // 't'
+    0xff, 0xff, 0xbb, 0xbb, 0xbb, 0xb3, 0xcb, 0xff, // 'u'
+    0xff, 0xff, 0xbb, 0xbb, 0xbb, 0xd7, 0xef, 0xff, // 'v'
+    0xff, 0xff, 0xbb, 0xbb, 0xab, 0xab, 0xd7, 0xff, // 'w'
+    0xff, 0xff, 0xbb, 0xd7, 0xef, 0xd7, 0xbb, 0xff, // 'x'
+    0xff, 0xff, 0xbb, 0xbb, 0xc3, 0xfb, 0xc7, 0xff, // 'y'
+    0xff, 0xff, 0x83, 0xf7, 0xef, 0xdf, 0x83, 0xff, // 'z'
+    0xf7, 0xef, 0xef, 0xdf, 0xef, 0xef, 0xf7, 0xff, // '{'
+    0xef, 0xef, 0xef, 0xef, 0xef, 0xef, 0xef, 0xff, // '|'
+    0xdf, 0xef, 0xef, 0xf7, 0xef, 0xef, 0xdf, 0xff, // '}'
+    0xff, 0xff, 0xdf, 0xab, 0xf7, 0xff, 0xff, 0xff, // '~'
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // \x7f
+    0xc3, 0xbd, 0x5a, 0x66, 0x66, 0x5a, 0xbd, 0xc3, // \x80
+    0xc3, 0xbd, 0x42, 0x7a, 0x76, 0x42, 0xbd, 0xc3, // \x81
+    0xc3, 0x81, 0x00, 0x00, 0x00, 0x00, 0x81, 0xc3, // \x82
+    0xc3, 0xbd, 0x7e, 0x7e, 0x7e, 0x7e, 0xbd, 0xc3, // \x83
+    0xef, 0xcf, 0x80, 0x00, 0x80, 0xcf, 0xef, 0xff, // \x84
+    0xf7, 0xf3, 0x01, 0x00, 0x01, 0xf3, 0xf7, 0xff, // \x85
+    0xef, 0xc7, 0x83, 0x01, 0xc7, 0xc7, 0xc7, 0xc7, // \x86
+    0xc7, 0xc7, 0xc7, 0xc7, 0x01, 0x83, 0xc7, 0xef, // \x87
+];
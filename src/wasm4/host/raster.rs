@@ -0,0 +1,418 @@
+// Software implementation of the WASM-4 drawing functions, writing into a 2bpp
+// framebuffer with the same layout `draw_utils::pixel` uses. The algorithms
+// follow the ones of the WASM-4 runtime so shapes come out pixel for pixel as
+// on the console (text excluded, see `font.rs`).
+//
+// Draw colors work as on the console: each nibble of DRAW_COLORS selects a
+// palette entry (1-4) or transparency (0).
+// - oval/rect: draw color 1 fills, draw color 2 outlines
+// - line/hline/vline: draw color 1
+// - text: draw color 1 for glyphs, draw color 2 for their background
+// - blit: sprite color N is drawn with draw color N+1
+
+use super::font::{FIRST_CHAR, FONT, LAST_CHAR};
+use crate::wasm4::{BLIT_2BPP, BLIT_FLIP_X, BLIT_FLIP_Y, BLIT_ROTATE, SCREEN_SIZE};
+
+pub type Framebuffer = [u8; 6400];
+
+const SIZE: i32 = SCREEN_SIZE as i32;
+
+/// Palette index picked by draw color `n` (0 to 3), `None` if transparent.
+fn draw_color(colors: u16, n: u16) -> Option<u8> {
+    match (colors >> (n * 4)) & 0xf {
+        0 => None,
+        dc => Some((dc as u8 - 1) & 0b11),
+    }
+}
+
+/// Palette index (0 to 3) of the pixel at (x, y).
+pub fn get_pixel(fb: &Framebuffer, x: i32, y: i32) -> u8 {
+    let idx = ((y * SIZE + x) as usize) >> 2;
+    let shift = (x as u8 & 0b11) << 1;
+    (fb[idx] >> shift) & 0b11
+}
+
+fn set_pixel(fb: &mut Framebuffer, x: i32, y: i32, color: u8) {
+    if !(0..SIZE).contains(&x) || !(0..SIZE).contains(&y) {
+        return;
+    }
+    let idx = ((y * SIZE + x) as usize) >> 2;
+    let shift = (x as u8 & 0b11) << 1;
+    let mask = 0b11 << shift;
+    fb[idx] = (color << shift) | (fb[idx] & !mask);
+}
+
+fn fill_hline(fb: &mut Framebuffer, color: u8, x: i32, y: i32, len: i32) {
+    for x in x..x + len {
+        set_pixel(fb, x, y, color);
+    }
+}
+
+fn fill_vline(fb: &mut Framebuffer, color: u8, x: i32, y: i32, len: i32) {
+    for y in y..y + len {
+        set_pixel(fb, x, y, color);
+    }
+}
+
+pub fn hline(fb: &mut Framebuffer, colors: u16, x: i32, y: i32, len: u32) {
+    if let Some(color) = draw_color(colors, 0) {
+        fill_hline(fb, color, x, y, len as i32);
+    }
+}
+
+pub fn vline(fb: &mut Framebuffer, colors: u16, x: i32, y: i32, len: u32) {
+    if let Some(color) = draw_color(colors, 0) {
+        fill_vline(fb, color, x, y, len as i32);
+    }
+}
+
+pub fn rect(fb: &mut Framebuffer, colors: u16, x: i32, y: i32, width: u32, height: u32) {
+    let (width, height) = (width as i32, height as i32);
+    if width <= 0 || height <= 0 {
+        return;
+    }
+    if let Some(fill) = draw_color(colors, 0) {
+        for y in y..y + height {
+            fill_hline(fb, fill, x, y, width);
+        }
+    }
+    if let Some(stroke) = draw_color(colors, 1) {
+        fill_hline(fb, stroke, x, y, width);
+        fill_hline(fb, stroke, x, y + height - 1, width);
+        fill_vline(fb, stroke, x, y, height);
+        fill_vline(fb, stroke, x + width - 1, y, height);
+    }
+}
+
+/// Bresenham line, both ends included.
+pub fn line(fb: &mut Framebuffer, colors: u16, x1: i32, y1: i32, x2: i32, y2: i32) {
+    let Some(color) = draw_color(colors, 0) else {
+        return;
+    };
+    let (mut x1, mut y1, x2, y2) = if y1 > y2 {
+        (x2, y2, x1, y1)
+    } else {
+        (x1, y1, x2, y2)
+    };
+    let dx = (x2 - x1).abs();
+    let sx = if x1 < x2 { 1 } else { -1 };
+    let dy = y2 - y1;
+    let mut err = if dx > dy { dx } else { -dy } / 2;
+    loop {
+        set_pixel(fb, x1, y1, color);
+        if x1 == x2 && y1 == y2 {
+            break;
+        }
+        let e2 = err;
+        if e2 > -dx {
+            err -= dy;
+            x1 += sx;
+        }
+        if e2 < dy {
+            err += dx;
+            y1 += 1;
+        }
+    }
+}
+
+/// Midpoint ellipse inscribed in the given rectangle (Alois Zingl's
+/// algorithm, as adapted by the WASM-4 runtime).
+pub fn oval(fb: &mut Framebuffer, colors: u16, x: i32, y: i32, width: u32, height: u32) {
+    let (width, height) = (width as i32, height as i32);
+    if width <= 0 || height <= 0 {
+        return;
+    }
+    let fill = draw_color(colors, 0);
+    let stroke = draw_color(colors, 1);
+    let mut point = |fb: &mut Framebuffer, x: i32, y: i32| {
+        if let Some(stroke) = stroke {
+            set_pixel(fb, x, y, stroke);
+        }
+    };
+
+    let mut a = (width - 1) as i64;
+    let b = (height - 1) as i64;
+    let mut b1 = b % 2;
+
+    let mut north = y + height / 2;
+    let mut west = x;
+    let mut east = x + width - 1;
+    let mut south = north - b1 as i32;
+
+    let mut dx = 4 * (1 - a) * b * b;
+    let mut dy = 4 * (b1 + 1) * a * a;
+    let mut err = dx + dy + b1 * a * a;
+
+    a = 8 * a * a;
+    b1 = 8 * b * b;
+
+    loop {
+        point(fb, east, north);
+        point(fb, west, north);
+        point(fb, west, south);
+        point(fb, east, south);
+
+        if let Some(fill) = fill {
+            let start = west + 1;
+            let len = east - start;
+            fill_hline(fb, fill, start, north, len);
+            fill_hline(fb, fill, start, south, len);
+        }
+
+        let err2 = 2 * err;
+        if err2 <= dy {
+            north += 1;
+            south -= 1;
+            dy += a;
+            err += dy;
+        }
+        if err2 >= dx || err2 > dy {
+            west += 1;
+            east -= 1;
+            dx += b1;
+            err += dx;
+        }
+        if west > east {
+            break;
+        }
+    }
+
+    // Flat ovals stop early, finish the tips
+    while north - south < height {
+        point(fb, west - 1, north);
+        point(fb, east + 1, north);
+        north += 1;
+        point(fb, west - 1, south);
+        point(fb, east + 1, south);
+        south -= 1;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn blit_sub(
+    fb: &mut Framebuffer,
+    colors: u16,
+    sprite: &[u8],
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    src_x: u32,
+    src_y: u32,
+    stride: u32,
+    flags: u32,
+) {
+    let (width, height) = (width as i32, height as i32);
+    let (src_x, src_y, stride) = (src_x as i32, src_y as i32, stride as i32);
+    let bpp2 = flags & BLIT_2BPP != 0;
+    let rotate = flags & BLIT_ROTATE != 0;
+    // Rotation is done by swapping axes, flip X to turn it counter-clockwise
+    let flip_x = (flags & BLIT_FLIP_X != 0) != rotate;
+    let flip_y = flags & BLIT_FLIP_Y != 0;
+
+    // Only visit the part of the sprite that lands on screen
+    let (min_x, min_y, max_x, max_y) = if rotate {
+        (
+            0.max(y) - y,
+            0.max(x) - x,
+            width.min(SIZE - y),
+            height.min(SIZE - x),
+        )
+    } else {
+        (
+            0.max(x) - x,
+            0.max(y) - y,
+            width.min(SIZE - x),
+            height.min(SIZE - y),
+        )
+    };
+
+    for row in min_y..max_y {
+        for col in min_x..max_x {
+            let (tx, ty) = if rotate {
+                (x + row, y + col)
+            } else {
+                (x + col, y + row)
+            };
+            let sx = src_x + if flip_x { width - col - 1 } else { col };
+            let sy = src_y + if flip_y { height - row - 1 } else { row };
+
+            let bit = (sy * stride + sx) as usize;
+            let color_idx = if bpp2 {
+                (sprite[bit >> 2] >> (6 - ((bit & 0b11) << 1))) & 0b11
+            } else {
+                (sprite[bit >> 3] >> (7 - (bit & 0b111))) & 0b1
+            };
+            if let Some(color) = draw_color(colors, color_idx as u16) {
+                set_pixel(fb, tx, ty, color);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn blit(
+    fb: &mut Framebuffer,
+    colors: u16,
+    sprite: &[u8],
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    flags: u32,
+) {
+    blit_sub(fb, colors, sprite, x, y, width, height, 0, 0, width, flags);
+}
+
+/// Text is blitted one 8x8 glyph at a time, `\n` starts a new line.
+pub fn text(fb: &mut Framebuffer, colors: u16, text: &[u8], x: i32, y: i32) {
+    let mut cursor_x = x;
+    let mut cursor_y = y;
+    for &c in text {
+        if c == b'\n' {
+            cursor_y += 8;
+            cursor_x = x;
+            continue;
+        }
+        if (FIRST_CHAR..=LAST_CHAR).contains(&c) {
+            let src_y = (c - FIRST_CHAR) as u32 * 8;
+            blit_sub(fb, colors, &FONT, cursor_x, cursor_y, 8, 8, 0, src_y, 8, 0);
+        }
+        cursor_x += 8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm4::{BLIT_1BPP, BLIT_2BPP};
+
+    fn count(fb: &Framebuffer, color: u8) -> usize {
+        (0..SIZE)
+            .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+            .filter(|&(x, y)| get_pixel(fb, x, y) == color)
+            .count()
+    }
+
+    #[test]
+    fn rect_fill_and_outline() {
+        let mut fb = [0; 6400];
+        rect(&mut fb, 0x32, 10, 10, 4, 3);
+        assert_eq!(count(&fb, 2), 10);
+        assert_eq!(count(&fb, 1), 2);
+        assert_eq!(get_pixel(&fb, 11, 11), 1);
+        assert_eq!(get_pixel(&fb, 13, 12), 2);
+
+        // Transparent outline, fill covers the whole rectangle
+        let mut fb = [0; 6400];
+        rect(&mut fb, 0x04, -2, -2, 4, 4);
+        assert_eq!(count(&fb, 3), 4);
+    }
+
+    #[test]
+    fn oval_is_symmetric() {
+        let mut fb = [0; 6400];
+        oval(&mut fb, 0x32, 20, 30, 7, 7);
+        for dy in 0..7 {
+            for dx in 0..7 {
+                assert_eq!(
+                    get_pixel(&fb, 20 + dx, 30 + dy),
+                    get_pixel(&fb, 26 - dx, 36 - dy)
+                );
+            }
+        }
+        // Corners are left untouched, center is filled, tips are outlined
+        assert_eq!(get_pixel(&fb, 20, 30), 0);
+        assert_eq!(get_pixel(&fb, 23, 33), 1);
+        assert_eq!(get_pixel(&fb, 23, 30), 2);
+        assert_eq!(get_pixel(&fb, 20, 33), 2);
+    }
+
+    #[test]
+    fn line_ends_included() {
+        let mut fb = [0; 6400];
+        line(&mut fb, 0x3, 5, 9, 0, 0);
+        assert_eq!(get_pixel(&fb, 0, 0), 2);
+        assert_eq!(get_pixel(&fb, 5, 9), 2);
+        assert_eq!(count(&fb, 2), 10);
+
+        hline(&mut fb, 0x4, 150, 0, 20);
+        vline(&mut fb, 0x4, 0, 155, 20);
+        assert_eq!(count(&fb, 3), 15);
+    }
+
+    #[test]
+    fn text_glyphs() {
+        let mut fb = [0; 6400];
+        text(&mut fb, 0x21, b"\x80\n!", 8, 0);
+        // Background of the cells
+        assert_eq!(get_pixel(&fb, 8, 0), 1);
+        // X icon center and the dot of the exclamation mark
+        assert_eq!(get_pixel(&fb, 11, 3), 0);
+        assert_eq!(get_pixel(&fb, 11, 14), 0);
+        assert_eq!(get_pixel(&fb, 11, 13), 1);
+        assert_eq!(count(&fb, 1) + count(&fb, 0), 160 * 160);
+        assert_eq!(count(&fb, 1), 128 - 28 - 6);
+    }
+
+    #[test]
+    fn blit_flags() {
+        // 2x2 sprite: one pixel per color
+        let sprite = [0b00_01_10_11];
+        let mut fb = [0; 6400];
+        blit(&mut fb, 0x4321, &sprite, 0, 0, 2, 2, BLIT_2BPP);
+        assert_eq!(
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| get_pixel(&fb, x, y)),
+            [0, 1, 2, 3]
+        );
+        blit(
+            &mut fb,
+            0x4321,
+            &sprite,
+            0,
+            0,
+            2,
+            2,
+            BLIT_2BPP | BLIT_FLIP_X,
+        );
+        assert_eq!(
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| get_pixel(&fb, x, y)),
+            [1, 0, 3, 2]
+        );
+        blit(
+            &mut fb,
+            0x4321,
+            &sprite,
+            0,
+            0,
+            2,
+            2,
+            BLIT_2BPP | BLIT_FLIP_Y,
+        );
+        assert_eq!(
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| get_pixel(&fb, x, y)),
+            [2, 3, 0, 1]
+        );
+        // Counter-clockwise quarter turn
+        blit(
+            &mut fb,
+            0x4321,
+            &sprite,
+            0,
+            0,
+            2,
+            2,
+            BLIT_2BPP | BLIT_ROTATE,
+        );
+        assert_eq!(
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| get_pixel(&fb, x, y)),
+            [1, 3, 0, 2]
+        );
+
+        // 1bpp with transparent background
+        let mut fb = [0; 6400];
+        blit(&mut fb, 0x20, &[0b1000_0001], 158, 0, 8, 1, BLIT_1BPP);
+        assert_eq!(get_pixel(&fb, 158, 0), 1);
+        assert_eq!(count(&fb, 1), 1);
+    }
+}